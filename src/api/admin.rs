@@ -2,79 +2,82 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
+    Json,
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    Json,
 };
+use firestore::path;
 use log::{error, info};
 use serde::Deserialize;
 use serde_json::json;
-use tokio_stream::StreamExt;
 
-use crate::common::dedup::{dedup_key, KeySkipReason, SubLike};
+use crate::common::dedup::{KeySkipReason, SubLike, dedup_key, question_dedup_keys};
 
 use crate::{
-    api::utils::response_handler,
+    api::{
+        answers::UserAnswer,
+        revision::{
+            ReasonQuery, RevisionContext, commit_revision_batch, commit_with_revision,
+            revision_writes,
+        },
+        utils::response_handler,
+    },
+    common::{
+        database::{Database, StoreQuery, WriteOp},
+        error::AppError,
+        quality::{RuleSet, Severity},
+        rich_text::canonicalize,
+    },
     models::{
         claim::AdminClaims,
         evaluate::Vote,
-        question::Question,
+        hint::HintUsage,
+        question::{Question, QuestionStatus},
+        review::ReviewCard,
+        revision::RevisionAction,
     },
 };
 
+/// (level_name, カテゴリキー -> (問題数, 小問数))
+type LevelCounts<K> = (String, HashMap<K, (usize, usize)>);
+
 #[derive(Deserialize)]
 pub struct QuestionPath {
     id: String,
 }
 
+/// parent_id ごとの (good, bad) に1票加算する
+fn count_vote(aggregation: &mut HashMap<String, (i64, i64)>, v: Vote) {
+    let entry = aggregation.entry(v.parent_id).or_insert((0, 0));
+    if v.vote == "good" {
+        entry.0 += 1;
+    } else if v.vote == "bad" {
+        entry.1 += 1;
+    }
+}
+
 /// GET /api/admin/votes/summary
 /// 投票の集計サマリーを返す
 pub async fn votes_summary(
     _admin: AdminClaims,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    // 全投票を取得
-    let votes = match db
-        .client
-        .fluent()
-        .list()
-        .from("votes")
-        .obj::<Vote>()
-        .stream_all()
-        .await
-    {
-        Ok(stream) => stream.collect::<Vec<Vote>>().await,
-        Err(e) => {
-            error!("Failed to fetch votes: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
-        }
-    };
-
-    let total_votes = votes.len();
-
-    // parent_id ごとに good/bad を集計
+) -> Result<impl IntoResponse, AppError> {
+    // 全投票をページ単位で読み、parent_id ごとに good/bad を集計
+    let mut total_votes = 0usize;
     let mut aggregation: HashMap<String, (i64, i64)> = HashMap::new();
-    for v in &votes {
-        let entry = aggregation.entry(v.parent_id.clone()).or_insert((0, 0));
-        if v.vote == "good" {
-            entry.0 += 1;
-        } else if v.vote == "bad" {
-            entry.1 += 1;
-        }
-    }
+    db.scan::<Vote, _>("votes", |v| {
+        total_votes += 1;
+        count_vote(&mut aggregation, v);
+    })
+    .await?;
 
     let total_questions_voted = aggregation.len();
     let good_count: i64 = aggregation.values().map(|(g, _)| g).sum();
     let bad_count: i64 = aggregation.values().map(|(_, b)| b).sum();
     let bad_questions_count = aggregation.values().filter(|(g, b)| b >= g).count();
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({
@@ -85,7 +88,7 @@ pub async fn votes_summary(
             "bad_questions_count": bad_questions_count,
         })),
         None,
-    )
+    ))
 }
 
 /// GET /api/admin/questions/bad
@@ -93,39 +96,11 @@ pub async fn votes_summary(
 pub async fn bad_questions(
     _admin: AdminClaims,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    // 全投票を取得
-    let votes = match db
-        .client
-        .fluent()
-        .list()
-        .from("votes")
-        .obj::<Vote>()
-        .stream_all()
-        .await
-    {
-        Ok(stream) => stream.collect::<Vec<Vote>>().await,
-        Err(e) => {
-            error!("Failed to fetch votes: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
-        }
-    };
-
-    // parent_id ごとに good/bad を集計
+) -> Result<impl IntoResponse, AppError> {
+    // 全投票をページ単位で読み、parent_id ごとに good/bad を集計
     let mut aggregation: HashMap<String, (i64, i64)> = HashMap::new();
-    for v in &votes {
-        let entry = aggregation.entry(v.parent_id.clone()).or_insert((0, 0));
-        if v.vote == "good" {
-            entry.0 += 1;
-        } else if v.vote == "bad" {
-            entry.1 += 1;
-        }
-    }
+    db.scan::<Vote, _>("votes", |v| count_vote(&mut aggregation, v))
+        .await?;
 
     // bad >= good の問題を抽出
     let mut bad_entries: Vec<(String, i64, i64)> = aggregation
@@ -135,20 +110,17 @@ pub async fn bad_questions(
         .collect();
 
     // bad_count 降順でソート
-    bad_entries.sort_by(|a, b| b.2.cmp(&a.2));
+    bad_entries.sort_by_key(|e| std::cmp::Reverse(e.2));
 
     // 各問題の詳細を取得
     let mut results = Vec::new();
     for (parent_id, good, bad) in &bad_entries {
-        let question = match db
-            .read::<Question>("questions", parent_id)
-            .await
-        {
+        let question = match db.read::<Question>("questions", parent_id).await {
             Ok(Some(q)) => json!(q),
             Ok(None) => json!({ "id": parent_id, "error": "question not found" }),
             Err(e) => {
                 error!("Failed to fetch question {}: {}", parent_id, e);
-                json!({ "id": parent_id, "error": e })
+                json!({ "id": parent_id, "error": e.code() })
             }
         };
 
@@ -167,12 +139,12 @@ pub async fn bad_questions(
         }));
     }
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!(results)),
         None,
-    )
+    ))
 }
 
 /// GET /api/admin/questions/{id}
@@ -181,55 +153,21 @@ pub async fn question_detail(
     _admin: AdminClaims,
     Path(path): Path<QuestionPath>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     // 問題を取得
-    let question = match db.read::<Question>("questions", &path.id).await {
-        Ok(Some(q)) => q,
-        Ok(None) => {
-            return response_handler(
-                StatusCode::NOT_FOUND,
-                "error".to_string(),
-                None,
-                Some("question not found".to_string()),
-            );
-        }
-        Err(e) => {
-            error!("Failed to fetch question: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e),
-            );
-        }
-    };
+    let question = db
+        .read::<Question>("questions", &path.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
 
     // この問題への投票を取得
-    let votes = match db
-        .client
-        .fluent()
-        .list()
-        .from("votes")
-        .obj::<Vote>()
-        .stream_all()
-        .await
-    {
-        Ok(stream) => stream.collect::<Vec<Vote>>().await,
-        Err(e) => {
-            error!("Failed to fetch votes: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
-        }
-    };
+    let query = StoreQuery::new().eq(path!(Vote::parent_id), &path.id);
+    let votes = db.query::<Vote>("votes", query).await?;
 
-    let good = votes.iter().filter(|v| v.parent_id == path.id && v.vote == "good").count();
-    let bad = votes.iter().filter(|v| v.parent_id == path.id && v.vote == "bad").count();
+    let good = votes.iter().filter(|v| v.vote == "good").count();
+    let bad = votes.iter().filter(|v| v.vote == "bad").count();
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({
@@ -240,125 +178,428 @@ pub async fn question_detail(
             }
         })),
         None,
-    )
+    ))
 }
 
-/// DELETE /api/admin/questions/{id}
-/// 問題を削除する（関連するuser_answersも連鎖削除）
+/// DELETE /api/admin/questions/{id}?reason=...
+/// 問題を削除する（関連するuser_answersも連鎖削除）。削除直前の内容は改訂履歴に残る
 pub async fn delete_question(
-    _admin: AdminClaims,
+    admin: AdminClaims,
     Path(path): Path<QuestionPath>,
+    Query(query): Query<ReasonQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    match db.delete("questions", &path.id).await {
-        Ok(_) => {
-            // 関連するuser_answersを非同期で連鎖削除
-            let db_clone = db.clone();
-            let qid = path.id.clone();
-            tokio::spawn(async move {
-                use tokio_stream::StreamExt;
-                if let Ok(mut stream) = db_clone
-                    .client
-                    .fluent()
-                    .select()
-                    .from("user_answers")
-                    .filter(|q| {
-                        q.field(firestore::path!(super::answers::UserAnswer::question_id)).eq(qid.clone())
-                    })
-                    .obj::<super::answers::UserAnswer>()
-                    .stream_query_with_errors()
-                    .await
-                {
-                    let mut cleaned = 0usize;
-                    while let Some(Ok(answer)) = stream.next().await {
-                        if let Ok(_) = db_clone.delete("user_answers", &answer.id).await {
-                            cleaned += 1;
-                        }
-                    }
-                    if cleaned > 0 {
-                        info!("問題削除に伴い user_answers {}件を連鎖削除", cleaned);
-                    }
-                }
-            });
+) -> Result<impl IntoResponse, AppError> {
+    let ctx = RevisionContext::new(&admin, query);
+    delete_with_revision(&db, &ctx, &path.id).await?;
 
-            response_handler(
-                StatusCode::OK,
-                "success".to_string(),
-                Some(json!({ "deleted": path.id })),
-                None,
-            )
-        }
-        Err(e) => {
-            error!("Failed to delete question: {}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e),
+    // 関連するuser_answersを非同期で連鎖削除
+    tokio::spawn(cascade_delete_learner_records(
+        db.clone(),
+        vec![path.id.clone()],
+    ));
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({ "deleted": path.id })),
+        None,
+    ))
+}
+
+/// 問題を削除し、存在していれば `delete` の版を記録する
+pub(crate) async fn delete_with_revision(
+    db: &Database,
+    ctx: &RevisionContext,
+    id: &str,
+) -> Result<(), AppError> {
+    match db.read::<Question>("questions", id).await? {
+        Some(question) => {
+            commit_with_revision(
+                db,
+                ctx,
+                RevisionAction::Delete,
+                Some(&question),
+                &question,
+                WriteOp::delete("questions", id),
             )
+            .await?;
         }
+        None => db.delete("questions", id).await?,
     }
+    Ok(())
 }
 
-/// GET /api/admin/stats
-/// レベル・カテゴリごとの統計情報を返す
-pub async fn stats(
-    _admin: AdminClaims,
+/// `POST /api/admin/questions` の本文。`status` は問題の本体と分けて受け取る
+/// (Question の `status` は既存のドキュメントに合わせて `published` が既定のため)
+#[derive(Deserialize)]
+pub struct CreateQuestionBody {
+    #[serde(flatten)]
+    question: Question,
+    #[serde(default)]
+    status: Option<QuestionStatus>,
+}
+
+/// POST /api/admin/questions?reason=...
+/// 問題を作成する。`id` 省略時は UUID を採番する。
+/// `status` は `draft` (省略時) / `pending_review` / `published` から選べる。
+/// 公開はレビューの承認 (`/approve`) を通すのが原則で、`published` の直接指定は移行用
+pub async fn create_question(
+    admin: AdminClaims,
+    Query(query): Query<ReasonQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    // 全問題を取得
-    let questions: Vec<Question> = match db
-        .client
-        .fluent()
-        .list()
-        .from("questions")
-        .obj::<Question>()
-        .stream_all()
-        .await
+    Json(body): Json<CreateQuestionBody>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut question = body.question;
+    question.status = body.status.unwrap_or(QuestionStatus::Draft);
+    if question.id.is_empty() {
+        question.id = uuid::Uuid::new_v4().to_string();
+    }
+    if db
+        .read::<Question>("questions", &question.id)
+        .await?
+        .is_some()
     {
-        Ok(stream) => stream.collect::<Vec<Question>>().await,
-        Err(e) => {
-            error!("Failed to fetch questions: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
+        return Err(AppError::Conflict(format!(
+            "問題IDが既に存在します: {}",
+            question.id
+        )));
+    }
+    if question.status == QuestionStatus::Retired {
+        return Err(AppError::Validation(
+            "retired の問題は作成できません".to_string(),
+        ));
+    }
+    question.review = None;
+    normalize_question(&mut question);
+    validate_question(&db, &question).await?;
+
+    let ctx = RevisionContext::new(&admin, query);
+    commit_with_revision(
+        &db,
+        &ctx,
+        RevisionAction::Create,
+        None,
+        &question,
+        WriteOp::create("questions", &question.id, &question)?,
+    )
+    .await?;
+    info!("問題を作成: {} (by {})", question.id, ctx.editor);
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(question)),
+        None,
+    ))
+}
+
+/// PUT /api/admin/questions/{id}?reason=...
+/// 問題を丸ごと置き換える。本文の `id` は無視してパスの ID を使う
+pub async fn replace_question(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    Query(query): Query<ReasonQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+    Json(mut question): Json<Question>,
+) -> Result<impl IntoResponse, AppError> {
+    let previous = db
+        .read::<Question>("questions", &path.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+
+    question.id = path.id;
+    let ctx = RevisionContext::new(&admin, query);
+    save_question(&db, &ctx, &previous, question).await
+}
+
+/// PATCH /api/admin/questions/{id}?reason=...
+/// JSON Merge Patch (RFC 7386) で部分更新する。配列 (`sub_questions` など) は丸ごと置き換わる
+pub async fn patch_question(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    Query(query): Query<ReasonQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+    Json(patch): Json<serde_json::Value>,
+) -> Result<impl IntoResponse, AppError> {
+    let current = db
+        .read::<serde_json::Value>("questions", &path.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+    let previous: Question = serde_json::from_value(current.clone())
+        .map_err(|e| AppError::Internal(format!("問題の読み込みに失敗しました: {}", e)))?;
+
+    let mut merged = current;
+    merge_patch(&mut merged, &patch);
+    let mut question: Question = serde_json::from_value(merged)
+        .map_err(|e| AppError::Validation(format!("問題の形式が不正です: {}", e)))?;
+    question.id = path.id;
+    let ctx = RevisionContext::new(&admin, query);
+    save_question(&db, &ctx, &previous, question).await
+}
+
+/// 検証して既存の問題を上書きし、`update` の版を記録する。
+/// 状態とレビューの記録はライフサイクルのエンドポイントでのみ変わるため、変更前の値を引き継ぐ
+async fn save_question(
+    db: &Database,
+    ctx: &RevisionContext,
+    previous: &Question,
+    mut question: Question,
+) -> Result<axum::response::Response, AppError> {
+    question.status = previous.status;
+    question.review = previous.review.clone();
+    normalize_question(&mut question);
+    validate_question(db, &question).await?;
+    commit_with_revision(
+        db,
+        ctx,
+        RevisionAction::Update,
+        Some(previous),
+        &question,
+        WriteOp::upsert("questions", &question.id, &question)?,
+    )
+    .await?;
+    info!("問題を更新: {} (by {})", question.id, ctx.editor);
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(question)),
+        None,
+    )
+    .into_response())
+}
+
+/// `level_name` を `level_id` から補完し、文字装飾を正規の記法に揃える
+pub(crate) fn normalize_question(question: &mut Question) {
+    if question.level_name.is_empty() {
+        question.level_name = format!("N{}", question.level_id);
+    }
+    canonicalize_markup(question);
+    question.dedup_keys = question_dedup_keys(question);
+}
+
+/// 問題文・本文・小問・選択肢・断片の記法を正規の記法に揃える。変わったら true
+pub(crate) fn canonicalize_markup(question: &mut Question) -> bool {
+    fn apply(s: &mut String, changed: &mut bool) {
+        let canonical = canonicalize(s);
+        if canonical != *s {
+            *s = canonical;
+            *changed = true;
         }
-    };
+    }
 
-    // 全投票を取得
-    let votes: Vec<Vote> = match db
-        .client
-        .fluent()
-        .list()
-        .from("votes")
-        .obj::<Vote>()
-        .stream_all()
-        .await
-    {
-        Ok(stream) => stream.collect::<Vec<Vote>>().await,
-        Err(e) => {
-            error!("Failed to fetch votes: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
+    let mut changed = false;
+    apply(&mut question.sentence, &mut changed);
+    if let Some(passage) = question.passage.as_mut() {
+        apply(passage, &mut changed);
+    }
+    for sub_q in &mut question.sub_questions {
+        if let Some(sentence) = sub_q.sentence.as_mut() {
+            apply(sentence, &mut changed);
         }
+        for sa in &mut sub_q.select_answer {
+            apply(&mut sa.value, &mut changed);
+        }
+        if let Some(ordering) = sub_q.ordering.as_mut() {
+            for fragment in &mut ordering.fragments {
+                apply(fragment, &mut changed);
+            }
+        }
+    }
+    changed
+}
+
+/// 品質監視と同じ品質規則と、dedup キーの重複チェックを行う。
+///
+/// 構造の不備は 400、既存の小問 (または同じ問題内の別の小問) との重複は 409。
+pub(crate) async fn validate_question(db: &Database, question: &Question) -> Result<(), AppError> {
+    let rules = RuleSet::load(db).await?;
+    let keys = check_structure(question, &rules)?;
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    if let Some((sub_id, other_id, other_sub)) = find_duplicate(db, &question.id, &keys).await? {
+        return Err(AppError::Conflict(format!(
+            "小問{}が既存の問題と重複しています: {} (小問{})",
+            sub_id, other_id, other_sub
+        )));
+    }
+    Ok(())
+}
+
+/// `contains_any` 1回で照合する dedup キーの数 (Firestore の上限)
+const DEDUP_QUERY_CHUNK: usize = 30;
+
+/// [`check_structure`] が返したキーを保存済みの問題の `dedup_keys` から引き、
+/// 最初に重複する小問を `(小問ID, 重複先の問題ID, 重複先の小問ID)` で返す。`question_id` 自身は除く
+pub(crate) async fn find_duplicate(
+    db: &Database,
+    question_id: &str,
+    keys: &HashMap<String, u32>,
+) -> Result<Option<(u32, String, u32)>, AppError> {
+    // 報告を安定させるため小問ID順に照合する
+    let mut keys: Vec<(&String, &u32)> = keys.iter().collect();
+    keys.sort_by_key(|(_, sub_id)| **sub_id);
+
+    let mut found: HashMap<String, (String, u32)> = HashMap::new();
+    for chunk in keys.chunks(DEDUP_QUERY_CHUNK) {
+        let values: Vec<&String> = chunk.iter().map(|(key, _)| *key).collect();
+        let query = StoreQuery::new().contains_any(path!(Question::dedup_keys), &values);
+        for other in db.query::<Question>("questions", query).await? {
+            if other.id == question_id {
+                continue;
+            }
+            for sub_q in &other.sub_questions {
+                if let Ok(key) = dedup_key(other.level_id, &SubLike::from(sub_q))
+                    && values.contains(&&key)
+                {
+                    found
+                        .entry(key)
+                        .or_insert_with(|| (other.id.clone(), sub_q.id));
+                }
+            }
+        }
+    }
+    Ok(keys.into_iter().find_map(|(key, sub_id)| {
+        found
+            .remove(key)
+            .map(|(other_id, other_sub)| (*sub_id, other_id, other_sub))
+    }))
+}
+
+/// DB を参照しない検証 (品質規則と、同じ問題内の小問どうしの重複)。
+///
+/// 隔離 (quarantine) 以上の規則に反する場合は受け付けない。警告だけの規則は確かめない。
+/// 成功時は dedup キー -> 小問ID を返す。キーを作れない小問 (数字だけの選択肢等) は含まない。
+pub(crate) fn check_structure(
+    question: &Question,
+    rules: &RuleSet,
+) -> Result<HashMap<String, u32>, AppError> {
+    if !(1..=5).contains(&question.level_id) {
+        return Err(AppError::Validation(
+            "level_id は 1〜5 で指定してください".to_string(),
+        ));
+    }
+    if question.sub_questions.is_empty() {
+        return Err(AppError::Validation("sub_questions が空です".to_string()));
+    }
+
+    let mut sub_ids = std::collections::HashSet::new();
+    let mut issues: Vec<String> = question
+        .sub_questions
+        .iter()
+        .filter(|sub_q| !sub_ids.insert(sub_q.id))
+        .map(|sub_q| format!("小問{}: IDが重複", sub_q.id))
+        .collect();
+    issues.extend(
+        rules
+            .check(question, Severity::Quarantine)
+            .iter()
+            .map(|finding| finding.to_string()),
+    );
+    if !issues.is_empty() {
+        return Err(AppError::Validation(format!(
+            "問題の形式が不正です: {}",
+            issues.join(", ")
+        )));
+    }
+
+    let mut keys: HashMap<String, u32> = HashMap::new();
+    for sub_q in &question.sub_questions {
+        if let Ok(key) = dedup_key(question.level_id, &SubLike::from(sub_q))
+            && let Some(other) = keys.insert(key, sub_q.id)
+        {
+            return Err(AppError::Conflict(format!(
+                "小問{}と小問{}が重複しています",
+                other, sub_q.id
+            )));
+        }
+    }
+    Ok(keys)
+}
+
+/// JSON Merge Patch (RFC 7386) を適用する
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
     };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    if let serde_json::Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(json!(null)), value);
+            }
+        }
+    }
+}
 
-    // question_id -> level_id のマッピングを作成
-    let mut question_level: HashMap<String, u32> = HashMap::new();
-    for q in &questions {
-        question_level.insert(q.id.clone(), q.level_id);
+/// 削除した問題に紐づく user_answers・review_cards・hint_usages を削除する
+pub(crate) async fn cascade_delete_learner_records(db: Arc<Database>, question_ids: Vec<String>) {
+    let mut cleaned = 0usize;
+    let mut cleaned_cards = 0usize;
+    for qid in &question_ids {
+        let query = StoreQuery::new().eq(path!(UserAnswer::question_id), qid);
+        if let Ok(answers) = db.query::<UserAnswer>("user_answers", query).await {
+            for answer in answers {
+                if db.delete("user_answers", &answer.id).await.is_ok() {
+                    cleaned += 1;
+                }
+            }
+        }
+        let query = StoreQuery::new().eq(path!(ReviewCard::question_id), qid);
+        if let Ok(cards) = db.query::<ReviewCard>("review_cards", query).await {
+            for card in cards {
+                if db.delete("review_cards", &card.id).await.is_ok() {
+                    cleaned_cards += 1;
+                }
+            }
+        }
+        let query = StoreQuery::new().eq(path!(HintUsage::question_id), qid);
+        if let Ok(usages) = db.query::<HintUsage>("hint_usages", query).await {
+            for usage in usages {
+                let _ = db.delete("hint_usages", &usage.id).await;
+            }
+        }
+    }
+    if cleaned > 0 || cleaned_cards > 0 {
+        info!(
+            "問題削除に伴い user_answers {}件・review_cards {}件を連鎖削除",
+            cleaned, cleaned_cards
+        );
     }
+}
+
+/// GET /api/admin/stats
+/// レベル・カテゴリごとの統計情報を返す
+pub async fn stats(
+    _admin: AdminClaims,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    // 全問題をページ単位で読み、レベル・カテゴリごとに集計
+    // question_id -> level_id のマッピングも合わせて作成
+    // key: level_id -> (level_name, HashMap<category_name, (questions, sub_questions)>)
+    let mut question_level: HashMap<String, u32> = HashMap::new();
+    let mut level_map: HashMap<u32, LevelCounts<String>> = HashMap::new();
+    db.scan::<Question, _>("questions", |q| {
+        question_level.insert(q.id, q.level_id);
+        let entry = level_map
+            .entry(q.level_id)
+            .or_insert_with(|| (q.level_name, HashMap::new()));
+        let cat = entry.1.entry(q.category_name).or_insert((0, 0));
+        cat.0 += 1;
+        cat.1 += q.sub_questions.len();
+    })
+    .await?;
 
     // レベルごとの投票集計
     let mut level_votes: HashMap<u32, (i64, i64)> = HashMap::new();
-    for v in &votes {
+    db.scan::<Vote, _>("votes", |v| {
         if let Some(&lid) = question_level.get(&v.parent_id) {
             let entry = level_votes.entry(lid).or_insert((0, 0));
             if v.vote == "good" {
@@ -367,19 +608,8 @@ pub async fn stats(
                 entry.1 += 1;
             }
         }
-    }
-
-    // レベル・カテゴリごとに集計
-    // key: level_id -> (level_name, HashMap<category_name, (questions, sub_questions)>)
-    let mut level_map: HashMap<u32, (String, HashMap<String, (usize, usize)>)> = HashMap::new();
-    for q in &questions {
-        let entry = level_map
-            .entry(q.level_id)
-            .or_insert_with(|| (q.level_name.clone(), HashMap::new()));
-        let cat = entry.1.entry(q.category_name.clone()).or_insert((0, 0));
-        cat.0 += 1;
-        cat.1 += q.sub_questions.len();
-    }
+    })
+    .await?;
 
     // レスポンス構築
     let mut levels: Vec<serde_json::Value> = level_map
@@ -400,7 +630,10 @@ pub async fn stats(
                 })
                 .collect();
             cats.sort_by(|a, b| {
-                a["name"].as_str().unwrap_or("").cmp(b["name"].as_str().unwrap_or(""))
+                a["name"]
+                    .as_str()
+                    .unwrap_or("")
+                    .cmp(b["name"].as_str().unwrap_or(""))
             });
 
             json!({
@@ -416,15 +649,18 @@ pub async fn stats(
         .collect();
 
     levels.sort_by(|a, b| {
-        a["level_id"].as_u64().unwrap_or(0).cmp(&b["level_id"].as_u64().unwrap_or(0))
+        a["level_id"]
+            .as_u64()
+            .unwrap_or(0)
+            .cmp(&b["level_id"].as_u64().unwrap_or(0))
     });
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({ "levels": levels })),
         None,
-    )
+    ))
 }
 
 /// GET /api/admin/coverage-stats
@@ -432,45 +668,20 @@ pub async fn stats(
 pub async fn coverage_stats(
     _admin: AdminClaims,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    // 全問題を取得
-    let questions: Vec<Question> = match db
-        .client
-        .fluent()
-        .list()
-        .from("questions")
-        .obj::<Question>()
-        .stream_all()
-        .await
-    {
-        Ok(stream) => stream.collect::<Vec<Question>>().await,
-        Err(e) => {
-            error!("Failed to fetch questions: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e.to_string()),
-            );
-        }
-    };
-
-    // レベル・カテゴリごとに集計
+) -> Result<impl IntoResponse, AppError> {
+    // 全問題をページ単位で読み、レベル・カテゴリごとに集計
     // key: (level_id) -> (level_name, HashMap<(category_id, category_name), (question_count, sub_question_count)>)
-    let mut level_map: HashMap<u32, (String, HashMap<(String, String), (usize, usize)>)> =
-        HashMap::new();
-    for q in &questions {
+    let mut level_map: HashMap<u32, LevelCounts<(String, String)>> = HashMap::new();
+    db.scan::<Question, _>("questions", |q| {
         let entry = level_map
             .entry(q.level_id)
-            .or_insert_with(|| (q.level_name.clone(), HashMap::new()));
-        let cat_id = q.category_id.clone().unwrap_or_default();
-        let cat = entry
-            .1
-            .entry((cat_id, q.category_name.clone()))
-            .or_insert((0, 0));
+            .or_insert_with(|| (q.level_name, HashMap::new()));
+        let cat_id = q.category_id.unwrap_or_default();
+        let cat = entry.1.entry((cat_id, q.category_name)).or_insert((0, 0));
         cat.0 += 1;
         cat.1 += q.sub_questions.len();
-    }
+    })
+    .await?;
 
     // ターゲット決定関数
     fn determine_target(category_name: &str) -> usize {
@@ -497,22 +708,24 @@ pub async fn coverage_stats(
 
             let mut cats: Vec<serde_json::Value> = categories
                 .into_iter()
-                .map(|((cat_id, cat_name), (question_count, sub_question_count))| {
-                    let target = determine_target(&cat_name);
-                    let coverage_pct = if target > 0 {
-                        (sub_question_count as f64 / target as f64) * 100.0
-                    } else {
-                        0.0
-                    };
-                    json!({
-                        "category_id": cat_id,
-                        "category_name": cat_name,
-                        "question_count": question_count,
-                        "sub_question_count": sub_question_count,
-                        "target": target,
-                        "coverage_pct": (coverage_pct * 10.0).round() / 10.0,
-                    })
-                })
+                .map(
+                    |((cat_id, cat_name), (question_count, sub_question_count))| {
+                        let target = determine_target(&cat_name);
+                        let coverage_pct = if target > 0 {
+                            (sub_question_count as f64 / target as f64) * 100.0
+                        } else {
+                            0.0
+                        };
+                        json!({
+                            "category_id": cat_id,
+                            "category_name": cat_name,
+                            "question_count": question_count,
+                            "sub_question_count": sub_question_count,
+                            "target": target,
+                            "coverage_pct": (coverage_pct * 10.0).round() / 10.0,
+                        })
+                    },
+                )
                 .collect();
             cats.sort_by(|a, b| {
                 a["category_id"]
@@ -537,12 +750,12 @@ pub async fn coverage_stats(
             .cmp(&b["level_id"].as_u64().unwrap_or(0))
     });
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({ "levels": levels })),
         None,
-    )
+    ))
 }
 
 #[derive(Deserialize)]
@@ -550,18 +763,20 @@ pub struct BulkDeleteRequest {
     ids: Vec<String>,
 }
 
-/// POST /api/admin/questions/bulk-delete
+/// POST /api/admin/questions/bulk-delete?reason=...
 /// 問題を一括削除する（関連するuser_answersも連鎖削除）
 pub async fn bulk_delete(
-    _admin: AdminClaims,
+    admin: AdminClaims,
+    Query(query): Query<ReasonQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
     Json(body): Json<BulkDeleteRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let mut deleted: usize = 0;
     let mut failed: usize = 0;
 
+    let ctx = RevisionContext::new(&admin, query);
     for id in &body.ids {
-        match db.delete("questions", id).await {
+        match delete_with_revision(&db, &ctx, id).await {
             Ok(_) => deleted += 1,
             Err(e) => {
                 error!("Failed to delete question {}: {}", id, e);
@@ -571,45 +786,149 @@ pub async fn bulk_delete(
     }
 
     // 関連するuser_answersを非同期で連鎖削除
-    let db_clone = db.clone();
-    let question_ids = body.ids.clone();
-    tokio::spawn(async move {
-        use tokio_stream::StreamExt;
-        let mut cleaned = 0usize;
-        for qid in &question_ids {
-            if let Ok(mut stream) = db_clone
-                .client
-                .fluent()
-                .select()
-                .from("user_answers")
-                .filter(|q| {
-                    q.field(firestore::path!(super::answers::UserAnswer::question_id)).eq(qid.clone())
-                })
-                .obj::<super::answers::UserAnswer>()
-                .stream_query_with_errors()
-                .await
-            {
-                while let Some(Ok(answer)) = stream.next().await {
-                    if let Ok(_) = db_clone.delete("user_answers", &answer.id).await {
-                        cleaned += 1;
+    tokio::spawn(cascade_delete_learner_records(db.clone(), body.ids.clone()));
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "deleted": deleted,
+            "failed": failed,
+        })),
+        None,
+    ))
+}
+
+/// 記法の移行を1トランザクションにまとめる問題数
+const NORMALIZE_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct NormalizeMarkupQuery {
+    #[serde(default)]
+    dry_run: bool,
+    reason: Option<String>,
+}
+
+/// POST /api/admin/questions/normalize-markup?dry_run=true&reason=...
+/// 保存済みの問題の文字装飾 (旧来の空欄・欄の表記、漢字だけのルビ等) を正規の記法に揃える。
+/// 変わった問題は `update` の版を記録する
+pub async fn normalize_markup(
+    admin: AdminClaims,
+    Query(query): Query<NormalizeMarkupQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut changed: Vec<(Question, Question)> = Vec::new();
+    db.scan::<Question, _>("questions", |q| {
+        let mut updated = q.clone();
+        if canonicalize_markup(&mut updated) {
+            updated.dedup_keys = question_dedup_keys(&updated);
+            changed.push((q, updated));
+        }
+    })
+    .await?;
+
+    let question_ids: Vec<&str> = changed.iter().map(|(_, q)| q.id.as_str()).collect();
+    let mut committed = 0;
+    if !query.dry_run {
+        let ctx = RevisionContext::new(
+            &admin,
+            ReasonQuery {
+                reason: query.reason,
+            },
+        );
+        for batch in changed.chunks(NORMALIZE_BATCH_SIZE) {
+            let mut writes = Vec::with_capacity(batch.len() * 2);
+            for (previous, question) in batch {
+                writes.push(WriteOp::upsert("questions", &question.id, question)?);
+                let (revisions, _) =
+                    revision_writes(&db, &ctx, RevisionAction::Update, Some(previous), question)
+                        .await?;
+                writes.extend(revisions);
+            }
+            commit_revision_batch(&db, writes).await?;
+            committed += batch.len();
+        }
+        info!("問題の記法を移行: {}件 (by {})", committed, ctx.editor);
+    }
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "dry_run": query.dry_run,
+            "changed": question_ids.len(),
+            "committed": committed,
+            "question_ids": question_ids,
+        })),
+        None,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DryRunQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// POST /api/admin/questions/reindex-dedup-keys?dry_run=true
+/// 保存済みの問題の `dedup_keys` (重複チェックの索引) を小問から計算し直す。
+/// 索引は問題の内容から決まるため、改訂履歴には残さない
+pub async fn reindex_dedup_keys(
+    admin: AdminClaims,
+    Query(query): Query<DryRunQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut changed: Vec<(String, Vec<String>)> = Vec::new();
+    db.scan::<Question, _>("questions", |q| {
+        let keys = question_dedup_keys(&q);
+        if keys != q.dedup_keys {
+            changed.push((q.id, keys));
+        }
+    })
+    .await?;
+
+    let mut committed = 0;
+    if !query.dry_run {
+        // 索引のフィールドだけを書き換え、同時に行われた編集を上書きしない
+        let merge = |id: &str, keys: &[String]| {
+            let fields = serde_json::Map::from_iter([("dedup_keys".to_string(), json!(keys))]);
+            WriteOp::merge("questions", id, fields)
+        };
+        for batch in changed.chunks(NORMALIZE_BATCH_SIZE) {
+            let writes = batch.iter().map(|(id, keys)| merge(id, keys)).collect();
+            match db.commit(writes).await {
+                Ok(()) => committed += batch.len(),
+                // 走査の後に削除された問題があれば、残りを1件ずつ書き込む (削除された問題は作り直さない)
+                Err(AppError::NotFound(_)) => {
+                    for (id, keys) in batch {
+                        match db.commit(vec![merge(id, keys)]).await {
+                            Ok(()) => committed += 1,
+                            Err(AppError::NotFound(_)) => {}
+                            Err(e) => return Err(e),
+                        }
                     }
                 }
+                Err(e) => return Err(e),
             }
         }
-        if cleaned > 0 {
-            info!("問題削除に伴い user_answers {}件を連鎖削除", cleaned);
-        }
-    });
+        info!(
+            "dedup キーの索引を更新: {}件 (by {})",
+            committed, admin.0.user_id
+        );
+    }
 
-    response_handler(
+    let question_ids: Vec<&str> = changed.iter().map(|(id, _)| id.as_str()).collect();
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({
-            "deleted": deleted,
-            "failed": failed,
+            "dry_run": query.dry_run,
+            "changed": question_ids.len(),
+            "committed": committed,
+            "question_ids": question_ids,
         })),
         None,
-    )
+    ))
 }
 
 /// GET /api/admin/duplicates
@@ -617,29 +936,18 @@ pub async fn bulk_delete(
 pub async fn duplicates(
     _admin: AdminClaims,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    let questions: Vec<Question> = match db.read_all::<Question>("questions", None).await {
-        Ok(qs) => qs,
-        Err(e) => {
-            error!("Failed to fetch questions: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e),
-            );
-        }
-    };
+) -> Result<impl IntoResponse, AppError> {
+    let mut total_parents = 0usize;
+    let mut total_sub_questions = 0usize;
 
-    let total_parents = questions.len();
-    let total_sub_questions: usize = questions.iter().map(|q| q.sub_questions.len()).sum();
-
-    // dedup キーごとの出現数を数える
+    // dedup キーごとの出現数を数える (全問題をページ単位で読む)
     let mut key_counts: HashMap<String, usize> = HashMap::new();
     let mut skipped_numeric_placeholder: usize = 0;
     let mut skipped_answer_not_in_options: usize = 0;
 
-    for q in &questions {
+    db.scan::<Question, _>("questions", |q| {
+        total_parents += 1;
+        total_sub_questions += q.sub_questions.len();
         for sub in &q.sub_questions {
             let sub_like = SubLike {
                 options: sub
@@ -662,7 +970,8 @@ pub async fn duplicates(
                 }
             }
         }
-    }
+    })
+    .await?;
 
     // 重複グループ (count >= 2) を集計
     let mut dup_keys: Vec<(String, usize)> = key_counts
@@ -671,7 +980,7 @@ pub async fn duplicates(
         .collect();
 
     // count 降順にソート
-    dup_keys.sort_by(|a, b| b.1.cmp(&a.1));
+    dup_keys.sort_by_key(|e| std::cmp::Reverse(e.1));
 
     let dedup_groups = dup_keys.len();
     let removable_subs: usize = dup_keys.iter().map(|(_, count)| count - 1).sum();
@@ -688,7 +997,7 @@ pub async fn duplicates(
         })
         .collect();
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({
@@ -701,5 +1010,5 @@ pub async fn duplicates(
             "top_groups": top_groups,
         })),
         None,
-    )
+    ))
 }
@@ -1,20 +1,31 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
 
 use axum::{
     extract::{Json, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
 };
-use firestore::{path, FirestoreQueryDirection};
-use log::error;
+use firestore::path;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio_stream::StreamExt;
 
 use crate::{
-    api::utils::response_handler,
+    api::utils::{PageQuery, page_response_handler, response_handler},
+    common::{
+        apkg::anki_package,
+        database::{DEFAULT_PAGE_SIZE, Direction, Page, StoreQuery, WriteOp},
+        error::AppError,
+        export::{ANKI_CSV_HEADER, AnkiCard, anki_csv_rows, mistake_card},
+        shuffle::ShuffleToken,
+    },
     models::claim::Claims,
+    models::hint::HintUsage,
     models::question::Question,
+    models::rating::{ItemRating, UserAbility, rating_deltas},
+    models::review::ReviewCard,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,21 +39,36 @@ pub struct UserAnswer {
     pub selected_answer: String,
     pub correct_answer: String,
     pub is_correct: bool,
+    /// ヒントを見てから回答したか
+    #[serde(default)]
+    pub assisted: bool,
     pub answered_at: i64,
 }
 
+/// ユーザーあたりの `user_answers` の上限 (目安)。
+///
+/// 超過分の削除は、コミットの前にトランザクションの外で読んだ一覧から決める。
+/// 同じユーザーの誤答が同時に記録されると一時的に上限を超えることがあるが、次の誤答の記録時に削られる
 const MAX_USER_ANSWERS: u32 = 200;
 
+/// 復習キューの既定件数
+const DEFAULT_REVIEW_LIMIT: u32 = 20;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CategoryStatsEntry {
     pub total: u32,
     pub correct: u32,
+    /// ヒントを見てから回答した数
+    #[serde(default)]
+    pub assisted: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LevelStatsEntry {
     pub total: u32,
     pub correct: u32,
+    #[serde(default)]
+    pub assisted: u32,
     pub categories: std::collections::HashMap<String, CategoryStatsEntry>,
 }
 
@@ -51,6 +77,8 @@ pub struct UserStatsDoc {
     pub user_id: String,
     pub total_answers: u32,
     pub total_correct: u32,
+    #[serde(default)]
+    pub total_assisted: u32,
     pub levels: std::collections::HashMap<String, LevelStatsEntry>,
 }
 
@@ -58,110 +86,188 @@ pub struct UserStatsDoc {
 pub struct RecordAnswerRequest {
     pub question_id: String,
     pub sub_question_id: u32,
+    /// 選んだ選択肢の key。`selected_order` を指定する場合は省略できる
+    #[serde(default)]
     pub selected_answer: String,
+    /// 文の組み立て (`ordering`) の小問で、並べた選択肢の key を先頭から順に指定する
+    #[serde(default)]
+    pub selected_order: Option<Vec<String>>,
+    /// `?shuffle=true` で配信された問題の `shuffle_token`。`selected_answer` は表示上のキーとして扱う
+    #[serde(default)]
+    pub shuffle_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct HistoryQuery {
+pub struct ReviewQuery {
     pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    /// 「今日」を判定するタイムゾーンの UTC からのオフセット (分)。既定は 0 (UTC)
+    pub tz_offset: Option<i32>,
+}
+
+/// 1回の回答で加算する user_stats のフィールド。
+///
+/// 不正解でも `correct` に 0 を加算し、集計エントリのフィールドを必ず揃える。
+/// ヒントを見てからの回答は `assisted` にも数える。
+fn stats_increments(
+    level_key: &str,
+    category_name: &str,
+    is_correct: bool,
+    assisted: bool,
+) -> Vec<(Vec<String>, i64)> {
+    let correct = i64::from(is_correct);
+    let assisted = i64::from(assisted);
+    let level = ["levels", level_key];
+    let category = ["levels", level_key, "categories", category_name];
+    let field = |prefix: &[&str], name: &str| -> Vec<String> {
+        prefix
+            .iter()
+            .chain(std::iter::once(&name))
+            .map(|s| s.to_string())
+            .collect()
+    };
+
+    vec![
+        (field(&[], "total_answers"), 1),
+        (field(&[], "total_correct"), correct),
+        (field(&[], "total_assisted"), assisted),
+        (field(&level, "total"), 1),
+        (field(&level, "correct"), correct),
+        (field(&level, "assisted"), assisted),
+        (field(&category, "total"), 1),
+        (field(&category, "correct"), correct),
+        (field(&category, "assisted"), assisted),
+    ]
 }
 
 /// POST /api/answers
+///
+/// 採点はサーバー側でのみ行う。回答後に正解キーと解説 (選択肢ごとの解説を含む) を返す。
+/// 不正解の小問には復習カードを作成し、以降は回答のたびに次回の出題日を更新する。
+///
+/// 回答前にヒントを見ていた場合 (`hint_usages` に記録がある場合) は `assisted` として集計し、
+/// 記録を消費する。ヒントありの回答は難易度・能力の推定 (Elo) には使わない。
+///
+/// 文の組み立て (`ordering`) の小問は `selected_order` で並び全体を採点できる (`selected_answer` だけなら ★ の選択肢で採点)。
+///
+/// `shuffle_token` がある場合は表示キーを正規キーに戻してから採点・保存し、
+/// レスポンスの `correct_answer` と `option_explanations` のキーは表示キーで返す。
+///
+/// 公開中でない問題 (下書き・レビュー待ち・出題終了) は存在しないものとして扱う。
 pub async fn record_answer(
     claims: Claims,
     State(db): State<Arc<crate::common::database::Database>>,
     Json(body): Json<RecordAnswerRequest>,
-) -> impl IntoResponse {
-    // Fetch the question from Firestore
-    let question: Question = match db.read::<Question>("questions", &body.question_id).await {
-        Ok(Some(q)) => q,
-        Ok(None) => {
-            return response_handler(
-                StatusCode::NOT_FOUND,
-                "error".to_string(),
-                None,
-                Some("question not found".to_string()),
-            );
-        }
-        Err(e) => {
-            error!("Failed to fetch question: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e),
-            );
-        }
-    };
-
-    // Find the sub_question
-    let sub_question = match question
+) -> Result<impl IntoResponse, AppError> {
+    let question: Question = db
+        .read::<Question>("questions", &body.question_id)
+        .await?
+        .filter(Question::is_published)
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+
+    let sub_question = question
         .sub_questions
         .iter()
         .find(|sq| sq.id == body.sub_question_id)
-    {
-        Some(sq) => sq,
-        None => {
-            return response_handler(
-                StatusCode::NOT_FOUND,
-                "error".to_string(),
-                None,
-                Some("sub_question not found".to_string()),
-            );
-        }
+        .ok_or_else(|| AppError::NotFound("sub_question not found".to_string()))?;
+
+    let shuffle = body
+        .shuffle_token
+        .as_deref()
+        .map(ShuffleToken::verify)
+        .transpose()?;
+    if shuffle.as_ref().is_some_and(|t| t.qid != question.id) {
+        return Err(AppError::Validation(
+            "shuffle_token が問題と一致しません".to_string(),
+        ));
+    }
+    let canonical = |displayed: &str| match &shuffle {
+        Some(token) => token.to_canonical(body.sub_question_id, displayed),
+        None => Ok(displayed.to_string()),
     };
 
     let correct_answer = sub_question.answer.clone();
-    let is_correct = body.selected_answer == correct_answer;
-    let level_key = format!("N{}", question.level_id);
-
-    // 1) Update user_stats incrementally
-    let stats_id = claims.user_id.clone();
-    let mut user_stats: UserStatsDoc = match db.read::<UserStatsDoc>("user_stats", &stats_id).await {
-        Ok(Some(s)) => s,
-        _ => UserStatsDoc {
-            user_id: claims.user_id.clone(),
-            total_answers: 0,
-            total_correct: 0,
-            levels: std::collections::HashMap::new(),
-        },
+    let explanation = sub_question.explanation.clone();
+    // 文の組み立ては並び全体で採点し、★ の位置に置いた選択肢を解答として記録する
+    let (selected_answer, is_correct) = match &body.selected_order {
+        Some(order) => {
+            let order = order
+                .iter()
+                .map(|key| canonical(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (is_correct, star) = sub_question
+                .grade_order(&order)
+                .map_err(AppError::Validation)?;
+            (star, is_correct)
+        }
+        None if body.selected_answer.is_empty() => {
+            return Err(AppError::Validation(
+                "selected_answer を指定してください".to_string(),
+            ));
+        }
+        None => {
+            let selected = canonical(&body.selected_answer)?;
+            let is_correct = selected == correct_answer;
+            (selected, is_correct)
+        }
     };
-    user_stats.total_answers += 1;
-    if is_correct {
-        user_stats.total_correct += 1;
-    }
-    let level_entry = user_stats.levels.entry(level_key).or_insert_with(|| LevelStatsEntry {
-        total: 0,
-        correct: 0,
-        categories: std::collections::HashMap::new(),
-    });
-    level_entry.total += 1;
-    if is_correct {
-        level_entry.correct += 1;
-    }
-    let cat_entry = level_entry.categories.entry(question.category_name.clone()).or_insert_with(|| CategoryStatsEntry {
-        total: 0,
-        correct: 0,
-    });
-    cat_entry.total += 1;
-    if is_correct {
-        cat_entry.correct += 1;
+    let level_key = format!("N{}", question.level_id);
+    let now = chrono::Utc::now().timestamp();
+    let usage_id = HintUsage::doc_id(&claims.user_id, &question.id, body.sub_question_id);
+    let assisted = db
+        .read::<HintUsage>("hint_usages", &usage_id)
+        .await?
+        .is_some();
+
+    // 1) user_stats はストア側の加算で更新する (同時回答でも取りこぼさない)
+    let mut writes = vec![WriteOp::Increment {
+        collection: "user_stats".to_string(),
+        id: claims.user_id.clone(),
+        merge: serde_json::Map::from_iter([("user_id".to_string(), json!(claims.user_id))]),
+        increments: stats_increments(&level_key, &question.category_name, is_correct, assisted),
+    }];
+    if assisted {
+        writes.push(WriteOp::delete("hint_usages", &usage_id));
     }
 
-    if let Err(e) = db.update::<UserStatsDoc>("user_stats", &stats_id, user_stats.clone()).await {
-        // If update fails (doc doesn't exist yet), try create
-        if let Err(e2) = db.create::<UserStatsDoc>("user_stats", &stats_id, user_stats.clone()).await {
-            error!("Failed to save user_stats: {} / {}", e, e2);
-        }
+    // 2) 復習カード: 不正解で作成し、既存カードは正誤にかかわらず更新する
+    let card_id = format!(
+        "{}_{}_{}",
+        claims.user_id, body.question_id, body.sub_question_id
+    );
+    let card = match db.read::<ReviewCard>("review_cards", &card_id).await? {
+        Some(card) => Some(card),
+        None if !is_correct => Some(ReviewCard::new(
+            &claims.user_id,
+            &body.question_id,
+            body.sub_question_id,
+            question.level_id,
+            &question.category_name,
+        )),
+        None => None,
+    };
+    if let Some(mut card) = card {
+        card.review(is_correct, now);
+        writes.push(WriteOp::upsert("review_cards", &card.id, &card)?);
     }
 
-    // 2) Save to user_answers only if incorrect (upsert: 同じ問題の重複を防止)
-    if !is_correct {
-        // 決定的ID: user_id + question_id + sub_question_id で一意に特定
-        let doc_id = format!(
-            "{}_{}_{}", claims.user_id, body.question_id, body.sub_question_id
+    // 3) 小問の難易度とユーザーの能力 (Elo): 差分だけをストア側で加算する
+    if !assisted {
+        writes.extend(
+            rating_writes(&db, &claims, &question, body.sub_question_id, is_correct).await?,
         );
+    }
 
+    // 4) Save to user_answers only if incorrect (upsert: 同じ問題の重複を防止)
+    // 決定的ID: user_id + question_id + sub_question_id で一意に特定
+    let doc_id = format!(
+        "{}_{}_{}",
+        claims.user_id, body.question_id, body.sub_question_id
+    );
+    if is_correct {
+        // 正解したら間違えた記録を消す (/mistakes に残さない)
+        writes.push(WriteOp::delete("user_answers", &doc_id));
+    } else {
         let user_answer = UserAnswer {
             id: doc_id.clone(),
             user_id: claims.user_id.clone(),
@@ -169,187 +275,209 @@ pub async fn record_answer(
             sub_question_id: body.sub_question_id,
             level_id: question.level_id,
             category_name: question.category_name.clone(),
-            selected_answer: body.selected_answer.clone(),
-            correct_answer,
+            selected_answer,
+            correct_answer: correct_answer.clone(),
             is_correct: false,
-            answered_at: chrono::Utc::now().timestamp(),
+            assisted,
+            answered_at: now,
         };
+        writes.push(WriteOp::upsert("user_answers", &doc_id, &user_answer)?);
+
+        // 5) Prune old answers if over limit (今回の upsert 分を含めて上限に収める)
+        // 一覧はトランザクションの外で読むため、上限は目安 (MAX_USER_ANSWERS 参照)
+        let query = StoreQuery::new()
+            .eq(path!(UserAnswer::user_id), &claims.user_id)
+            .order_by(path!(UserAnswer::answered_at), Direction::Descending);
+        let existing = db.query::<UserAnswer>("user_answers", query).await?;
+        writes.extend(
+            existing
+                .iter()
+                .filter(|a| a.id != doc_id)
+                .skip(MAX_USER_ANSWERS as usize - 1)
+                .map(|old| WriteOp::delete("user_answers", &old.id)),
+        );
+    }
 
-        // upsert: 既存なら上書き、なければ作成
-        if let Err(e) = db.update::<UserAnswer>("user_answers", &doc_id, user_answer.clone()).await {
-            if let Err(e2) = db.create::<UserAnswer>("user_answers", &doc_id, user_answer).await {
-                error!("Failed to save user_answer: {} / {}", e, e2);
-            }
-        }
+    db.commit(writes).await?;
 
-        // 3) Prune old answers if over limit
-        if let Ok(mut stream) = db
-            .client
-            .fluent()
-            .select()
-            .from("user_answers")
-            .filter(|q| {
-                q.field(path!(UserAnswer::user_id)).eq(claims.user_id.clone())
-            })
-            .order_by([(
-                path!(UserAnswer::answered_at),
-                FirestoreQueryDirection::Descending,
-            )])
-            .obj::<UserAnswer>()
-            .stream_query_with_errors()
-            .await
-        {
-            let mut all_answers = Vec::new();
-            while let Some(item) = stream.next().await {
-                if let Ok(a) = item {
-                    all_answers.push(a);
-                }
-            }
-            if all_answers.len() as u32 > MAX_USER_ANSWERS {
-                for old in &all_answers[MAX_USER_ANSWERS as usize..] {
-                    let _ = db.delete("user_answers", &old.id).await;
-                }
-            }
-        }
-    }
+    let displayed = |key: &str| {
+        shuffle
+            .as_ref()
+            .and_then(|t| t.to_displayed(body.sub_question_id, key))
+            .unwrap_or_else(|| key.to_string())
+    };
+    let option_explanations: BTreeMap<String, String> = sub_question
+        .option_explanations
+        .iter()
+        .map(|(key, text)| (displayed(key), text.clone()))
+        .collect();
+    let correct_answer = displayed(&correct_answer);
+    let correct_order: Option<Vec<String>> = sub_question
+        .correct_order()
+        .map(|order| order.iter().map(|key| displayed(key)).collect());
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
-        Some(json!({ "is_correct": is_correct })),
+        Some(json!({
+            "is_correct": is_correct,
+            "assisted": assisted,
+            "correct_answer": correct_answer,
+            "correct_order": correct_order,
+            "explanation": explanation,
+            "option_explanations": option_explanations,
+        })),
         None,
-    )
+    ))
+}
+
+/// 回答結果から Elo の差分を求め、`item_ratings` と `user_abilities` の加算操作を返す
+async fn rating_writes(
+    db: &crate::common::database::Database,
+    claims: &Claims,
+    question: &Question,
+    sub_question_id: u32,
+    is_correct: bool,
+) -> Result<Vec<WriteOp>, AppError> {
+    let category_id = question.category_id.clone().unwrap_or_default();
+    let item_id = ItemRating::doc_id(&question.id, sub_question_id);
+    let ability_id = UserAbility::doc_id(&claims.user_id, question.level_id, &category_id);
+
+    let item = db.read::<ItemRating>("item_ratings", &item_id).await?;
+    let ability = db
+        .read::<UserAbility>("user_abilities", &ability_id)
+        .await?;
+    let (user_delta, item_delta) = rating_deltas(
+        ability.map_or((0, 0), |a| (a.rating, a.attempts)),
+        item.map_or((0, 0), |i| (i.rating, i.attempts)),
+        is_correct,
+    );
+
+    let increments = |delta: i64| {
+        vec![
+            (vec!["rating".to_string()], delta),
+            (vec!["attempts".to_string()], 1),
+        ]
+    };
+    Ok(vec![
+        WriteOp::Increment {
+            collection: "item_ratings".to_string(),
+            id: item_id,
+            merge: serde_json::Map::from_iter([
+                ("question_id".to_string(), json!(question.id)),
+                ("sub_question_id".to_string(), json!(sub_question_id)),
+                ("level_id".to_string(), json!(question.level_id)),
+                ("category_id".to_string(), json!(category_id)),
+            ]),
+            increments: increments(item_delta),
+        },
+        WriteOp::Increment {
+            collection: "user_abilities".to_string(),
+            id: ability_id,
+            merge: serde_json::Map::from_iter([
+                ("user_id".to_string(), json!(claims.user_id)),
+                ("level_id".to_string(), json!(question.level_id)),
+                ("category_id".to_string(), json!(category_id)),
+            ]),
+            increments: increments(user_delta),
+        },
+    ])
 }
 
-/// GET /api/users/me/history?limit=50
+/// GET /api/users/me/history?limit=50&cursor=...
 pub async fn history(
     claims: Claims,
-    Query(query): Query<HistoryQuery>,
+    Query(page): Query<PageQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    let limit = query.limit.unwrap_or(50);
-
-    match db
-        .client
-        .fluent()
-        .select()
-        .from("user_answers")
-        .filter(|q| {
-            q.field(path!(UserAnswer::user_id)).eq(claims.user_id.clone())
-        })
-        .order_by([(
-            path!(UserAnswer::answered_at),
-            FirestoreQueryDirection::Descending,
-        )])
-        .limit(limit)
-        .obj::<UserAnswer>()
-        .stream_query_with_errors()
-        .await
-    {
-        Ok(mut stream) => {
-            let mut answers = Vec::new();
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(answer) => answers.push(answer),
-                    Err(e) => {
-                        error!("Error reading user_answer: {:?}", e);
-                    }
-                }
+) -> Result<impl IntoResponse, AppError> {
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    let query = StoreQuery::new()
+        .eq(path!(UserAnswer::user_id), &claims.user_id)
+        .order_by(path!(UserAnswer::answered_at), Direction::Descending);
+
+    let Page {
+        items: answers,
+        next_cursor,
+    } = db
+        .query_page::<UserAnswer>("user_answers", query, page.cursor.as_deref(), limit)
+        .await?;
+
+    // question_id で重複除外（ページ内で最新のみ保持）+ 削除済み問題の存在確認
+    let mut seen = std::collections::HashSet::new();
+    let mut orphaned_ids = Vec::new();
+    let mut results = Vec::new();
+
+    for a in &answers {
+        if !seen.insert(a.question_id.clone()) {
+            continue;
+        }
+        // 問題がまだ存在するか確認
+        // 通信エラーを「削除済み」と誤判定して消さないよう、None のときだけ孤立扱いにする
+        match db.read::<Question>("questions", &a.question_id).await? {
+            Some(_) => {
+                let level_name = format!("N{}", a.level_id);
+                let level_slug = format!("n{}", a.level_id);
+                let created_at = chrono::DateTime::from_timestamp(a.answered_at, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default();
+                results.push(json!({
+                    "id": a.id,
+                    "question_id": a.question_id,
+                    "level_name": level_name,
+                    "level_slug": level_slug,
+                    "category_name": a.category_name,
+                    "created_at": created_at,
+                }));
             }
-
-            // question_id で重複除外（最新のみ保持）+ 削除済み問題の存在確認
-            let mut seen = std::collections::HashSet::new();
-            let mut orphaned_ids = Vec::new();
-            let mut results = Vec::new();
-
-            for a in &answers {
-                if !seen.insert(a.question_id.clone()) {
-                    continue;
-                }
-                // 問題がまだ存在するか確認
-                match db.read::<Question>("questions", &a.question_id).await {
-                    Ok(Some(_)) => {
-                        let level_name = format!("N{}", a.level_id);
-                        let level_slug = format!("n{}", a.level_id);
-                        let created_at = chrono::DateTime::from_timestamp(a.answered_at, 0)
-                            .map(|dt| dt.to_rfc3339())
-                            .unwrap_or_default();
-                        results.push(json!({
-                            "id": a.id,
-                            "question_id": a.question_id,
-                            "level_name": level_name,
-                            "level_slug": level_slug,
-                            "category_name": a.category_name,
-                            "created_at": created_at,
-                        }));
-                    }
-                    _ => {
-                        // 削除済み問題 → 孤立レコードとして記録
-                        orphaned_ids.push(a.id.clone());
-                    }
-                }
+            None => {
+                // 削除済み問題 → 孤立レコードとして記録
+                orphaned_ids.push(a.id.clone());
             }
+        }
+    }
 
-            // 孤立レコードをバックグラウンドで削除
-            if !orphaned_ids.is_empty() {
-                let db_clone = db.clone();
-                let count = orphaned_ids.len();
-                tokio::spawn(async move {
-                    for id in &orphaned_ids {
-                        let _ = db_clone.delete("user_answers", id).await;
-                    }
-                    log::info!("孤立user_answers {}件を削除", count);
-                });
+    // 孤立レコードをバックグラウンドで削除
+    if !orphaned_ids.is_empty() {
+        let db_clone = db.clone();
+        let count = orphaned_ids.len();
+        tokio::spawn(async move {
+            for id in &orphaned_ids {
+                let _ = db_clone.delete("user_answers", id).await;
             }
-
-            response_handler(
-                StatusCode::OK,
-                "success".to_string(),
-                Some(json!(results)),
-                None,
-            )
-        }
-        Err(e) => {
-            error!("Failed to query history: {:?}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(format!("{:?}", e)),
-            )
-        }
+            log::info!("孤立user_answers {}件を削除", count);
+        });
     }
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(results),
+        next_cursor,
+    ))
 }
 
 /// GET /api/users/me/stats
 pub async fn stats(
     claims: Claims,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    let user_stats: UserStatsDoc = match db.read::<UserStatsDoc>("user_stats", &claims.user_id).await {
-        Ok(Some(s)) => s,
-        Ok(None) => {
-            return response_handler(
-                StatusCode::OK,
-                "success".to_string(),
-                Some(json!({
-                    "total_answers": 0,
-                    "total_correct": 0,
-                    "overall_accuracy": 0.0,
-                    "levels": [],
-                })),
-                None,
-            );
-        }
-        Err(e) => {
-            error!("Failed to read user_stats: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(e),
-            );
-        }
+) -> Result<impl IntoResponse, AppError> {
+    let Some(user_stats) = db
+        .read::<UserStatsDoc>("user_stats", &claims.user_id)
+        .await?
+    else {
+        return Ok(response_handler(
+            StatusCode::OK,
+            "success".to_string(),
+            Some(json!({
+                "total_answers": 0,
+                "total_correct": 0,
+                "total_assisted": 0,
+                "overall_accuracy": 0.0,
+                "levels": [],
+            })),
+            None,
+        ));
     };
 
     let overall_accuracy = if user_stats.total_answers > 0 {
@@ -380,6 +508,7 @@ pub async fn stats(
                         "category_name": cat_name,
                         "total": cat.total,
                         "correct": cat.correct,
+                        "assisted": cat.assisted,
                         "accuracy": cat_accuracy,
                     })
                 })
@@ -388,6 +517,7 @@ pub async fn stats(
                 "level_name": level_name,
                 "total": level.total,
                 "correct": level.correct,
+                "assisted": level.assisted,
                 "accuracy": level_accuracy,
                 "categories": categories,
             })
@@ -396,75 +526,205 @@ pub async fn stats(
 
     // Sort by level name (N1, N2, ...)
     levels_json.sort_by(|a, b| {
-        a["level_name"].as_str().unwrap_or("").cmp(&b["level_name"].as_str().unwrap_or(""))
+        a["level_name"]
+            .as_str()
+            .unwrap_or("")
+            .cmp(b["level_name"].as_str().unwrap_or(""))
     });
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!({
             "total_answers": user_stats.total_answers,
             "total_correct": user_stats.total_correct,
+            "total_assisted": user_stats.total_assisted,
             "overall_accuracy": overall_accuracy,
             "levels": levels_json,
         })),
         None,
-    )
+    ))
 }
 
-/// GET /api/users/me/mistakes?limit=20
+/// GET /api/users/me/mistakes?limit=20&cursor=...
 pub async fn mistakes(
     claims: Claims,
-    Query(query): Query<HistoryQuery>,
+    Query(page): Query<PageQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    let limit = query.limit.unwrap_or(20);
-
-    match db
-        .client
-        .fluent()
-        .select()
-        .from("user_answers")
-        .filter(|q| {
-            q.for_all([
-                q.field(path!(UserAnswer::user_id)).eq(claims.user_id.clone()),
-                q.field(path!(UserAnswer::is_correct)).eq(false),
-            ])
-        })
-        .order_by([(
-            path!(UserAnswer::answered_at),
-            FirestoreQueryDirection::Descending,
-        )])
-        .limit(limit)
-        .obj::<UserAnswer>()
-        .stream_query_with_errors()
-        .await
-    {
-        Ok(mut stream) => {
-            let mut results = Vec::new();
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(answer) => results.push(answer),
-                    Err(e) => {
-                        error!("Error reading mistake: {:?}", e);
-                    }
-                }
-            }
-            response_handler(
-                StatusCode::OK,
-                "success".to_string(),
-                Some(json!(results)),
-                None,
-            )
+) -> Result<impl IntoResponse, AppError> {
+    let limit = page.limit.unwrap_or(20);
+
+    let query = StoreQuery::new()
+        .eq(path!(UserAnswer::user_id), &claims.user_id)
+        .eq(path!(UserAnswer::is_correct), false)
+        .order_by(path!(UserAnswer::answered_at), Direction::Descending);
+
+    let page = db
+        .query_page::<UserAnswer>("user_answers", query, page.cursor.as_deref(), limit)
+        .await?;
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(page.items),
+        page.next_cursor,
+    ))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MistakeExportFormat {
+    #[default]
+    AnkiCsv,
+    Apkg,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MistakeExportQuery {
+    #[serde(default)]
+    format: MistakeExportFormat,
+}
+
+/// Anki のデッキ名
+const MISTAKE_DECK_NAME: &str = "JLPT 間違えた問題";
+
+/// GET /api/users/me/mistakes/export?format=anki_csv|apkg
+///
+/// 間違えた小問を Anki のカードとして書き出す。同じ小問を何度か間違えた場合は最新の解答を使い、
+/// 削除された問題は含めない。
+pub async fn export_mistakes(
+    claims: Claims,
+    Query(params): Query<MistakeExportQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<Response, AppError> {
+    // 回答履歴はユーザーごとに MAX_USER_ANSWERS 件までなので一度に読む
+    let query = StoreQuery::new()
+        .eq(path!(UserAnswer::user_id), &claims.user_id)
+        .eq(path!(UserAnswer::is_correct), false)
+        .order_by(path!(UserAnswer::answered_at), Direction::Descending);
+    let answers = db.query::<UserAnswer>("user_answers", query).await?;
+
+    let mut seen = HashSet::new();
+    let mut questions: HashMap<String, Option<Question>> = HashMap::new();
+    let mut cards: Vec<AnkiCard> = Vec::new();
+    for answer in answers {
+        if !seen.insert((answer.question_id.clone(), answer.sub_question_id)) {
+            continue;
+        }
+        if !questions.contains_key(&answer.question_id) {
+            let question = db
+                .read::<Question>("questions", &answer.question_id)
+                .await?;
+            questions.insert(answer.question_id.clone(), question);
+        }
+        let Some(question) = questions.get(&answer.question_id).and_then(Option::as_ref) else {
+            continue;
+        };
+        if let Some(sub_q) = question
+            .sub_questions
+            .iter()
+            .find(|sq| sq.id == answer.sub_question_id)
+        {
+            cards.push(mistake_card(question, sub_q, &answer.selected_answer));
         }
-        Err(e) => {
-            error!("Failed to query mistakes: {:?}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some(format!("{:?}", e)),
-            )
+    }
+
+    let (content_type, filename, body) = match params.format {
+        MistakeExportFormat::AnkiCsv => (
+            "text/csv; charset=utf-8",
+            "mistakes.csv",
+            format!("{}{}", ANKI_CSV_HEADER, anki_csv_rows(&cards)?).into_bytes(),
+        ),
+        MistakeExportFormat::Apkg => {
+            let package =
+                tokio::task::spawn_blocking(move || anki_package(MISTAKE_DECK_NAME, &cards))
+                    .await
+                    .map_err(|e| AppError::Internal(e.to_string()))??;
+            ("application/apkg", "mistakes.apkg", package)
         }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// GET /api/users/me/review?limit=20&cursor=...&tz_offset=540
+///
+/// 今日中 (`tz_offset` のタイムゾーンで日付が変わるまで) に出題日を迎える復習カードを、
+/// 出題日の早い順に返す。
+pub async fn review(
+    claims: Claims,
+    Query(params): Query<ReviewQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let tz_offset = params.tz_offset.unwrap_or(0);
+    if !(-12 * 60..=14 * 60).contains(&tz_offset) {
+        return Err(AppError::Validation(
+            "tz_offset は -720〜840 で指定してください".to_string(),
+        ));
+    }
+    let until = end_of_day(chrono::Utc::now().timestamp(), tz_offset);
+
+    let query = StoreQuery::new()
+        .eq(path!(ReviewCard::user_id), &claims.user_id)
+        .order_by(path!(ReviewCard::due_at), Direction::Ascending);
+    let page = db
+        .query_page::<ReviewCard>(
+            "review_cards",
+            query,
+            params.cursor.as_deref(),
+            params.limit.unwrap_or(DEFAULT_REVIEW_LIMIT),
+        )
+        .await?;
+
+    // 出題日順なので、今日より後のカードが出た時点で打ち切る
+    let fetched = page.items.len();
+    let due: Vec<ReviewCard> = page
+        .items
+        .into_iter()
+        .take_while(|card| card.due_at < until)
+        .collect();
+    let next_cursor = if due.len() == fetched {
+        page.next_cursor
+    } else {
+        None
+    };
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(due),
+        next_cursor,
+    ))
+}
+
+/// `now` を含む日 (UTC から `tz_offset` 分ずれたタイムゾーン) の翌日0時の UNIX 時刻
+fn end_of_day(now: i64, tz_offset: i32) -> i64 {
+    const SECS_PER_DAY: i64 = 60 * 60 * 24;
+    let offset = i64::from(tz_offset) * 60;
+    ((now + offset).div_euclid(SECS_PER_DAY) + 1) * SECS_PER_DAY - offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_end_of_day() {
+        // 2023-11-14T22:13:20Z
+        let now = 1_700_000_000;
+        assert_eq!(end_of_day(now, 0), 1_700_006_400);
+        // JST では 2023-11-15T07:13:20+09:00 → 翌日0時は 2023-11-15T15:00:00Z
+        assert_eq!(end_of_day(now, 540), 1_700_060_400);
+        assert_eq!(end_of_day(now, -300), 1_700_024_400);
     }
 }
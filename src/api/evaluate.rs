@@ -9,7 +9,7 @@ use log::info;
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{api::utils::response_handler, models::evaluate::Vote};
+use crate::{api::utils::response_handler, common::error::AppError, models::evaluate::Vote};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -42,18 +42,13 @@ pub async fn vote(
     Path(path_params): Path<PathParams>,
     Query(query_params): Query<QueryParams>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let parent_id = query_params.parent_id.as_deref().unwrap_or_default();
     let child_id = query_params.child_id.as_deref().unwrap_or_default();
 
     // parent_id, child_id の長さ制限
     if parent_id.len() > 128 || child_id.len() > 128 {
-        return response_handler(
-            StatusCode::BAD_REQUEST,
-            "error".to_string(),
-            None,
-            Some("IDが長すぎます".to_string()),
-        );
+        return Err(AppError::Validation("IDが長すぎます".to_string()));
     }
 
     let vote_str = path_params.vote.to_string();
@@ -70,31 +65,16 @@ pub async fn vote(
         child_id.to_string(),
     );
 
-    match db
-        .client
-        .fluent()
-        .insert()
-        .into("votes")
-        .document_id(vote.id())
-        .object(&vote)
-        .execute::<Vote>()
-        .await
-    {
-        Ok(_) => response_handler(
-            StatusCode::OK,
-            "success".to_string(),
-            Some(json!({
-                "vote": &vote_str,
-                "parent_id": parent_id,
-                "child_id": child_id,
-            })),
-            None,
-        ),
-        Err(e) => response_handler(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "error".to_string(),
-            None,
-            Some(e.to_string()),
-        ),
-    }
+    db.create::<Vote>("votes", &vote.id(), vote.clone()).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "vote": &vote_str,
+            "parent_id": parent_id,
+            "child_id": child_id,
+        })),
+        None,
+    ))
 }
@@ -0,0 +1,488 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use firestore::path;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    api::utils::{PageQuery, page_response_handler, response_handler},
+    common::{
+        database::{DEFAULT_PAGE_SIZE, Database, Direction, StoreQuery},
+        error::{AppError, AppResult},
+    },
+    models::{
+        claim::Claims,
+        exam::{
+            Blueprint, CategoryKind, ExamAnswer, ExamQuestion, ExamSection, ExamSession,
+            ExamStatus, blueprint, score_exam,
+        },
+        meta::CatValue,
+        question::{PublicQuestion, Question},
+    },
+};
+
+/// 通信遅延を考慮して制限時間後も受け付ける秒数
+const DEADLINE_GRACE_SECS: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateExamRequest {
+    pub level_id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SectionPath {
+    id: String,
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExamAnswerRequest {
+    pub question_id: String,
+    pub sub_question_id: u32,
+    pub selected_answer: String,
+}
+
+/// POST /api/exams
+///
+/// `categories` のメタデータからレベルの模試を組み立てる。
+/// 科目ごとに種別ごとの出題数 (小問数) までランダムに問題を選ぶ。出題の無い科目は含めない。
+/// 問題は含めず、科目ごとに `GET /api/exams/{id}/sections/{index}` で取得する。
+pub async fn create_exam(
+    claims: Claims,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<CreateExamRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let blueprint = blueprint(body.level_id)
+        .ok_or_else(|| AppError::Validation("level_id は 1〜5 で指定してください".to_string()))?;
+
+    let mut pools = question_pools(&db, blueprint).await?;
+    let sections: Vec<ExamSection> = blueprint
+        .sections
+        .iter()
+        .map(|spec| {
+            let mut questions = Vec::new();
+            for (kind, quota) in spec.quotas {
+                questions.extend(pick_questions(
+                    pools.remove(kind).unwrap_or_default(),
+                    *kind,
+                    *quota,
+                ));
+            }
+            ExamSection {
+                key: spec.key.to_string(),
+                name: spec.name.to_string(),
+                time_limit_secs: i64::from(spec.time_limit_mins) * 60,
+                started_at: None,
+                questions,
+            }
+        })
+        .filter(|section| !section.questions.is_empty())
+        .collect();
+
+    if sections.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "模試を作成できる問題がありません: N{}",
+            body.level_id
+        )));
+    }
+
+    let session = ExamSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: claims.user_id,
+        level_id: body.level_id,
+        status: ExamStatus::InProgress,
+        created_at: chrono::Utc::now().timestamp(),
+        sections,
+        finished_at: None,
+        result: None,
+    };
+    db.create("exams", &session.id, &session).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(session_json(&session, &HashMap::new())),
+        None,
+    ))
+}
+
+/// GET /api/exams/{id}
+///
+/// 受験中は科目の情報と回答済みのキーだけを返す (問題は科目ごとに取得する)。
+/// 終了後は全科目の問題を正解・解説と結果つきで返す。
+pub async fn get_exam(
+    claims: Claims,
+    Path(id): Path<String>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = read_session(&db, &id, &claims).await?;
+    let answers = read_answers(&db, &session).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(session_json(&session, &answers)),
+        None,
+    ))
+}
+
+/// GET /api/exams/{id}/sections/{index}
+///
+/// 科目 (0 始まり) の問題を正解を含めずに返す。最初に取得した時刻から制限時間を数える。
+/// 後の科目を取得すると前の科目には戻れない。制限時間を過ぎた科目は取得できない。
+pub async fn get_section(
+    claims: Claims,
+    Path(path): Path<SectionPath>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut session = read_session(&db, &path.id, &claims).await?;
+    if session.status == ExamStatus::Finished {
+        return Err(AppError::Conflict("模試は既に終了しています".to_string()));
+    }
+    if path.index >= session.sections.len() {
+        return Err(AppError::NotFound("exam section not found".to_string()));
+    }
+    check_section_open(&session, path.index)?;
+
+    let now = chrono::Utc::now().timestamp();
+    if session.sections[path.index].started_at.is_none() {
+        session.sections[path.index].started_at = Some(now);
+        db.update("exams", &session.id, &session).await?;
+    }
+    let section = &session.sections[path.index];
+    let deadline = check_deadline(section, now)?;
+
+    let answers = read_answers(&db, &session).await?;
+    let answers: HashMap<(String, u32), String> = answers
+        .into_iter()
+        .filter(|((question_id, sub_question_id), _)| {
+            section.contains(question_id, *sub_question_id)
+        })
+        .collect();
+    let questions: Vec<PublicQuestion> = section
+        .questions
+        .iter()
+        .map(|eq| PublicQuestion::from(eq.question.clone()))
+        .collect();
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "index": path.index,
+            "key": section.key,
+            "name": section.name,
+            "time_limit_secs": section.time_limit_secs,
+            "started_at": section.started_at,
+            "deadline": deadline,
+            "remaining_secs": (deadline - now).max(0),
+            "questions": questions,
+            "answers": answers_json(&answers),
+        })),
+        None,
+    ))
+}
+
+/// POST /api/exams/{id}/answers
+///
+/// 取得済み (制限時間の開始済み) の科目の小問にだけ回答できる。後の科目を取得すると前の科目には戻れない。
+/// 同じ小問への再回答は上書きする。採点は終了時に行う。
+pub async fn submit_answer(
+    claims: Claims,
+    Path(id): Path<String>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<ExamAnswerRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = read_session(&db, &id, &claims).await?;
+    if session.status == ExamStatus::Finished {
+        return Err(AppError::Conflict("模試は既に終了しています".to_string()));
+    }
+
+    let index = session
+        .sections
+        .iter()
+        .position(|s| s.contains(&body.question_id, body.sub_question_id))
+        .ok_or_else(|| AppError::NotFound("exam question not found".to_string()))?;
+    check_section_open(&session, index)?;
+    let section = &session.sections[index];
+    if section.started_at.is_none() {
+        return Err(AppError::Validation(
+            "科目の問題を取得してから回答してください".to_string(),
+        ));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let deadline = check_deadline(section, now)?;
+
+    let answer = ExamAnswer {
+        exam_id: session.id.clone(),
+        user_id: claims.user_id,
+        question_id: body.question_id,
+        sub_question_id: body.sub_question_id,
+        selected_answer: body.selected_answer,
+        answered_at: now,
+    };
+    let doc_id = format!(
+        "{}_{}_{}",
+        answer.exam_id, answer.question_id, answer.sub_question_id
+    );
+    db.update("exam_answers", &doc_id, &answer).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "section": section.key,
+            "deadline": deadline,
+            "remaining_secs": (deadline - now).max(0),
+        })),
+        None,
+    ))
+}
+
+/// POST /api/exams/{id}/finish
+///
+/// 採点して結果をセッションに保存する。未回答の小問は不正解として数える。
+pub async fn finish_exam(
+    claims: Claims,
+    Path(id): Path<String>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut session = read_session(&db, &id, &claims).await?;
+    if session.status == ExamStatus::Finished {
+        return Err(AppError::Conflict("模試は既に終了しています".to_string()));
+    }
+    let blueprint = blueprint(session.level_id)
+        .ok_or_else(|| AppError::Internal(format!("unknown exam level: {}", session.level_id)))?;
+
+    let answers = read_answers(&db, &session).await?;
+    let result = score_exam(blueprint, &session.sections, &answers);
+
+    session.status = ExamStatus::Finished;
+    session.finished_at = Some(chrono::Utc::now().timestamp());
+    session.result = Some(result.clone());
+    db.update("exams", &session.id, &session).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(result)),
+        None,
+    ))
+}
+
+/// GET /api/users/me/exams?limit=20&cursor=...
+///
+/// 自分の模試を新しい順に返す。問題は含めない。
+pub async fn list_exams(
+    claims: Claims,
+    Query(page): Query<PageQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let query = StoreQuery::new()
+        .eq(path!(ExamSession::user_id), &claims.user_id)
+        .order_by(path!(ExamSession::created_at), Direction::Descending);
+    let page = db
+        .query_page::<ExamSession>(
+            "exams",
+            query,
+            page.cursor.as_deref(),
+            page.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+
+    let items: Vec<Value> = page
+        .items
+        .iter()
+        .map(|s| {
+            json!({
+                "id": s.id,
+                "level_id": s.level_id,
+                "status": s.status,
+                "created_at": s.created_at,
+                "finished_at": s.finished_at,
+                "result": s.result,
+            })
+        })
+        .collect();
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(items),
+        page.next_cursor,
+    ))
+}
+
+/// 後の科目を始めた後は、前の科目を取得・回答できない
+fn check_section_open(session: &ExamSession, index: usize) -> AppResult<()> {
+    if session.sections[index + 1..]
+        .iter()
+        .any(|s| s.started_at.is_some())
+    {
+        return Err(AppError::Validation(
+            "終了した科目には戻れません".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 開始済みの科目の締め切り。猶予を過ぎていればエラー
+fn check_deadline(section: &ExamSection, now: i64) -> AppResult<i64> {
+    let deadline = section.deadline().unwrap_or(now);
+    if now > deadline + DEADLINE_GRACE_SECS {
+        return Err(AppError::Validation(
+            "科目の制限時間を過ぎています".to_string(),
+        ));
+    }
+    Ok(deadline)
+}
+
+/// 本人の模試のみ返す (他人の模試は存在しない扱い)
+async fn read_session(db: &Database, id: &str, claims: &Claims) -> AppResult<ExamSession> {
+    db.read::<ExamSession>("exams", id)
+        .await?
+        .filter(|s| s.user_id == claims.user_id)
+        .ok_or_else(|| AppError::NotFound("exam not found".to_string()))
+}
+
+async fn read_answers(
+    db: &Database,
+    session: &ExamSession,
+) -> AppResult<HashMap<(String, u32), String>> {
+    let query = StoreQuery::new().eq(path!(ExamAnswer::exam_id), &session.id);
+    Ok(db
+        .query::<ExamAnswer>("exam_answers", query)
+        .await?
+        .into_iter()
+        .filter(|a| a.user_id == session.user_id)
+        .map(|a| ((a.question_id, a.sub_question_id), a.selected_answer))
+        .collect())
+}
+
+/// レベルのカテゴリを種別ごとにまとめ、カテゴリ単位の問題リストを返す
+async fn question_pools(
+    db: &Database,
+    blueprint: &Blueprint,
+) -> AppResult<HashMap<CategoryKind, Vec<Vec<Question>>>> {
+    let mut categories: Vec<CatValue> = db
+        .read_all::<CatValue>("categories")
+        .await?
+        .into_iter()
+        .filter(|c| c.level_id == blueprint.level_id)
+        .collect();
+    categories.sort_by_key(|c| c.id);
+
+    let mut pools: HashMap<CategoryKind, Vec<Vec<Question>>> = HashMap::new();
+    for category in categories {
+        let query = StoreQuery::new()
+            .eq(path!(Question::level_id), blueprint.level_id)
+            .eq(path!(Question::category_id), category.id.to_string());
+        let questions: Vec<Question> = db
+            .query::<Question>("questions", query)
+            .await?
+            .into_iter()
+            .filter(Question::is_published)
+            .collect();
+        if !questions.is_empty() {
+            pools
+                .entry(CategoryKind::classify(&category.name))
+                .or_default()
+                .push(questions);
+        }
+    }
+    Ok(pools)
+}
+
+/// 各カテゴリから順番に1問ずつ選び、小問数が `quota` に達するまで続ける。
+/// 出題順はカテゴリ順 (公式の大問の並び) にまとめる。
+fn pick_questions(
+    mut categories: Vec<Vec<Question>>,
+    kind: CategoryKind,
+    quota: u32,
+) -> Vec<ExamQuestion> {
+    let mut rng = rand::rng();
+    categories.iter_mut().for_each(|qs| qs.shuffle(&mut rng));
+
+    let mut picked: Vec<Vec<Question>> = vec![Vec::new(); categories.len()];
+    let mut count = 0;
+    'outer: loop {
+        let mut progressed = false;
+        for (i, pool) in categories.iter_mut().enumerate() {
+            if count >= quota as usize {
+                break 'outer;
+            }
+            if let Some(question) = pool.pop() {
+                count += question.sub_questions.len();
+                picked[i].push(question);
+                progressed = true;
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+
+    picked
+        .into_iter()
+        .flatten()
+        .map(|question| ExamQuestion { kind, question })
+        .collect()
+}
+
+fn session_json(session: &ExamSession, answers: &HashMap<(String, u32), String>) -> Value {
+    let finished = session.status == ExamStatus::Finished;
+    let sections: Vec<Value> = session
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| {
+            let mut value = json!({
+                "index": index,
+                "key": section.key,
+                "name": section.name,
+                "time_limit_secs": section.time_limit_secs,
+                "started_at": section.started_at,
+                "deadline": section.deadline(),
+                "question_count": section.questions.len(),
+            });
+            // 受験中の問題は科目ごとの取得でのみ返す
+            if finished {
+                let questions: Vec<&Question> =
+                    section.questions.iter().map(|eq| &eq.question).collect();
+                value["questions"] = json!(questions);
+            }
+            value
+        })
+        .collect();
+
+    json!({
+        "id": session.id,
+        "level_id": session.level_id,
+        "status": session.status,
+        "created_at": session.created_at,
+        "finished_at": session.finished_at,
+        "sections": sections,
+        "answers": answers_json(answers),
+        "result": session.result,
+    })
+}
+
+fn answers_json(answers: &HashMap<(String, u32), String>) -> Vec<Value> {
+    answers
+        .iter()
+        .map(|((question_id, sub_question_id), selected)| {
+            json!({
+                "question_id": question_id,
+                "sub_question_id": sub_question_id,
+                "selected_answer": selected,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use firestore::path;
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use serde::Deserialize;
+
+use crate::{
+    common::{
+        database::{Database, MAX_PAGE_SIZE, StoreQuery},
+        error::{AppError, AppResult},
+        export::{ANKI_CSV_HEADER, anki_cards, anki_csv_rows, gift, jsonl_line, qti_package},
+    },
+    models::{claim::AdminClaims, question::Question},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    AnkiCsv,
+    Gift,
+    Qti,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "application/x-ndjson; charset=utf-8",
+            ExportFormat::AnkiCsv => "text/csv; charset=utf-8",
+            ExportFormat::Gift => "text/plain; charset=utf-8",
+            ExportFormat::Qti => "application/zip",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::AnkiCsv => "csv",
+            ExportFormat::Gift => "gift.txt",
+            ExportFormat::Qti => "qti.zip",
+        }
+    }
+
+    /// 1ページ分の問題を書き出す (QTI 以外)
+    fn render(self, questions: &[Question]) -> AppResult<String> {
+        match self {
+            ExportFormat::Jsonl => questions.iter().map(jsonl_line).collect(),
+            ExportFormat::AnkiCsv => {
+                let cards: Vec<_> = questions.iter().flat_map(anki_cards).collect();
+                anki_csv_rows(&cards)
+            }
+            ExportFormat::Gift => Ok(questions.iter().map(gift).collect()),
+            ExportFormat::Qti => Err(AppError::Internal(
+                "QTI はページ単位で書き出せません".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    level_id: Option<u32>,
+    category_id: Option<String>,
+}
+
+/// 条件に一致する問題をドキュメントID順に1ページずつ読む
+fn question_pages(
+    db: Arc<Database>,
+    query: StoreQuery,
+) -> impl Stream<Item = AppResult<Vec<Question>>> {
+    // 状態は次に読むページのカーソル。`None` で終了
+    stream::try_unfold(Some(None::<String>), move |cursor| {
+        let db = db.clone();
+        let query = query.clone();
+        async move {
+            let Some(cursor) = cursor else {
+                return Ok(None);
+            };
+            let page = db
+                .query_page::<Question>("questions", query, cursor.as_deref(), MAX_PAGE_SIZE)
+                .await?;
+            Ok(Some((page.items, page.next_cursor.map(Some))))
+        }
+    })
+}
+
+/// GET /api/admin/questions/export?format=jsonl|anki_csv|gift|qti&level_id=3&category_id=8
+/// 問題バンクを書き出す。QTI 以外はページごとにストリーミングで返す
+pub async fn export_questions(
+    _admin: AdminClaims,
+    Query(params): Query<ExportQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<Response, AppError> {
+    let mut query = StoreQuery::new();
+    if let Some(level_id) = params.level_id {
+        query = query.eq(path!(Question::level_id), level_id);
+    }
+    if let Some(category_id) = &params.category_id {
+        query = query.eq(path!(Question::category_id), category_id);
+    }
+
+    let format = params.format;
+    let pages = question_pages(db, query);
+    let body = match format {
+        ExportFormat::Qti => {
+            let questions: Vec<Question> = pages.try_concat().await?;
+            Body::from(qti_package(&questions)?)
+        }
+        _ => {
+            let header = match format {
+                ExportFormat::AnkiCsv => ANKI_CSV_HEADER,
+                _ => "",
+            };
+            let chunks =
+                pages.map(move |page| page.and_then(|questions| format.render(&questions)));
+            Body::from_stream(stream::once(async move { Ok(header.to_string()) }).chain(chunks))
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"questions.{}\"", format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,457 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use firestore::path;
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    api::{
+        admin::{check_structure, normalize_question},
+        import::BankIndex,
+        revision::{RevisionContext, revision_writes},
+        utils::response_handler,
+    },
+    common::{
+        database::{Database, StoreQuery, WriteOp},
+        error::{AppError, AppResult},
+        llm::{LlmClient, SharedLlm},
+        quality::RuleSet,
+    },
+    models::{
+        claim::AdminClaims,
+        generation::{
+            DEFAULT_TEMPLATE_ID, GenerationJob, JobStatus, PromptTemplate, RejectedCandidate,
+            explanation_prompt, parse_candidates, parse_explanation_drafts,
+        },
+        meta::CatValue,
+        question::{Question, QuestionStatus},
+        revision::{RevisionAction, diff_questions},
+    },
+};
+
+/// 1ジョブで生成できる問題数の上限
+const MAX_GENERATION_COUNT: u32 = 20;
+/// 生成数の既定値
+const DEFAULT_GENERATION_COUNT: u32 = 5;
+/// プロンプトに含める既存の問題の数
+const EXAMPLE_COUNT: usize = 3;
+/// 例を探すときに1回に読む問題の数 (非公開の問題を除いて足りなければ次を読む)
+const EXAMPLE_PAGE_SIZE: u32 = 20;
+
+#[derive(Deserialize)]
+pub struct IdPath {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateRequest {
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DraftQuery {
+    /// true なら既に解説がある小問も書き直す
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartJobRequest {
+    level_id: u32,
+    category_id: u32,
+    count: Option<u32>,
+    /// 省略時は保存済みの `default`、無ければ組み込みのテンプレート
+    template_id: Option<String>,
+}
+
+/// GET /api/admin/prompt-templates
+pub async fn list_templates(
+    _admin: AdminClaims,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut templates = db.read_all::<PromptTemplate>("prompt_templates").await?;
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "templates": templates,
+            "builtin": PromptTemplate::builtin(),
+        })),
+        None,
+    ))
+}
+
+/// PUT /api/admin/prompt-templates/{id}
+/// テンプレートを作成・更新する
+pub async fn put_template(
+    admin: AdminClaims,
+    Path(path): Path<IdPath>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<TemplateRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if body.body.trim().is_empty() {
+        return Err(AppError::Validation("body が空です".to_string()));
+    }
+    let template = PromptTemplate {
+        id: path.id,
+        body: body.body,
+        updated_by: admin.0.user_id,
+        updated_at: chrono::Utc::now().timestamp(),
+    };
+    db.update("prompt_templates", &template.id, &template)
+        .await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(template)),
+        None,
+    ))
+}
+
+async fn resolve_template(db: &Database, template_id: Option<&str>) -> AppResult<PromptTemplate> {
+    match template_id {
+        Some(id) => db
+            .read::<PromptTemplate>("prompt_templates", id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("テンプレートが存在しません: {}", id))),
+        None => Ok(db
+            .read::<PromptTemplate>("prompt_templates", DEFAULT_TEMPLATE_ID)
+            .await?
+            .unwrap_or_else(PromptTemplate::builtin)),
+    }
+}
+
+/// POST /api/admin/generation-jobs
+/// (レベル, カテゴリ) の問題生成ジョブを起動する。生成はバックグラウンドで行い、ジョブを 202 で返す
+pub async fn start_job(
+    admin: AdminClaims,
+    Extension(llm): Extension<SharedLlm>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<StartJobRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let llm = llm.ok_or_else(|| {
+        AppError::Validation("生成AIが設定されていません (GEMINI_API_KEY)".to_string())
+    })?;
+    let count = body.count.unwrap_or(DEFAULT_GENERATION_COUNT);
+    if !(1..=MAX_GENERATION_COUNT).contains(&count) {
+        return Err(AppError::Validation(format!(
+            "count は 1〜{} で指定してください",
+            MAX_GENERATION_COUNT
+        )));
+    }
+
+    let query = StoreQuery::new()
+        .eq(path!(CatValue::level_id), body.level_id)
+        .eq(path!(CatValue::id), body.category_id);
+    let category = db
+        .query::<CatValue>("categories", query)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "カテゴリが存在しません: N{} / {}",
+                body.level_id, body.category_id
+            ))
+        })?;
+    let template = resolve_template(&db, body.template_id.as_deref()).await?;
+
+    let job = GenerationJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        level_id: body.level_id,
+        category_id: body.category_id.to_string(),
+        count,
+        template_id: template.id.clone(),
+        status: JobStatus::Running,
+        created_by: admin.0.user_id,
+        created_at: chrono::Utc::now().timestamp(),
+        finished_at: None,
+        model: Some(llm.model_name().to_string()),
+        pending_ids: vec![],
+        rejected: vec![],
+        error: None,
+    };
+    db.create("generation_jobs", &job.id, &job).await?;
+    info!(
+        "生成ジョブ開始: {} (N{} {} x{})",
+        job.id, job.level_id, category.name, count
+    );
+
+    tokio::spawn(run_job(db.clone(), llm, job.clone(), template, category));
+
+    Ok(response_handler(
+        StatusCode::ACCEPTED,
+        "accepted".to_string(),
+        Some(json!(job)),
+        None,
+    ))
+}
+
+/// ジョブを実行し、結果をジョブのドキュメントに書き込む
+async fn run_job(
+    db: Arc<Database>,
+    llm: Arc<dyn LlmClient>,
+    mut job: GenerationJob,
+    template: PromptTemplate,
+    category: CatValue,
+) {
+    let result = match generate_candidates(&db, llm.as_ref(), &mut job, &template, &category).await
+    {
+        Ok(questions) => question_writes(&db, &job, &questions)
+            .await
+            .map(|writes| (questions, writes)),
+        Err(e) => Err(e),
+    };
+    job.finished_at = Some(chrono::Utc::now().timestamp());
+    let mut writes = Vec::new();
+    match result {
+        Ok((questions, question_writes)) => {
+            job.status = JobStatus::Succeeded;
+            job.pending_ids = questions.into_iter().map(|q| q.id).collect();
+            writes = question_writes;
+        }
+        Err(e) => {
+            error!("生成ジョブ失敗: {} - {}", job.id, e);
+            job.status = JobStatus::Failed;
+            job.error = Some(e.to_string());
+        }
+    }
+
+    match WriteOp::upsert("generation_jobs", &job.id, &job) {
+        Ok(write) => writes.push(write),
+        Err(e) => error!("ジョブのシリアライズに失敗: {}", e),
+    }
+    if let Err(e) = db.commit(writes).await {
+        error!("生成ジョブの保存に失敗: {} - {}", job.id, e);
+        job.status = JobStatus::Failed;
+        job.error = Some(e.to_string());
+        job.pending_ids.clear();
+        if let Err(e) = db.update("generation_jobs", &job.id, &job).await {
+            error!("生成ジョブの保存に失敗: {} - {}", job.id, e);
+        }
+    }
+    info!(
+        "生成ジョブ終了: {} ({:?}, 候補{}件, 除外{}件)",
+        job.id,
+        job.status,
+        job.pending_ids.len(),
+        job.rejected.len()
+    );
+}
+
+/// 候補を `questions` に作成する書き込み。起動した管理者を編集者として `create` の版を記録する
+async fn question_writes(
+    db: &Database,
+    job: &GenerationJob,
+    questions: &[Question],
+) -> AppResult<Vec<WriteOp>> {
+    let ctx = RevisionContext {
+        editor: job.created_by.clone(),
+        reason: Some(format!("生成ジョブ {}", job.id)),
+    };
+    let mut writes = Vec::new();
+    for question in questions {
+        writes.push(WriteOp::create("questions", &question.id, question)?);
+        let (revisions, _) =
+            revision_writes(db, &ctx, RevisionAction::Create, None, question).await?;
+        writes.extend(revisions);
+    }
+    Ok(writes)
+}
+
+/// LLM を呼び出し、構造チェックと重複チェックを通った候補をレビュー待ちの問題として返す。
+/// 除外した候補は `job.rejected` に記録する
+async fn generate_candidates(
+    db: &Database,
+    llm: &dyn LlmClient,
+    job: &mut GenerationJob,
+    template: &PromptTemplate,
+    category: &CatValue,
+) -> AppResult<Vec<Question>> {
+    let level_name = format!("N{}", job.level_id);
+    // 公開中の問題だけを例にする。status の無い旧来の問題 (公開扱い) も含めるため、
+    // 取得してから絞り込み、足りなければ次のページを読む
+    let query = StoreQuery::new()
+        .eq(path!(Question::level_id), job.level_id)
+        .eq(path!(Question::category_id), &job.category_id);
+    let mut examples: Vec<Question> = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = db
+            .query_page::<Question>(
+                "questions",
+                query.clone(),
+                cursor.as_deref(),
+                EXAMPLE_PAGE_SIZE,
+            )
+            .await?;
+        examples.extend(page.items.into_iter().filter(Question::is_published));
+        cursor = page.next_cursor;
+        if examples.len() >= EXAMPLE_COUNT || cursor.is_none() {
+            break;
+        }
+    }
+    examples.truncate(EXAMPLE_COUNT);
+    let prompt = template.render(&level_name, &category.name, job.count, &examples);
+
+    let output = llm.generate(&prompt).await?;
+    let candidates = parse_candidates(&output).map_err(AppError::Upstream)?;
+
+    // レビュー待ちや下書きを含む問題バンクと、同じジョブで先に受理した候補の両方とキーで照合する
+    let mut bank = BankIndex::default();
+    let rules = RuleSet::load(db).await?;
+
+    let mut accepted = Vec::new();
+    for (index, candidate) in candidates.into_iter().enumerate() {
+        let mut question = match candidate {
+            Ok(question) => question,
+            Err(reason) => {
+                job.rejected.push(RejectedCandidate { index, reason });
+                continue;
+            }
+        };
+        question.id = uuid::Uuid::new_v4().to_string();
+        question.level_id = job.level_id;
+        question.level_name = level_name.clone();
+        question.category_id = Some(job.category_id.clone());
+        question.category_name = category.name.clone();
+        question.generated_by = Some(llm.model_name().to_string());
+        question.status = QuestionStatus::PendingReview;
+        question.review = None;
+        normalize_question(&mut question);
+
+        let keys = match check_structure(&question, &rules) {
+            Ok(keys) => keys,
+            Err(AppError::Validation(reason) | AppError::Conflict(reason)) => {
+                job.rejected.push(RejectedCandidate { index, reason });
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some((sub_id, other_id, other_sub)) = bank.lookup(db, &question.id, &keys).await? {
+            job.rejected.push(RejectedCandidate {
+                index,
+                reason: format!(
+                    "小問{}が重複しています: {} (小問{})",
+                    sub_id, other_id, other_sub
+                ),
+            });
+            continue;
+        }
+        bank.insert(&question.id, keys);
+        accepted.push(question);
+    }
+    Ok(accepted)
+}
+
+/// GET /api/admin/generation-jobs/{id}
+pub async fn get_job(
+    _admin: AdminClaims,
+    Path(path): Path<IdPath>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let job = db
+        .read::<GenerationJob>("generation_jobs", &path.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("job not found".to_string()))?;
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(job)),
+        None,
+    ))
+}
+
+/// POST /api/admin/questions/{id}/explanations/draft?overwrite=false
+/// 解説の無い小問の解説を LLM に下書きさせ、反映後の問題と差分を返す。保存はしない。
+/// 管理者が内容を確認して `PUT /api/admin/questions/{id}` で保存する
+pub async fn draft_explanations(
+    _admin: AdminClaims,
+    Extension(llm): Extension<SharedLlm>,
+    Path(path): Path<IdPath>,
+    Query(query): Query<DraftQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let llm = llm.ok_or_else(|| {
+        AppError::Validation("生成AIが設定されていません (GEMINI_API_KEY)".to_string())
+    })?;
+    let current = db
+        .read::<Question>("questions", &path.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+
+    let targets: Vec<_> = current
+        .sub_questions
+        .iter()
+        .filter(|s| query.overwrite || s.explanation.is_none() || s.option_explanations.is_empty())
+        .collect();
+    let mut proposed = current.clone();
+    let mut drafted = Vec::new();
+    if !targets.is_empty() {
+        let output = llm
+            .generate(&explanation_prompt(&current, &targets))
+            .await?;
+        let drafts = parse_explanation_drafts(&output).map_err(AppError::Upstream)?;
+        for draft in drafts {
+            let Some(sub) = proposed
+                .sub_questions
+                .iter_mut()
+                .find(|s| s.id == draft.sub_question_id)
+            else {
+                continue;
+            };
+            if !targets.iter().any(|t| t.id == sub.id) {
+                continue;
+            }
+            let mut changed = false;
+            let explanation = draft.explanation.trim();
+            if !explanation.is_empty() && (query.overwrite || sub.explanation.is_none()) {
+                sub.explanation = Some(explanation.to_string());
+                changed = true;
+            }
+            for (key, text) in draft.option_explanations {
+                let text = text.trim();
+                // 選択肢に無い key は捨てる
+                if text.is_empty() || !sub.select_answer.iter().any(|a| a.key == key) {
+                    continue;
+                }
+                if query.overwrite || !sub.option_explanations.contains_key(&key) {
+                    sub.option_explanations.insert(key, text.to_string());
+                    changed = true;
+                }
+            }
+            if changed && !drafted.contains(&sub.id) {
+                drafted.push(sub.id);
+            }
+        }
+    }
+    drafted.sort_unstable();
+    info!(
+        "解説の下書き: {} (小問 {:?}, {})",
+        current.id,
+        drafted,
+        llm.model_name()
+    );
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "question_id": current.id,
+            "model": llm.model_name(),
+            "drafted": drafted,
+            "changes": diff_questions(Some(&current), &proposed),
+            "proposed": proposed,
+        })),
+        None,
+    ))
+}
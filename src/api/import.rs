@@ -0,0 +1,474 @@
+//! 問題の一括インポート (`POST /api/admin/questions/import`)。
+//!
+//! JSONL (1行1問) または CSV (1行1小問) を受け取り、作成時と同じ構造チェックと
+//! 既存の問題バンクに対する dedup キーの照合を行って、行ごとの判定を返す。
+//! `dry_run` でなければ受理した問題だけをバッチに分けてコミットする。
+//! 取り込んだ問題はファイル内の `status` にかかわらずレビュー待ち (`pending_review`) になる。
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    api::{
+        admin::{check_structure, find_duplicate, normalize_question},
+        revision::{RevisionContext, commit_revision_batch, revision_writes},
+        utils::response_handler,
+    },
+    common::{
+        database::{Database, WriteOp},
+        error::AppError,
+        quality::RuleSet,
+    },
+    models::{
+        claim::AdminClaims,
+        question::{Question, QuestionStatus, QuestionType, SelectAnswer, SubQuestion},
+        revision::RevisionAction,
+    },
+};
+
+/// 1回のコミットに含める問題数。改訂の追記と合わせて Firestore の上限 (500 書き込み) に収める
+const IMPORT_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// 省略時は Content-Type が `text/csv` なら CSV、それ以外は JSONL
+    format: Option<ImportFormat>,
+    #[serde(default)]
+    dry_run: bool,
+    reason: Option<String>,
+}
+
+/// 取り込み対象の1件。`line` は入力の行番号 (CSV は問題の先頭行)
+#[derive(Debug)]
+struct ImportRecord {
+    line: u64,
+    parsed: Result<Question, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(flatten)]
+    outcome: RowOutcome,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum RowOutcome {
+    Accepted,
+    /// 既存の問題 (または同じファイル内の先の行) と重複
+    Duplicate {
+        duplicate_of: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_question_id: Option<u32>,
+        reason: String,
+    },
+    Invalid {
+        reason: String,
+    },
+    /// 受理したがコミットに失敗した
+    Failed {
+        reason: String,
+    },
+}
+
+/// CSV の1行 (1小問)。同じ `id` が連続する行は1つの問題にまとめる
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    #[serde(default)]
+    id: String,
+    level_id: u32,
+    #[serde(default)]
+    level_name: String,
+    category_id: Option<String>,
+    #[serde(default)]
+    category_name: String,
+    #[serde(default)]
+    sentence: String,
+    prerequisites: Option<String>,
+    /// 省略時は `multiple_choice`。文の組み立ては断片を持てないため JSONL で取り込む
+    question_type: Option<QuestionType>,
+    passage: Option<String>,
+    sub_question_id: u32,
+    sub_sentence: Option<String>,
+    sub_prerequisites: Option<String>,
+    option_1: String,
+    option_2: String,
+    option_3: String,
+    option_4: String,
+    answer: String,
+    explanation: Option<String>,
+}
+
+impl CsvRow {
+    fn sub_question(&self) -> SubQuestion {
+        SubQuestion {
+            id: self.sub_question_id,
+            sentence: self.sub_sentence.clone(),
+            prerequisites: self.sub_prerequisites.clone(),
+            select_answer: [
+                &self.option_1,
+                &self.option_2,
+                &self.option_3,
+                &self.option_4,
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| SelectAnswer {
+                key: (i + 1).to_string(),
+                value: value.to_string(),
+            })
+            .collect(),
+            answer: self.answer.clone(),
+            explanation: self.explanation.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn into_question(self) -> Question {
+        Question {
+            sub_questions: vec![self.sub_question()],
+            id: self.id,
+            level_id: self.level_id,
+            level_name: self.level_name,
+            category_id: self.category_id,
+            category_name: self.category_name,
+            sentence: self.sentence,
+            prerequisites: self.prerequisites,
+            question_type: self.question_type.unwrap_or_default(),
+            passage: self.passage.filter(|p| !p.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_jsonl(body: &str) -> Vec<ImportRecord> {
+    body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| ImportRecord {
+            line: i as u64 + 1,
+            parsed: serde_json::from_str::<Question>(line)
+                .map_err(|e| format!("JSON として読めません: {}", e)),
+        })
+        .collect()
+}
+
+fn parse_csv(body: &str) -> Result<Vec<ImportRecord>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::Validation(format!("CSV のヘッダーを読めません: {}", e)))?
+        .clone();
+
+    let mut records: Vec<ImportRecord> = Vec::new();
+    for (i, row) in reader.records().enumerate() {
+        // ヘッダーが1行目
+        let fallback_line = i as u64 + 2;
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                records.push(ImportRecord {
+                    line: e.position().map_or(fallback_line, |p| p.line()),
+                    parsed: Err(format!("CSV として読めません: {}", e)),
+                });
+                continue;
+            }
+        };
+        let line = row.position().map_or(fallback_line, |p| p.line());
+        let row = match row.deserialize::<CsvRow>(Some(&headers)) {
+            Ok(row) => row,
+            Err(e) => {
+                records.push(ImportRecord {
+                    line,
+                    parsed: Err(format!("CSV の列が不正です: {}", e)),
+                });
+                continue;
+            }
+        };
+
+        // 直前の行と同じ問題IDなら小問として追加する
+        if !row.id.is_empty()
+            && let Some(ImportRecord {
+                parsed: Ok(question),
+                ..
+            }) = records.last_mut()
+            && question.id == row.id
+        {
+            question.sub_questions.push(row.sub_question());
+            continue;
+        }
+        records.push(ImportRecord {
+            line,
+            parsed: Ok(row.into_question()),
+        });
+    }
+    Ok(records)
+}
+
+/// 問題バンクの dedup キー索引 (dedup キー -> (問題ID, 小問ID))。
+///
+/// 保存済みの問題は [`lookup`](Self::lookup) でキーごとに引くため、
+/// 索引には同じ取り込みで受理した問題だけを持つ
+#[derive(Debug, Default)]
+pub(crate) struct BankIndex(HashMap<String, (String, u32)>);
+
+impl BankIndex {
+    /// [`check_structure`] が返したキーのうち、索引と重複する最初の小問。
+    /// `(小問ID, 重複先の問題ID, 重複先の小問ID)` を返す
+    fn find(&self, keys: &HashMap<String, u32>) -> Option<(u32, String, u32)> {
+        // 報告を安定させるため小問ID順に照合する
+        let mut keys: Vec<(&String, &u32)> = keys.iter().collect();
+        keys.sort_by_key(|(_, sub_id)| **sub_id);
+        keys.into_iter().find_map(|(key, sub_id)| {
+            self.0
+                .get(key)
+                .map(|(other_id, other_sub)| (*sub_id, other_id.clone(), *other_sub))
+        })
+    }
+
+    /// 索引と保存済みの問題バンクの両方から、最初に重複する小問を探す
+    pub(crate) async fn lookup(
+        &self,
+        db: &Database,
+        question_id: &str,
+        keys: &HashMap<String, u32>,
+    ) -> Result<Option<(u32, String, u32)>, AppError> {
+        let existing = find_duplicate(db, question_id, keys).await?;
+        Ok([existing, self.find(keys)]
+            .into_iter()
+            .flatten()
+            .min_by_key(|(sub_id, _, _)| *sub_id))
+    }
+
+    pub(crate) fn insert(&mut self, question_id: &str, keys: HashMap<String, u32>) {
+        for (key, sub_id) in keys {
+            self.0.insert(key, (question_id.to_string(), sub_id));
+        }
+    }
+}
+
+/// 1件を判定する。受理した場合は索引と ID に追加し、以降の行との重複も検出する
+/// (不正・重複の行の ID は予約しないので、同じ ID の修正した行を後に置ける)
+async fn classify(
+    db: &Database,
+    question: &mut Question,
+    bank: &mut BankIndex,
+    seen_ids: &mut HashSet<String>,
+    rules: &RuleSet,
+) -> Result<RowOutcome, AppError> {
+    if question.id.is_empty() {
+        question.id = uuid::Uuid::new_v4().to_string();
+    } else if seen_ids.contains(&question.id)
+        || db
+            .read::<Question>("questions", &question.id)
+            .await?
+            .is_some()
+    {
+        return Ok(RowOutcome::Duplicate {
+            duplicate_of: question.id.clone(),
+            sub_question_id: None,
+            reason: "問題IDが既に存在します".to_string(),
+        });
+    }
+    normalize_question(question);
+    question.status = QuestionStatus::PendingReview;
+    question.review = None;
+
+    let keys = match check_structure(question, rules) {
+        Ok(keys) => keys,
+        Err(AppError::Validation(reason) | AppError::Conflict(reason)) => {
+            return Ok(RowOutcome::Invalid { reason });
+        }
+        Err(e) => return Err(e),
+    };
+    if let Some((sub_id, other_id, other_sub)) = bank.lookup(db, &question.id, &keys).await? {
+        return Ok(RowOutcome::Duplicate {
+            duplicate_of: other_id,
+            sub_question_id: Some(other_sub),
+            reason: format!("小問{}が重複しています", sub_id),
+        });
+    }
+    bank.insert(&question.id, keys);
+    seen_ids.insert(question.id.clone());
+    Ok(RowOutcome::Accepted)
+}
+
+/// POST /api/admin/questions/import?format=jsonl|csv&dry_run=true&reason=...
+/// 問題を一括で取り込み、行ごとの判定を返す
+pub async fn import_questions(
+    admin: AdminClaims,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    State(db): State<Arc<Database>>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    let format = query.format.unwrap_or_else(|| {
+        let is_csv = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/csv"));
+        if is_csv {
+            ImportFormat::Csv
+        } else {
+            ImportFormat::Jsonl
+        }
+    });
+    let records = match format {
+        ImportFormat::Jsonl => parse_jsonl(&body),
+        ImportFormat::Csv => parse_csv(&body)?,
+    };
+    if records.is_empty() {
+        return Err(AppError::Validation("取り込む問題がありません".to_string()));
+    }
+
+    let mut bank = BankIndex::default();
+    let rules = RuleSet::load(&db).await?;
+    let mut seen_ids = HashSet::new();
+
+    let mut rows: Vec<ImportRow> = Vec::with_capacity(records.len());
+    let mut accepted: Vec<(usize, Question)> = Vec::new();
+    for record in records {
+        let (id, outcome) = match record.parsed {
+            Ok(mut question) => {
+                let outcome =
+                    classify(&db, &mut question, &mut bank, &mut seen_ids, &rules).await?;
+                let id = question.id.clone();
+                if outcome == RowOutcome::Accepted {
+                    accepted.push((rows.len(), question));
+                }
+                (Some(id), outcome)
+            }
+            Err(reason) => (None, RowOutcome::Invalid { reason }),
+        };
+        rows.push(ImportRow {
+            line: record.line,
+            id,
+            outcome,
+        });
+    }
+
+    let mut committed = 0;
+    if !query.dry_run {
+        let ctx = RevisionContext {
+            editor: admin.0.user_id.clone(),
+            reason: query.reason.filter(|r| !r.trim().is_empty()),
+        };
+        for batch in accepted.chunks(IMPORT_BATCH_SIZE) {
+            let result = commit_batch(&db, &ctx, batch).await;
+            match result {
+                Ok(()) => committed += batch.len(),
+                Err(e) => {
+                    error!("Failed to import batch: {}", e);
+                    for (row, _) in batch {
+                        rows[*row].outcome = RowOutcome::Failed {
+                            reason: e.to_string(),
+                        };
+                    }
+                }
+            }
+        }
+        info!("問題をインポート: {}件 (by {})", committed, ctx.editor);
+    }
+
+    let count =
+        |status: fn(&RowOutcome) -> bool| rows.iter().filter(|r| status(&r.outcome)).count();
+    let summary = json!({
+        "total": rows.len(),
+        "accepted": count(|o| matches!(o, RowOutcome::Accepted)),
+        "duplicate": count(|o| matches!(o, RowOutcome::Duplicate { .. })),
+        "invalid": count(|o| matches!(o, RowOutcome::Invalid { .. })),
+        "failed": count(|o| matches!(o, RowOutcome::Failed { .. })),
+    });
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "dry_run": query.dry_run,
+            "committed": committed,
+            "summary": summary,
+            "rows": rows,
+        })),
+        None,
+    ))
+}
+
+/// 問題の作成と `create` の版をまとめてコミットする
+async fn commit_batch(
+    db: &Database,
+    ctx: &RevisionContext,
+    batch: &[(usize, Question)],
+) -> Result<(), AppError> {
+    let mut writes = Vec::with_capacity(batch.len() * 2);
+    for (_, question) in batch {
+        writes.push(WriteOp::create("questions", &question.id, question)?);
+        let (revisions, _) =
+            revision_writes(db, ctx, RevisionAction::Create, None, question).await?;
+        writes.extend(revisions);
+    }
+    commit_revision_batch(db, writes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "id,level_id,category_id,sentence,sub_question_id,sub_sentence,option_1,option_2,option_3,option_4,answer";
+
+    #[test]
+    fn test_parse_csv_groups_consecutive_rows_by_id() {
+        let body = format!(
+            "{}\nq1,3,8,問題,1,文1,あ,い,う,え,1\nq1,3,8,問題,2,文2,か,き,く,け,2\n,3,8,問題,1,\"文,3\",さ,し,す,せ,3\nq2,x,8,問題,1,文,た,ち,つ,て,1\n",
+            HEADER
+        );
+        let records = parse_csv(&body).unwrap();
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].line, 2);
+        let q1 = records[0].parsed.as_ref().unwrap();
+        assert_eq!(q1.sub_questions.len(), 2);
+        assert_eq!(q1.category_id.as_deref(), Some("8"));
+        assert_eq!(q1.sub_questions[1].select_answer[3].value, "け");
+
+        assert_eq!(records[1].line, 4);
+        let q = records[1].parsed.as_ref().unwrap();
+        assert!(q.id.is_empty());
+        assert_eq!(q.sub_questions[0].sentence.as_deref(), Some("文,3"));
+
+        assert_eq!(records[2].line, 5);
+        assert!(records[2].parsed.is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl_skips_blank_lines() {
+        let body = "{\"id\":\"q1\",\"level_id\":3,\"sub_questions\":[]}\n\nnot json\n";
+        let records = parse_jsonl(body);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].parsed.as_ref().unwrap().id, "q1");
+        assert_eq!(records[1].line, 3);
+        assert!(records[1].parsed.is_err());
+    }
+}
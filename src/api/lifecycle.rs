@@ -0,0 +1,229 @@
+//! 問題のライフサイクル (下書き → レビュー待ち → 公開 → 出題終了) を進める管理者API。
+//!
+//! 状態の変更はすべて `update` の版として改訂履歴に残る。内容の編集 (PUT / PATCH) では状態は変わらない。
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use firestore::path;
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    api::{
+        admin::validate_question,
+        revision::{ReasonQuery, RevisionContext, commit_with_revision},
+        utils::{page_response_handler, response_handler},
+    },
+    common::{
+        database::{DEFAULT_PAGE_SIZE, Database, StoreQuery, WriteOp},
+        dedup::question_dedup_keys,
+        error::{AppError, AppResult},
+    },
+    models::{
+        claim::AdminClaims,
+        question::{Question, QuestionStatus, ReviewDecision, ReviewRecord},
+        revision::RevisionAction,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct QuestionPath {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQueueQuery {
+    limit: Option<u32>,
+    cursor: Option<String>,
+    level_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+/// GET /api/admin/review-queue?limit=50&cursor=...&level_id=3
+/// レビュー待ちの問題をID順にカーソルページングで返す
+pub async fn review_queue(
+    _admin: AdminClaims,
+    Query(params): Query<ReviewQueueQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut query = StoreQuery::new().eq(
+        path!(Question::status),
+        QuestionStatus::PendingReview.as_str(),
+    );
+    if let Some(level_id) = params.level_id {
+        query = query.eq(path!(Question::level_id), level_id);
+    }
+    let page = db
+        .query_page::<Question>(
+            "questions",
+            query,
+            params.cursor.as_deref(),
+            params.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(page.items),
+        page.next_cursor,
+    ))
+}
+
+/// POST /api/admin/questions/{id}/submit?reason=...
+/// 下書き (または出題終了) の問題をレビューに回す
+pub async fn submit(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    Query(query): Query<ReasonQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let ctx = RevisionContext::new(&admin, query);
+    let question = transition(&db, &ctx, &path.id, QuestionStatus::PendingReview, None).await?;
+    Ok(question_response(question))
+}
+
+/// POST /api/admin/questions/{id}/approve
+/// レビュー待ちの問題を承認して公開する。承認時にも重複チェックを行う
+pub async fn approve(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<ReviewRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let review = review_record(&admin, ReviewDecision::Approved, body.comment);
+    let ctx = review_context(&admin, &review, "レビュー承認");
+    let question = transition(&db, &ctx, &path.id, QuestionStatus::Published, Some(review)).await?;
+    Ok(question_response(question))
+}
+
+/// POST /api/admin/questions/{id}/reject
+/// レビュー待ちの問題を下書きに差し戻す。`comment` は必須
+pub async fn reject(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<ReviewRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if body.comment.as_deref().is_none_or(|c| c.trim().is_empty()) {
+        return Err(AppError::Validation(
+            "差し戻しには comment が必要です".to_string(),
+        ));
+    }
+    let review = review_record(&admin, ReviewDecision::Rejected, body.comment);
+    let ctx = review_context(&admin, &review, "レビュー差し戻し");
+    let question = transition(&db, &ctx, &path.id, QuestionStatus::Draft, Some(review)).await?;
+    Ok(question_response(question))
+}
+
+/// POST /api/admin/questions/{id}/retire?reason=...
+/// 公開中の問題の出題を終了する (学習者向けの API から見えなくなる)
+pub async fn retire(
+    admin: AdminClaims,
+    Path(path): Path<QuestionPath>,
+    Query(query): Query<ReasonQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let ctx = RevisionContext::new(&admin, query);
+    let question = transition(&db, &ctx, &path.id, QuestionStatus::Retired, None).await?;
+    Ok(question_response(question))
+}
+
+fn review_record(
+    admin: &AdminClaims,
+    decision: ReviewDecision,
+    comment: Option<String>,
+) -> ReviewRecord {
+    ReviewRecord {
+        reviewer: admin.0.user_id.clone(),
+        decision,
+        comment: comment.filter(|c| !c.trim().is_empty()),
+        reviewed_at: chrono::Utc::now().timestamp(),
+    }
+}
+
+/// レビューのコメントを改訂の理由として記録する
+fn review_context(admin: &AdminClaims, review: &ReviewRecord, default: &str) -> RevisionContext {
+    RevisionContext::new(
+        admin,
+        ReasonQuery {
+            reason: Some(
+                review
+                    .comment
+                    .clone()
+                    .unwrap_or_else(|| default.to_string()),
+            ),
+        },
+    )
+}
+
+/// 状態を `next` に進めて保存する。遷移できない場合は 409
+async fn transition(
+    db: &Database,
+    ctx: &RevisionContext,
+    id: &str,
+    next: QuestionStatus,
+    review: Option<ReviewRecord>,
+) -> AppResult<Question> {
+    let previous = db
+        .read::<Question>("questions", id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+    if !previous.status.can_transition_to(next) {
+        return Err(AppError::Conflict(format!(
+            "{} の問題は {} にできません",
+            previous.status.as_str(),
+            next.as_str()
+        )));
+    }
+
+    let mut question = previous.clone();
+    question.status = next;
+    // 索引を持たない既存の問題も、状態を変えるときに索引を付ける
+    question.dedup_keys = question_dedup_keys(&question);
+    if review.is_some() {
+        question.review = review;
+    }
+    if next == QuestionStatus::Published {
+        validate_question(db, &question).await?;
+    }
+    commit_with_revision(
+        db,
+        ctx,
+        RevisionAction::Update,
+        Some(&previous),
+        &question,
+        WriteOp::upsert("questions", &question.id, &question)?,
+    )
+    .await?;
+    info!(
+        "問題の状態を変更: {} {} -> {} (by {})",
+        question.id,
+        previous.status.as_str(),
+        next.as_str(),
+        ctx.editor
+    );
+    Ok(question)
+}
+
+fn question_response(question: Question) -> axum::response::Response {
+    response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(question)),
+        None,
+    )
+    .into_response()
+}
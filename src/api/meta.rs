@@ -6,6 +6,7 @@ use serde_json::json;
 
 use crate::{
     api::utils::{self, response_handler},
+    common::error::AppError,
     models::meta::{CatValue, Meta, Value},
 };
 
@@ -48,27 +49,18 @@ use crate::{
 ///
 /// ## 関連エンドポイント
 /// - `create`: カテゴリ作成エンドポイント
-pub async fn get(State(db): State<Arc<crate::common::database::Database>>) -> impl IntoResponse {
-    let mut levels = db
-        .read_all::<Value>("levels", None)
-        .await
-        .unwrap_or_default();
+pub async fn get(
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut levels = db.read_all::<Value>("levels").await?;
 
     // sort by id
     levels.sort_by_key(|level| level.id);
 
-    let mut categories = db
-        .read_all::<CatValue>("categories", None)
-        .await
-        .unwrap_or_default();
+    let mut categories = db.read_all::<CatValue>("categories").await?;
 
     if categories.is_empty() || levels.is_empty() {
-        return response_handler(
-            StatusCode::NOT_FOUND,
-            "error".to_string(),
-            None,
-            Some("meta data not found".to_string()),
-        );
+        return Err(AppError::NotFound("meta data not found".to_string()));
     }
 
     // sort by name length
@@ -76,12 +68,12 @@ pub async fn get(State(db): State<Arc<crate::common::database::Database>>) -> im
 
     let meta = Meta { levels, categories };
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(json!(meta)),
         None,
-    )
+    ))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -106,9 +98,9 @@ mod tests {
 
     use firestore::path;
     use serde_json::Value;
-    use tokio_stream::StreamExt;
 
     use super::*;
+    use crate::common::database::StoreQuery;
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct NewCatValue {
@@ -120,6 +112,7 @@ mod tests {
 
     /// メタデータのカテゴリに問題数を追加する
     #[tokio::test]
+    #[ignore = "本番 Firestore (.env.local の PROJECT_ID) に書き込むメンテナンス用。手動で --ignored 指定して実行する"]
     async fn test_renew_categories() {
         let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let target_env = PathBuf::from(crate_dir).join(".env.local");
@@ -129,7 +122,7 @@ mod tests {
 
         // CatValuesを取得
         let categories = db
-            .read_all::<CatValue>("categories_raw", None)
+            .read_all::<CatValue>("categories_raw")
             .await
             .unwrap_or_default();
 
@@ -145,49 +138,27 @@ mod tests {
 
         // 各レベルxカテゴリの問題数を取得
         for category in new_categories.iter_mut() {
-            if let Some(count) = category.reten {
-                if count > 0 {
-                    println!(
-                        "level: {}, category: {} - count: {}",
-                        category.level_id, category.id, count
-                    );
-                    continue;
-                }
+            if let Some(count) = category.reten
+                && count > 0
+            {
+                println!(
+                    "level: {}, category: {} - count: {}",
+                    category.level_id, category.id, count
+                );
+                continue;
             }
 
-            let count = match db
-                .client
-                .fluent()
-                .select()
-                .fields(&[path!(crate::models::question::Question::id)])
-                .from("questions")
-                .filter(|x| {
-                    x.for_all([
-                        x.field(path!(crate::models::question::Question::level_id))
-                            .eq(category.level_id),
-                        x.field(path!(crate::models::question::Question::category_id))
-                            .eq(category.id),
-                    ])
-                })
-                .obj::<Value>()
-                .stream_query_with_errors()
-                .await
-            {
-                Ok(mut data) => {
-                    let mut count = 0;
-
-                    while let Some(item) = data.next().await {
-                        match item {
-                            Ok(_) => {
-                                count += 1;
-                            }
-                            Err(e) => {
-                                eprintln!("error: {:?}", e);
-                            }
-                        }
-                    }
-                    count
-                }
+            let query = StoreQuery::new()
+                .eq(
+                    path!(crate::models::question::Question::level_id),
+                    category.level_id,
+                )
+                .eq(
+                    path!(crate::models::question::Question::category_id),
+                    category.id,
+                );
+            let count = match db.query::<Value>("questions", query).await {
+                Ok(data) => data.len() as u32,
                 Err(e) => {
                     eprintln!("error: {:?}", e);
                     0
@@ -204,26 +175,20 @@ mod tests {
 
         // レベルをデータベースに保存
         for category in new_categories.iter() {
-            if let Some(count) = category.reten {
-                if count < 1 {
-                    println!(
-                        "reten is none, level: {}, category: {} - count: {}",
-                        category.level_id, category.id, count
-                    );
-                    continue;
-                }
+            if let Some(count) = category.reten
+                && count < 1
+            {
+                println!(
+                    "reten is none, level: {}, category: {} - count: {}",
+                    category.level_id, category.id, count
+                );
+                continue;
             }
 
             let uid = uuid::Uuid::new_v4().to_string();
 
             match db
-                .client
-                .fluent()
-                .insert()
-                .into("categories")
-                .document_id(uid)
-                .object(category)
-                .execute::<NewCatValue>()
+                .create::<&NewCatValue>("categories", &uid, category)
                 .await
             {
                 Ok(_) => {
@@ -1,10 +1,17 @@
 pub mod admin;
 pub mod answers;
 pub mod evaluate;
+pub mod exam;
+pub mod export;
+pub mod generation;
+pub mod import;
 pub mod initial;
+pub mod lifecycle;
 pub mod meta;
 pub mod monitor;
+pub mod quality;
 pub mod question;
 pub mod report;
+pub mod revision;
 pub mod user;
 pub mod utils;
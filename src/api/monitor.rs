@@ -1,20 +1,29 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum::http::HeaderMap;
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use log::{info, warn};
 use serde::Deserialize;
 use serde_json::json;
-use tokio_stream::StreamExt;
 
 use crate::{
+    api::admin::{cascade_delete_learner_records, delete_with_revision},
+    api::revision::{RevisionContext, commit_with_revision},
     api::utils::response_handler,
-    common::dedup::{dedup_key, KeySkipReason, SubLike},
-    common::similarity::{normalized_similarity, DEFAULT_SIMILARITY_THRESHOLD},
-    models::question::Question,
+    common::database::{StoreQuery, WriteOp},
+    common::dedup::{KeySkipReason, SubLike, dedup_key},
+    common::error::AppError,
+    common::quality::{RuleSet, Severity},
+    common::rich_text::RichText,
+    common::similarity::{DEFAULT_SIMILARITY_THRESHOLD, NearDuplicateIndex},
+    models::question::{Question, QuestionStatus},
+    models::revision::RevisionAction,
 };
 
+/// 隔離・削除を改訂履歴に記録するときの編集者
+const MONITOR_EDITOR: &str = "quality-monitor";
+
 #[derive(Deserialize, Default)]
 pub struct MonitorQuery {
     pub execute: Option<bool>,
@@ -25,55 +34,46 @@ pub struct MonitorQuery {
 /// 認証チェック: AdminClaims JWT または X-Scheduler-Secret ヘッダ
 fn is_authorized(headers: &HeaderMap) -> bool {
     // 1. X-Scheduler-Secret ヘッダによる認証
-    if let Ok(secret) = std::env::var("SCHEDULER_SECRET") {
-        if let Some(header_val) = headers.get("x-scheduler-secret") {
-            if let Ok(val) = header_val.to_str() {
-                if val == secret {
-                    return true;
-                }
-            }
-        }
+    if let Ok(secret) = std::env::var("SCHEDULER_SECRET")
+        && let Some(header_val) = headers.get("x-scheduler-secret")
+        && let Ok(val) = header_val.to_str()
+        && val == secret
+    {
+        return true;
     }
 
     // 2. JWT Bearer トークンによる認証（AdminClaims相当）
     // claim.rs の JWT_SECRET を再利用（unwrap_or_default による空シークレットの脆弱性を修正）
-    if let Some(auth_header) = headers.get("authorization") {
-        if let Ok(val) = auth_header.to_str() {
-            if let Some(token) = val.strip_prefix("Bearer ") {
-                if let Ok(token_data) = jsonwebtoken::decode::<crate::models::claim::Claims>(
-                    token,
-                    &jsonwebtoken::DecodingKey::from_secret(
-                        crate::models::claim::JWT_SECRET.as_bytes(),
-                    ),
-                    &jsonwebtoken::Validation::default(),
-                ) {
-                    if token_data.claims.role.as_deref() == Some("admin") {
-                        return true;
-                    }
-                }
-            }
-        }
+    if let Some(auth_header) = headers.get("authorization")
+        && let Ok(val) = auth_header.to_str()
+        && let Some(token) = val.strip_prefix("Bearer ")
+        && let Ok(token_data) = jsonwebtoken::decode::<crate::models::claim::Claims>(
+            token,
+            &jsonwebtoken::DecodingKey::from_secret(crate::models::claim::JWT_SECRET.as_bytes()),
+            &jsonwebtoken::Validation::default(),
+        )
+        && token_data.claims.role.as_deref() == Some("admin")
+    {
+        return true;
     }
 
     false
 }
 
 /// POST /api/admin/monitor-quality
-/// DB内問題の重複検出・品質レポート・削除
+/// DB内問題の重複検出・品質レポート・削除・隔離
+///
+/// 品質規則 (`common::quality`) の重さに応じて、問題ごとに最も重いものを適用する。
+/// delete は削除、quarantine は公開中の問題をレビュー待ちに戻す、warn は報告のみ
 ///
 /// 認証: Admin JWT または X-Scheduler-Secret ヘッダ
 pub async fn monitor_quality(
     headers: HeaderMap,
     axum::extract::Query(query): axum::extract::Query<MonitorQuery>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     if !is_authorized(&headers) {
-        return response_handler(
-            StatusCode::UNAUTHORIZED,
-            "error".to_string(),
-            None,
-            Some("認証が必要です".to_string()),
-        );
+        return Err(AppError::Unauthorized("認証が必要です".to_string()));
     }
     let execute = query.execute.unwrap_or(false);
     let threshold = query.threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
@@ -83,12 +83,9 @@ pub async fn monitor_quality(
             match n.parse::<u32>() {
                 Ok(id) if (1..=5).contains(&id) => vec![id],
                 _ => {
-                    return response_handler(
-                        StatusCode::BAD_REQUEST,
-                        "error".to_string(),
-                        None,
-                        Some("level は n1〜n5 を指定してください".to_string()),
-                    )
+                    return Err(AppError::Validation(
+                        "level は n1〜n5 を指定してください".to_string(),
+                    ));
                 }
             }
         }
@@ -102,55 +99,50 @@ pub async fn monitor_quality(
         target_levels
     );
 
+    let rules = RuleSet::load(&db).await?;
+
     let mut level_reports = Vec::new();
     let mut all_delete_ids: Vec<String> = Vec::new();
+    // 問題ID -> 隔離の理由となった規則
+    let mut all_quarantine: BTreeMap<String, Vec<&'static str>> = BTreeMap::new();
     let mut total_questions = 0usize;
     let mut total_sub_questions = 0usize;
     let mut total_exact = 0usize;
-    let mut total_similar = 0usize;
     let mut total_malformed = 0usize;
+    let mut total_quarantine = 0usize;
+    let mut total_warnings = 0usize;
+    let mut total_by_rule: BTreeMap<&'static str, usize> = BTreeMap::new();
     let mut total_skipped_numeric = 0usize;
     let mut total_skipped_no_answer = 0usize;
 
+    // DB全問題取得 (類似はレベルをまたいで探すため先に全レベル分を読む)
+    let mut levels: Vec<(u32, Vec<Question>)> = Vec::new();
     for level_id in &target_levels {
-        // DB全問題取得
-        let questions: Vec<Question> = match db
-            .client
-            .fluent()
-            .select()
-            .from("questions")
-            .filter(|q| q.field(firestore::path!(Question::level_id)).eq(*level_id))
-            .obj::<Question>()
-            .stream_query_with_errors()
-            .await
-        {
-            Ok(stream) => {
-                let mut items = Vec::new();
-                let mut stream = stream;
-                while let Some(item) = stream.next().await {
-                    match item {
-                        Ok(q) => items.push(q),
-                        Err(e) => warn!("N{} ドキュメント読取エラー: {}", level_id, e),
-                    }
-                }
-                items
-            }
-            Err(e) => {
-                warn!("N{} クエリエラー: {}", level_id, e);
-                continue;
-            }
-        };
+        let query = StoreQuery::new().eq(firestore::path!(Question::level_id), *level_id);
+        match db.query::<Question>("questions", query).await {
+            Ok(items) => levels.push((*level_id, items)),
+            Err(e) => warn!("N{} クエリエラー: {}", level_id, e),
+        }
+    }
 
+    let (mut level_similar, cross_level_similar) = find_similar(&levels, threshold);
+    let total_similar_cross_level = cross_level_similar.len();
+    let mut total_similar = total_similar_cross_level;
+
+    for (level_id, questions) in &levels {
         let level_q_count = questions.len();
         total_questions += level_q_count;
 
-        // カテゴリ別グルーピング (similar 検出用、カテゴリ内の sentence 比較)
-        let mut category_groups: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        // カテゴリ別の小問数 (文が空でないもの)
+        let mut category_groups: HashMap<String, usize> = HashMap::new();
         let mut category_names: HashMap<String, String> = HashMap::new();
         let mut level_sub_count = 0usize;
         let mut answer_dist = [0usize; 4];
+        // 出題形式別の問題数
+        let mut type_counts: BTreeMap<&str, usize> = BTreeMap::new();
 
-        for q in &questions {
+        for q in questions {
+            *type_counts.entry(q.question_type.as_str()).or_default() += 1;
             let cat_id = q.category_id.clone().unwrap_or_default();
             category_names
                 .entry(cat_id.clone())
@@ -159,95 +151,78 @@ pub async fn monitor_quality(
             for sub_q in &q.sub_questions {
                 level_sub_count += 1;
 
-                if let Ok(ans) = sub_q.answer.parse::<usize>() {
-                    if (1..=4).contains(&ans) {
-                        answer_dist[ans - 1] += 1;
-                    }
+                if let Ok(ans) = sub_q.answer.parse::<usize>()
+                    && (1..=4).contains(&ans)
+                {
+                    answer_dist[ans - 1] += 1;
                 }
 
-                let sentence = sub_q
-                    .sentence
-                    .as_deref()
-                    .unwrap_or("")
-                    .trim()
-                    .to_string();
-
-                if !sentence.is_empty() {
-                    category_groups
-                        .entry(cat_id.clone())
-                        .or_default()
-                        .push((q.id.clone(), sentence));
+                if !sub_q.sentence.as_deref().unwrap_or("").trim().is_empty() {
+                    *category_groups.entry(cat_id.clone()).or_default() += 1;
                 }
             }
         }
 
         total_sub_questions += level_sub_count;
 
-        // 品質異常検出（空括弧、選択肢異常、正解キー不在等）
+        // 品質規則の検出。問題ごとに最も重い規則で削除・隔離・警告に振り分ける
         let mut malformed_details = Vec::new();
+        let mut quarantine_details = Vec::new();
+        let mut warning_details = Vec::new();
         let mut malformed_ids: HashSet<String> = HashSet::new();
+        let mut by_rule: BTreeMap<&'static str, usize> = BTreeMap::new();
 
-        for q in &questions {
+        for q in questions {
+            let findings = rules.check(q, Severity::Warn);
+            let Some(worst) = findings.iter().map(|f| f.severity).max() else {
+                continue;
+            };
             let mut issues: Vec<String> = Vec::new();
-            let cat_id_num = q.category_id.as_deref().unwrap_or("0")
-                .parse::<u32>().unwrap_or(0);
-
-            for sub_q in &q.sub_questions {
-                let sentence = sub_q.sentence.as_deref().unwrap_or("").trim();
-
-                // 空括弧チェック — 漢字読み(2)・表記(3)のみ対象
-                // 文脈規定(4)・文法(8)等の穴埋め問題では（　　）は正常
-                let has_empty_parens = sentence.contains("（　　）")
-                    || sentence.contains("（）")
-                    || sentence.contains("（  ）")
-                    || sentence.contains("（ ）");
-                if has_empty_parens && (cat_id_num == 2 || cat_id_num == 3) {
-                    issues.push("空括弧(読み/表記)".to_string());
-                }
-
-                // 選択肢数チェック
-                if sub_q.select_answer.len() != 4 {
-                    issues.push(format!("選択肢{}個", sub_q.select_answer.len()));
+            for finding in &findings {
+                *by_rule.entry(finding.rule).or_default() += 1;
+                let issue = finding.to_string();
+                if !issues.contains(&issue) {
+                    issues.push(issue);
                 }
-
-                // 正解キー存在チェック
-                let answer_exists = sub_q
-                    .select_answer
-                    .iter()
-                    .any(|sa| sa.key == sub_q.answer);
-                if !answer_exists {
-                    issues.push("正解キー不在".to_string());
-                }
-
-                // 空の選択肢チェック
-                let empty_choices = sub_q
-                    .select_answer
-                    .iter()
-                    .filter(|sa| sa.value.trim().is_empty())
-                    .count();
-                if empty_choices > 0 {
-                    issues.push(format!("空選択肢{}個", empty_choices));
+            }
+            let detail = json!({
+                "question_id": q.id,
+                "question_type": q.question_type.as_str(),
+                "category_id": q.category_id,
+                "category_name": q.category_name,
+                "status": q.status.as_str(),
+                "issues": issues,
+                "findings": findings,
+            });
+            match worst {
+                Severity::Delete => {
+                    malformed_details.push(detail);
+                    malformed_ids.insert(q.id.clone());
                 }
-
-                // 空のsentenceチェック
-                if sentence.is_empty() {
-                    issues.push("空問題文".to_string());
+                Severity::Quarantine => {
+                    quarantine_details.push(detail);
+                    // 公開中でなければ学習者に出ないため状態は変えない
+                    if q.status == QuestionStatus::Published {
+                        let mut rule_ids: Vec<&'static str> = findings
+                            .iter()
+                            .filter(|f| f.severity == Severity::Quarantine)
+                            .map(|f| f.rule)
+                            .collect();
+                        rule_ids.sort_unstable();
+                        rule_ids.dedup();
+                        all_quarantine.insert(q.id.clone(), rule_ids);
+                    }
                 }
-            }
-
-            if !issues.is_empty() {
-                let unique_issues: HashSet<String> = issues.into_iter().collect();
-                malformed_details.push(json!({
-                    "question_id": q.id,
-                    "category_id": q.category_id,
-                    "category_name": q.category_name,
-                    "issues": unique_issues.into_iter().collect::<Vec<_>>(),
-                }));
-                malformed_ids.insert(q.id.clone());
+                Severity::Warn => warning_details.push(detail),
             }
         }
 
         total_malformed += malformed_details.len();
+        total_quarantine += quarantine_details.len();
+        total_warnings += warning_details.len();
+        for (rule, count) in &by_rule {
+            *total_by_rule.entry(rule).or_default() += count;
+        }
 
         // ─────────────────────────────────────────────────────────────
         // exact 重複検出: common::dedup::dedup_key (NFKC正規化 + 選択肢セット+正解 完全一致)
@@ -263,7 +238,7 @@ pub async fn monitor_quality(
         // dedup_key -> (parent_id, sub_idx, sentence) の最初に出現したレコードを記録
         let mut seen_keys: HashMap<String, (String, u32, String)> = HashMap::new();
 
-        for q in &questions {
+        for q in questions {
             for sub_q in &q.sub_questions {
                 let sub_like = SubLike {
                     options: sub_q
@@ -308,44 +283,8 @@ pub async fn monitor_quality(
         total_skipped_numeric += skipped_numeric;
         total_skipped_no_answer += skipped_no_answer;
 
-        // ─────────────────────────────────────────────────────────────
-        // similar 検出 (警告のみ・削除しない): カテゴリ内 Levenshtein ≥ threshold
-        // ─────────────────────────────────────────────────────────────
-        let mut similar_details = Vec::new();
-
-        for (_cat_id, items) in &category_groups {
-            let mut seen: Vec<usize> = Vec::new();
-
-            for (idx, (doc_id, sentence)) in items.iter().enumerate() {
-                let s_len = sentence.chars().count();
-                let similar_hit = seen.iter().find(|&&oi| {
-                    let orig_s = &items[oi].1;
-                    let o_len = orig_s.chars().count();
-                    let ratio = s_len.min(o_len) as f64 / s_len.max(o_len).max(1) as f64;
-                    if ratio < 0.8 {
-                        return false;
-                    }
-                    let sim = normalized_similarity(sentence, orig_s);
-                    sim >= threshold && sim < 1.0
-                });
-
-                if let Some(&orig_idx) = similar_hit {
-                    let sim = normalized_similarity(sentence, &items[orig_idx].1);
-                    similar_details.push(json!({
-                        "type": "similar",
-                        "similarity": format!("{:.0}%", sim * 100.0),
-                        "question_id_a": items[orig_idx].0,
-                        "question_id_b": doc_id,
-                        "sentence_a": items[orig_idx].1,
-                        "sentence_b": sentence,
-                    }));
-                    // delete_ids には追加しない (警告のみ)
-                }
-
-                seen.push(idx);
-            }
-        }
-
+        // similar 検出 (警告のみ・削除しない): このレベル内の組 (カテゴリをまたぐものを含む)
+        let similar_details = level_similar.remove(level_id).unwrap_or_default();
         let similar_count = similar_details.len();
         total_similar += similar_count;
 
@@ -365,9 +304,9 @@ pub async fn monitor_quality(
         // カテゴリ別集計
         let mut categories: Vec<serde_json::Value> = category_groups
             .iter()
-            .map(|(cat_id, items)| {
+            .map(|(cat_id, count)| {
                 let name = category_names.get(cat_id).cloned().unwrap_or_default();
-                json!({ "id": cat_id, "name": name, "sub_question_count": items.len() })
+                json!({ "id": cat_id, "name": name, "sub_question_count": count })
             })
             .collect();
         categories.sort_by_key(|c| {
@@ -384,9 +323,13 @@ pub async fn monitor_quality(
             "level": format!("N{}", level_id),
             "questions": level_q_count,
             "sub_questions": level_sub_count,
+            "question_types": type_counts,
             "duplicates_exact": exact_count,
             "duplicates_similar": similar_count,
             "malformed": malformed_details.len(),
+            "quarantine": quarantine_details.len(),
+            "warnings": warning_details.len(),
+            "findings_by_rule": by_rule,
             "skipped_numeric_placeholder": skipped_numeric,
             "skipped_answer_not_in_options": skipped_no_answer,
             "answer_distribution": dist,
@@ -394,6 +337,8 @@ pub async fn monitor_quality(
             "exact_details": exact_details,
             "similar_details": similar_details,
             "malformed_details": malformed_details,
+            "quarantine_details": quarantine_details,
+            "warning_details": warning_details,
         }));
     }
 
@@ -408,28 +353,55 @@ pub async fn monitor_quality(
     };
 
     if execute && !unique_delete.is_empty() {
+        // 管理者の削除と同じく、削除直前の内容を改訂履歴に残し、学習記録を連鎖削除する
+        let ctx = RevisionContext {
+            editor: MONITOR_EDITOR.to_string(),
+            reason: Some("品質監視で削除".to_string()),
+        };
+        let mut deleted_ids = Vec::new();
         for qid in &unique_delete {
-            match db.delete("questions", qid).await {
+            match delete_with_revision(&db, &ctx, qid).await {
                 Ok(_) => {
                     deleted_count += 1;
+                    deleted_ids.push(qid.clone());
                     info!("削除: {}", qid);
                 }
                 Err(e) => warn!("削除失敗 {}: {}", qid, e),
             }
         }
+        cascade_delete_learner_records(db.clone(), deleted_ids).await;
         info!("{}件削除完了", deleted_count);
     }
 
+    // 隔離実行 (削除した問題は除く)。公開中の問題をレビュー待ちに戻し、改訂履歴に残す
+    all_quarantine.retain(|id, _| !unique_delete.contains(id));
+    let mut quarantined_count = 0usize;
+    if execute {
+        for (qid, rule_ids) in &all_quarantine {
+            match quarantine(&db, qid, rule_ids).await {
+                Ok(true) => {
+                    quarantined_count += 1;
+                    info!("隔離: {} ({})", qid, rule_ids.join(", "));
+                }
+                Ok(false) => {}
+                Err(e) => warn!("隔離失敗 {}: {}", qid, e),
+            }
+        }
+    }
+
     info!(
-        "品質監視完了: questions={}, sub_questions={}, exact={}, similar={} (warn-only), malformed={}, skipped(numeric={}, no_answer={}), deleted={}",
+        "品質監視完了: questions={}, sub_questions={}, exact={}, similar={} (warn-only), malformed={}, quarantine={}, warnings={}, skipped(numeric={}, no_answer={}), deleted={}, quarantined={}",
         total_questions,
         total_sub_questions,
         total_exact,
         total_similar,
         total_malformed,
+        total_quarantine,
+        total_warnings,
         total_skipped_numeric,
         total_skipped_no_answer,
-        deleted_count
+        deleted_count,
+        quarantined_count
     );
 
     let response_data = json!({
@@ -438,27 +410,140 @@ pub async fn monitor_quality(
             "total_sub_questions": total_sub_questions,
             "duplicates_exact": total_exact,
             "duplicates_similar": total_similar,
+            "duplicates_similar_cross_level": total_similar_cross_level,
             "malformed": total_malformed,
+            "quarantine": total_quarantine,
+            "warnings": total_warnings,
+            "findings_by_rule": total_by_rule,
             "skipped_numeric_placeholder": total_skipped_numeric,
             "skipped_answer_not_in_options": total_skipped_no_answer,
             "delete_targets": unique_delete.len(),
             "deleted": deleted_count,
+            "quarantine_targets": all_quarantine.len(),
+            "quarantined": quarantined_count,
             "executed": execute,
             "dedup_logic": "common::dedup (NFKC + sorted options + answer)",
+            "similar_logic": "common::similarity (MinHash/LSH candidates + Levenshtein, across categories and levels)",
             "similar_policy": "warning only (not deleted)",
+            "quality_rule_policy": "delete: deleted / quarantine: published -> pending_review / warn: warning only",
         },
         "levels": level_reports,
+        "cross_level_similar_details": cross_level_similar,
     });
 
     // Discord Webhook通知
     notify_discord(&response_data).await;
 
-    response_handler(
+    Ok(response_handler(
         StatusCode::OK,
         "success".to_string(),
         Some(response_data),
         None,
+    ))
+}
+
+/// 類似検出の対象となる小問
+struct SimilarItem<'a> {
+    level_id: u32,
+    question: &'a Question,
+    sub_id: u32,
+    sentence: String,
+}
+
+/// 全レベルの小問の文から類似の組を探す (警告のみ)。
+///
+/// MinHash/LSH で候補を絞り、Levenshtein の類似度が `threshold` 以上 (完全一致を除く) の組を返す。
+/// 各小問は先に現れた最初の類似の小問とだけ組にする。
+/// 戻り値は (レベル -> そのレベル内の組, レベルをまたぐ組)
+fn find_similar(
+    levels: &[(u32, Vec<Question>)],
+    threshold: f64,
+) -> (HashMap<u32, Vec<serde_json::Value>>, Vec<serde_json::Value>) {
+    let mut index = NearDuplicateIndex::new();
+    let mut items = Vec::new();
+    for (level_id, questions) in levels {
+        for q in questions {
+            for sub_q in &q.sub_questions {
+                // 類似度は装飾を除いた文で比べる
+                let sentence =
+                    RichText::parse(sub_q.sentence.as_deref().unwrap_or("").trim()).to_plain();
+                if sentence.is_empty() {
+                    continue;
+                }
+                index.insert(&sentence);
+                items.push(SimilarItem {
+                    level_id: *level_id,
+                    question: q,
+                    sub_id: sub_q.id,
+                    sentence,
+                });
+            }
+        }
+    }
+
+    let mut by_level: HashMap<u32, Vec<serde_json::Value>> = HashMap::new();
+    let mut cross_level = Vec::new();
+    for dup in index.near_duplicates(threshold) {
+        let (a, b) = (&items[dup.a], &items[dup.b]);
+        let scope = if a.level_id != b.level_id {
+            "cross_level"
+        } else if a.question.category_id != b.question.category_id {
+            "cross_category"
+        } else {
+            "category"
+        };
+        let detail = json!({
+            "type": "similar",
+            "scope": scope,
+            "similarity": format!("{:.0}%", dup.similarity * 100.0),
+            "question_id_a": a.question.id,
+            "sub_id_a": a.sub_id,
+            "level_a": format!("N{}", a.level_id),
+            "category_id_a": a.question.category_id,
+            "sentence_a": a.sentence,
+            "question_id_b": b.question.id,
+            "sub_id_b": b.sub_id,
+            "level_b": format!("N{}", b.level_id),
+            "category_id_b": b.question.category_id,
+            "sentence_b": b.sentence,
+        });
+        if a.level_id == b.level_id {
+            by_level.entry(a.level_id).or_default().push(detail);
+        } else {
+            cross_level.push(detail);
+        }
+    }
+    (by_level, cross_level)
+}
+
+/// 公開中の問題をレビュー待ちに戻す。公開中でなくなっていれば何もせず false
+async fn quarantine(
+    db: &crate::common::database::Database,
+    id: &str,
+    rule_ids: &[&str],
+) -> Result<bool, AppError> {
+    let Some(previous) = db.read::<Question>("questions", id).await? else {
+        return Ok(false);
+    };
+    if previous.status != QuestionStatus::Published {
+        return Ok(false);
+    }
+    let mut question = previous.clone();
+    question.status = QuestionStatus::PendingReview;
+    let ctx = RevisionContext {
+        editor: MONITOR_EDITOR.to_string(),
+        reason: Some(format!("品質監視で隔離: {}", rule_ids.join(", "))),
+    };
+    commit_with_revision(
+        db,
+        &ctx,
+        RevisionAction::Update,
+        Some(&previous),
+        &question,
+        WriteOp::upsert("questions", &question.id, &question)?,
     )
+    .await?;
+    Ok(true)
 }
 
 /// Discord Webhookにレポートを送信
@@ -476,9 +561,17 @@ async fn notify_discord(data: &serde_json::Value) {
     let total_sub = summary["total_sub_questions"].as_u64().unwrap_or(0);
     let exact = summary["duplicates_exact"].as_u64().unwrap_or(0);
     let similar = summary["duplicates_similar"].as_u64().unwrap_or(0);
+    let similar_cross_level = summary["duplicates_similar_cross_level"]
+        .as_u64()
+        .unwrap_or(0);
     let malformed = summary["malformed"].as_u64().unwrap_or(0);
+    let quarantine = summary["quarantine"].as_u64().unwrap_or(0);
+    let warnings = summary["warnings"].as_u64().unwrap_or(0);
+    let quarantined = summary["quarantined"].as_u64().unwrap_or(0);
     let skipped_numeric = summary["skipped_numeric_placeholder"].as_u64().unwrap_or(0);
-    let skipped_no_answer = summary["skipped_answer_not_in_options"].as_u64().unwrap_or(0);
+    let skipped_no_answer = summary["skipped_answer_not_in_options"]
+        .as_u64()
+        .unwrap_or(0);
     let deleted = summary["deleted"].as_u64().unwrap_or(0);
     let executed = summary["executed"].as_bool().unwrap_or(false);
 
@@ -492,14 +585,15 @@ async fn notify_discord(data: &serde_json::Value) {
             let ex = lv["duplicates_exact"].as_u64().unwrap_or(0);
             let si = lv["duplicates_similar"].as_u64().unwrap_or(0);
             let mal = lv["malformed"].as_u64().unwrap_or(0);
+            let qua = lv["quarantine"].as_u64().unwrap_or(0);
             let dist = &lv["answer_distribution"];
             let d1 = dist["1"].as_str().unwrap_or("-");
             let d2 = dist["2"].as_str().unwrap_or("-");
             let d3 = dist["3"].as_str().unwrap_or("-");
             let d4 = dist["4"].as_str().unwrap_or("-");
             level_lines.push(format!(
-                "**{}** : {}問 (sub:{}) | 完全一致:{} 類似:{} 不良:{} | 正解: {}/{}/{}/{}",
-                name, q, sub, ex, si, mal, d1, d2, d3, d4
+                "**{}** : {}問 (sub:{}) | 完全一致:{} 類似:{} 不良:{} 隔離:{} | 正解: {}/{}/{}/{}",
+                name, q, sub, ex, si, mal, qua, d1, d2, d3, d4
             ));
         }
     }
@@ -542,7 +636,7 @@ async fn notify_discord(data: &serde_json::Value) {
                 },
                 {
                     "name": "類似(警告のみ)",
-                    "value": format!("{}件 [削除しない]", similar),
+                    "value": format!("{}件 (うちレベル横断 {}件) [削除しない]", similar, similar_cross_level),
                     "inline": true
                 },
                 {
@@ -550,6 +644,20 @@ async fn notify_discord(data: &serde_json::Value) {
                     "value": format!("{}件 [削除対象]", malformed),
                     "inline": true
                 },
+                {
+                    "name": "隔離",
+                    "value": if executed {
+                        format!("{}件 [レビュー待ちに戻した: {}件]", quarantine, quarantined)
+                    } else {
+                        format!("{}件 [公開中ならレビュー待ちに戻す]", quarantine)
+                    },
+                    "inline": true
+                },
+                {
+                    "name": "規則の警告",
+                    "value": format!("{}件 [削除しない]", warnings),
+                    "inline": true
+                },
                 {
                     "name": "削除",
                     "value": if executed {
@@ -0,0 +1,136 @@
+//! 品質規則の設定を管理する API。
+//!
+//! 設定は Firestore の `quality_rules` に保存し、品質監視と問題の作成・取り込み・生成時の検証で読む。
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    api::utils::response_handler,
+    common::{
+        database::Database,
+        error::AppError,
+        quality::{REGISTRY, RuleConfig, RuleSet, Severity, validate_config},
+    },
+    models::{claim::AdminClaims, quality_rule::QUALITY_RULES_COLLECTION},
+};
+
+#[derive(Deserialize)]
+pub struct IdPath {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuleConfigRequest {
+    rule: String,
+    enabled: Option<bool>,
+    severity: Option<Severity>,
+    #[serde(default)]
+    level_ids: Vec<u32>,
+    #[serde(default)]
+    category_ids: Vec<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+/// GET /api/admin/quality-rules
+/// 登録済みの規則 (既定値) と、ファイル・Firestore から読んだ有効な設定を返す
+pub async fn list_rules(
+    _admin: AdminClaims,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let rules: Vec<Value> = REGISTRY
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "description": rule.description(),
+                "default_severity": rule.default_severity(),
+                "default_category_ids": rule.default_categories(),
+                "default_params": rule.default_params(),
+            })
+        })
+        .collect();
+    let rule_set = RuleSet::load(&db).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "rules": rules,
+            "configs": rule_set.configs(),
+        })),
+        None,
+    ))
+}
+
+/// PUT /api/admin/quality-rules/{id}
+/// 規則の設定を作成・更新する
+pub async fn put_rule(
+    admin: AdminClaims,
+    Path(path): Path<IdPath>,
+    State(db): State<Arc<Database>>,
+    Json(body): Json<RuleConfigRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if body.level_ids.iter().any(|id| !(1..=5).contains(id)) {
+        return Err(AppError::Validation(
+            "level_ids は 1〜5 で指定してください".to_string(),
+        ));
+    }
+    let config = RuleConfig {
+        id: path.id,
+        rule: body.rule,
+        enabled: body.enabled,
+        severity: body.severity,
+        level_ids: body.level_ids,
+        category_ids: body.category_ids,
+        params: body.params,
+        updated_by: admin.0.user_id,
+        updated_at: chrono::Utc::now().timestamp(),
+    };
+    validate_config(&config).map_err(AppError::Validation)?;
+    db.update(QUALITY_RULES_COLLECTION, &config.id, &config)
+        .await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!(config)),
+        None,
+    ))
+}
+
+/// DELETE /api/admin/quality-rules/{id}
+/// 設定を削除する (規則は既定値に戻る)
+pub async fn delete_rule(
+    _admin: AdminClaims,
+    Path(path): Path<IdPath>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    if db
+        .read::<RuleConfig>(QUALITY_RULES_COLLECTION, &path.id)
+        .await?
+        .is_none()
+    {
+        return Err(AppError::NotFound(format!(
+            "設定が存在しません: {}",
+            path.id
+        )));
+    }
+    db.delete(QUALITY_RULES_COLLECTION, &path.id).await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({ "id": path.id })),
+        None,
+    ))
+}
@@ -1,18 +1,32 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
-use firestore::{FirestoreQueryDirection, path};
+use firestore::path;
 use log::info;
 use serde::Deserialize;
 use serde_json::json;
 
-use tokio_stream::StreamExt;
-
-use crate::{api::utils::response_handler, models::question::Question};
+use crate::{
+    api::utils::{page_response_handler, response_handler},
+    common::{
+        database::{DEFAULT_PAGE_SIZE, Database, StoreQuery},
+        error::{AppError, AppResult},
+        furigana::{FuriganaMode, furigana_question},
+        quality::RuleSet,
+        rich_text::{RenderMode, render},
+        shuffle::{ShuffleToken, shuffle_question},
+    },
+    models::{
+        claim::Claims,
+        hint::{HintStep, HintUsage, hint_ladder},
+        question::{PublicQuestion, Question},
+        rating::{ItemRating, UserAbility, select_adaptive},
+    },
+};
 
 #[derive(Deserialize)]
 pub struct PathParams {
@@ -23,6 +37,54 @@ pub struct PathParams {
 #[derive(Deserialize)]
 pub struct QueryParams {
     limit: Option<u32>,
+    page_size: Option<u32>,
+    cursor: Option<String>,
+    #[serde(default)]
+    shuffle: bool,
+    #[serde(default)]
+    mode: SelectionMode,
+    #[serde(default)]
+    render: RenderMode,
+    #[serde(default)]
+    furigana: FuriganaMode,
+}
+
+/// `limit` 指定時の選び方
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+    /// 全件から一様にランダム
+    #[default]
+    Random,
+    /// 学習者の推定能力に近い難易度から選ぶ
+    Adaptive,
+}
+
+/// 適応出題で `limit` 未指定のときの件数
+const DEFAULT_ADAPTIVE_LIMIT: u32 = 10;
+
+#[derive(Deserialize)]
+pub struct ShuffleParams {
+    #[serde(default)]
+    shuffle: bool,
+    #[serde(default)]
+    render: RenderMode,
+    #[serde(default)]
+    furigana: FuriganaMode,
+}
+
+#[derive(Deserialize)]
+pub struct HintPath {
+    id: String,
+    sub_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct HintQuery {
+    /// 何段目までのヒントを返すか (既定 1)
+    level: Option<u32>,
+    /// `?shuffle=true` で配信された問題の `shuffle_token`。除外する選択肢の key を表示キーで返す
+    shuffle_token: Option<String>,
 }
 
 /// # get
@@ -33,14 +95,20 @@ pub struct QueryParams {
 /// ## HTTP情報
 /// - **メソッド**: GET
 /// - **パス**: /api/levels/{level_id: u32}/categories/{category_id: u32}/questions
-/// - **認証**: 不要
+/// - **認証**: 不要 (Admin の場合のみ正解・解説を含む全フィールドと、公開前・出題終了の問題も返す)
 ///
 /// ## パラメータ
 /// - `level_id`: レベルID (u32) - レベルを指定するID
 /// - `category_id`: カテゴリID (u32) - カテゴリを指定するID
 ///
 /// ## クエリ
-/// - `limit`: 取得する問題数 (u32) - 取得する問題数を指定する
+/// - `limit`: 取得する問題数 (u32) - 全件からランダムに指定数だけ返す
+/// - `page_size`: 1ページの件数 (u32) - 指定時はID順のカーソルページングで返す
+/// - `cursor`: 前ページの `next_cursor` (String) - 続きのページを取得する
+/// - `shuffle`: 選択肢を並べ替えて配信する (bool) - 各問題に `shuffle_token` が付く (Admin には適用しない)
+/// - `mode`: `random` (既定) / `adaptive` - `adaptive` は推定能力に合う難易度の問題を `limit` 件 (既定 10) 選ぶ
+/// - `render`: `markup` (既定) / `html` / `plain` - 問題文・本文・選択肢の文字装飾の形式 (Admin には適用しない)
+/// - `furigana`: `none` (既定) / `above_level` / `all` - 漢字にふりがなを付ける。`above_level` は問題のレベルより難しい漢字を含む語だけ (Admin には適用しない)
 ///
 /// ## レスポンス
 /// ### 成功時
@@ -51,7 +119,8 @@ pub struct QueryParams {
 ///   {
 ///     "status": "success",
 ///     "message": "success",
-///     "data": [Quesion{}...]
+///     "data": [PublicQuestion{}...],
+///     "next_cursor": "eyJpZCI6Ij..." // 続きがない場合は null
 ///   }
 ///   ```
 ///
@@ -63,13 +132,17 @@ pub struct QueryParams {
 /// GET /api/levels/1/categories/1/questions
 ///
 /// ## 関連エンドポイント
-/// - `get_answer`: 回答取得エンドポイント
+/// - `answers::record_answer`: 回答の採点 (正解・解説を返す)
 /// - `get_hint`: ヒント取得エンドポイント
 pub async fn get(
+    claims: Option<Claims>,
     Path(path_params): Path<PathParams>,
     Query(query_params): Query<QueryParams>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
+    let full_view = claims.as_ref().is_some_and(|c| c.is_admin());
+    let rules = furigana_rules(&db, full_view, query_params.furigana).await?;
+
     // level_idを受けて、そのレベルに紐づくカテゴリー群を取得する
     info!(
         "level_id: {}, category_id: {}, limit: {}",
@@ -78,21 +151,45 @@ pub async fn get(
         query_params.limit.unwrap_or_default()
     );
 
+    // page_size / cursor 指定時はID順にページングして返す。
+    // 非公開の問題はページ取得後に除くため、1ページの件数が page_size より少ないことがある
+    if query_params.page_size.is_some() || query_params.cursor.is_some() {
+        let mut page = db
+            .query_page::<Question>(
+                "questions",
+                category_query(&path_params),
+                query_params.cursor.as_deref(),
+                query_params.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+            )
+            .await?;
+        if !full_view {
+            page.items.retain(Question::is_published);
+        }
+        if page.items.is_empty() && page.next_cursor.is_none() && query_params.cursor.is_none() {
+            return Err(not_found(&path_params));
+        }
+        return Ok(page_response_handler(
+            StatusCode::OK,
+            "ok".to_string(),
+            questions_json(
+                page.items,
+                full_view,
+                query_params.shuffle,
+                query_params.render,
+                query_params.furigana,
+                &rules,
+            )?,
+            page.next_cursor,
+        ));
+    }
+
     // 全問題を取得し、limitが指定されていればシャッフルして指定数だけ返す（案2を採用）
-    let mut questions = read_db(&path_params, db.clone()).await;
+    let mut questions = read_db(&path_params, db.clone()).await?;
+    if !full_view {
+        questions.retain(Question::is_published);
+    }
     if questions.is_empty() {
-        return response_handler(
-            StatusCode::NOT_FOUND,
-            "Not Found".to_string(),
-            None,
-            Some(
-                format!(
-                    "database has not questions, level_id: {}, category_id: {}",
-                    path_params.level_id, path_params.category_id
-                )
-                .to_string(),
-            ),
-        );
+        return Err(not_found(&path_params));
     }
 
     info!(
@@ -102,6 +199,30 @@ pub async fn get(
         questions.len()
     );
 
+    if query_params.mode == SelectionMode::Adaptive {
+        let questions = select_for_learner(
+            &db,
+            claims.as_ref(),
+            &path_params,
+            questions,
+            query_params.limit.unwrap_or(DEFAULT_ADAPTIVE_LIMIT),
+        )
+        .await?;
+        return Ok(page_response_handler(
+            StatusCode::OK,
+            "ok".to_string(),
+            questions_json(
+                questions,
+                full_view,
+                query_params.shuffle,
+                query_params.render,
+                query_params.furigana,
+                &rules,
+            )?,
+            None,
+        ));
+    }
+
     // limitがあれば、指定数だけ取得
     let questions = match query_params.limit {
         Some(limit) => {
@@ -119,76 +240,298 @@ pub async fn get(
 
     info!("result count: {}", questions.len());
 
-    response_handler(
+    Ok(page_response_handler(
         StatusCode::OK,
         "ok".to_string(),
-        Some(json!(questions)),
+        questions_json(
+            questions,
+            full_view,
+            query_params.shuffle,
+            query_params.render,
+            query_params.furigana,
+            &rules,
+        )?,
         None,
-    )
+    ))
+}
+
+/// 学習者の能力 (未ログイン・未回答なら 0) と小問の難易度から問題を選ぶ
+async fn select_for_learner(
+    db: &Database,
+    claims: Option<&Claims>,
+    path_params: &PathParams,
+    questions: Vec<Question>,
+    limit: u32,
+) -> AppResult<Vec<Question>> {
+    let category_id = path_params.category_id.to_string();
+    let ability = match claims {
+        Some(claims) => db
+            .read::<UserAbility>(
+                "user_abilities",
+                &UserAbility::doc_id(&claims.user_id, path_params.level_id, &category_id),
+            )
+            .await?
+            .map_or(0, |a| a.rating),
+        None => 0,
+    };
+
+    let query = StoreQuery::new()
+        .eq(path!(ItemRating::level_id), path_params.level_id)
+        .eq(path!(ItemRating::category_id), &category_id);
+    let ratings: HashMap<String, i64> = db
+        .query::<ItemRating>("item_ratings", query)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                ItemRating::doc_id(&r.question_id, r.sub_question_id),
+                r.rating,
+            )
+        })
+        .collect();
+
+    info!(
+        "adaptive: ability: {}, rated items: {}",
+        ability,
+        ratings.len()
+    );
+    Ok(select_adaptive(
+        questions,
+        &ratings,
+        ability,
+        limit as usize,
+        &mut rand::rng(),
+    ))
+}
+
+fn not_found(path_params: &PathParams) -> AppError {
+    AppError::NotFound(format!(
+        "database has not questions, level_id: {}, category_id: {}",
+        path_params.level_id, path_params.category_id
+    ))
+}
+
+/// 複合インデックス (level_id + category_id) を使用してFirestore側でフィルタ
+fn category_query(path_params: &PathParams) -> StoreQuery {
+    StoreQuery::new()
+        .eq(path!(Question::level_id), path_params.level_id)
+        .eq(
+            path!(Question::category_id),
+            path_params.category_id.to_string(),
+        )
 }
 
 /// GET /api/questions/{id}
+///
+/// 学習者には正解を含まない [`PublicQuestion`] を返す。Admin のみ全フィールドを返す。
+/// `?shuffle=true` で選択肢を並べ替え、`shuffle_token` を付けて返す。
+/// `?render=html|plain` で文字装飾を HTML または装飾なしの文字列にする。
+/// `?furigana=above_level|all` でふりがなを付ける。
+/// 公開 (`published`) されていない問題は学習者には 404 になる。
 pub async fn get_by_id(
+    claims: Option<Claims>,
     Path(id): Path<String>,
+    Query(params): Query<ShuffleParams>,
     State(db): State<Arc<crate::common::database::Database>>,
-) -> impl IntoResponse {
-    match db.read::<Question>("questions", &id).await {
-        Ok(Some(q)) => response_handler(
-            StatusCode::OK,
-            "ok".to_string(),
-            Some(json!(q)),
-            None,
-        ),
-        Ok(None) => response_handler(
-            StatusCode::NOT_FOUND,
-            "Not Found".to_string(),
-            None,
-            Some("question not found".to_string()),
-        ),
-        Err(e) => response_handler(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "error".to_string(),
-            None,
-            Some(e),
-        ),
+) -> Result<impl IntoResponse, AppError> {
+    let question = db
+        .read::<Question>("questions", &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+
+    let data = if claims.is_some_and(|c| c.is_admin()) {
+        json!(question)
+    } else if !question.is_published() {
+        return Err(AppError::NotFound("question not found".to_string()));
+    } else {
+        let rules = furigana_rules(&db, false, params.furigana).await?;
+        json!(public_question(
+            question,
+            params.shuffle,
+            params.render,
+            params.furigana,
+            &rules,
+        )?)
+    };
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "ok".to_string(),
+        Some(data),
+        None,
+    ))
+}
+
+/// 配信用に問題を JSON 化する。`full_view` でなければ正解・解説を除く
+fn questions_json(
+    questions: Vec<Question>,
+    full_view: bool,
+    shuffle: bool,
+    mode: RenderMode,
+    furigana: FuriganaMode,
+    rules: &RuleSet,
+) -> AppResult<serde_json::Value> {
+    if full_view {
+        return Ok(json!(questions));
+    }
+    let questions = questions
+        .into_iter()
+        .map(|q| public_question(q, shuffle, mode, furigana, rules))
+        .collect::<AppResult<Vec<_>>>()?;
+    Ok(json!(questions))
+}
+
+/// ふりがなを付ける場合だけ品質規則を読み込む (漢字読み・表記の問題の判定に使う)
+async fn furigana_rules(
+    db: &Database,
+    full_view: bool,
+    furigana: FuriganaMode,
+) -> AppResult<RuleSet> {
+    if full_view || furigana == FuriganaMode::None {
+        return Ok(RuleSet::builtin());
+    }
+    RuleSet::load(db).await
+}
+
+/// 学習者向けに変換する。`shuffle` 指定時は選択肢を並べ替えてトークンを付ける。
+/// ふりがなは記法のルビとして付けてから `mode` の形式にする
+fn public_question(
+    question: Question,
+    shuffle: bool,
+    mode: RenderMode,
+    furigana: FuriganaMode,
+    rules: &RuleSet,
+) -> AppResult<PublicQuestion> {
+    let mut question = PublicQuestion::from(question);
+    if shuffle {
+        let token = shuffle_question(&mut question, &mut rand::rng());
+        question.shuffle_token = Some(token.sign()?);
+    }
+    furigana_question(&mut question, furigana, rules);
+    if mode != RenderMode::Markup {
+        render_question(&mut question, mode);
+    }
+    Ok(question)
+}
+
+/// 問題文・本文・小問・選択肢の文字装飾を `mode` の形式にする
+fn render_question(question: &mut PublicQuestion, mode: RenderMode) {
+    question.sentence = render(&question.sentence, mode);
+    if let Some(passage) = question.passage.as_mut() {
+        *passage = render(passage, mode);
+    }
+    for sub_q in &mut question.sub_questions {
+        if let Some(sentence) = sub_q.sentence.as_mut() {
+            *sentence = render(sentence, mode);
+        }
+        for sa in &mut sub_q.select_answer {
+            sa.value = render(&sa.value, mode);
+        }
     }
 }
 
 async fn read_db(
     path_params: &PathParams,
     db: Arc<crate::common::database::Database>,
-) -> Vec<Question> {
-    let cat_id_str = path_params.category_id.to_string();
-
-    // 複合インデックス (level_id + category_id) を使用してFirestore側でフィルタ
-    match db
-        .client
-        .fluent()
-        .select()
-        .from("questions")
-        .filter(|x| {
-            x.for_all([
-                x.field(path!(Question::level_id)).eq(path_params.level_id),
-                x.field(path!(Question::category_id)).eq(&cat_id_str),
-            ])
+) -> AppResult<Vec<Question>> {
+    let data = db
+        .query::<Question>("questions", category_query(path_params))
+        .await?;
+    info!(
+        "Firestore returned {} questions for N{}/cat={}",
+        data.len(),
+        path_params.level_id,
+        path_params.category_id
+    );
+    Ok(data)
+}
+
+/// GET /api/questions/{id}/sub/{sub_id}/hint?level=1&shuffle_token=...
+///
+/// 小問のヒントを `level` 段目まで易しい順に返す。最後の段は誤りの選択肢を1つ除外する。
+/// `level` が段数を超える場合はすべての段を返す。
+/// ヒントを見たことは `hint_usages` に記録し、次の `POST /api/answers` をヒントありの回答として扱う。
+pub async fn get_hint(
+    claims: Claims,
+    Path(path): Path<HintPath>,
+    Query(params): Query<HintQuery>,
+    State(db): State<Arc<crate::common::database::Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let level = params.level.unwrap_or(1);
+    if level == 0 {
+        return Err(AppError::Validation(
+            "level は 1 以上で指定してください".to_string(),
+        ));
+    }
+    let question = db
+        .read::<Question>("questions", &path.id)
+        .await?
+        .filter(|q| claims.is_admin() || q.is_published())
+        .ok_or_else(|| AppError::NotFound("question not found".to_string()))?;
+    let sub_q = question
+        .sub_questions
+        .iter()
+        .find(|sq| sq.id == path.sub_id)
+        .ok_or_else(|| AppError::NotFound("sub_question not found".to_string()))?;
+
+    let shuffle = params
+        .shuffle_token
+        .as_deref()
+        .map(ShuffleToken::verify)
+        .transpose()?;
+    if shuffle.as_ref().is_some_and(|t| t.qid != question.id) {
+        return Err(AppError::Validation(
+            "shuffle_token が問題と一致しません".to_string(),
+        ));
+    }
+
+    let usage_id = HintUsage::doc_id(&claims.user_id, &question.id, sub_q.id);
+    let ladder = hint_ladder(sub_q, &usage_id);
+    let max_level = ladder.len() as u32;
+    let hints: Vec<HintStep> = ladder
+        .into_iter()
+        .take(level as usize)
+        .map(|step| match (&shuffle, step.key.as_deref()) {
+            (Some(token), Some(key)) => {
+                let displayed = token
+                    .to_displayed(sub_q.id, key)
+                    .unwrap_or_else(|| key.to_string());
+                let value = sub_q
+                    .select_answer
+                    .iter()
+                    .find(|c| c.key == key)
+                    .map(|c| c.value.as_str())
+                    .unwrap_or_default();
+                HintStep::eliminate(step.level, &displayed, value)
+            }
+            _ => step,
         })
-        .obj::<Question>()
-        .query()
-        .await
-    {
-        Ok(data) => {
-            info!(
-                "Firestore returned {} questions for N{}/cat={}",
-                data.len(),
-                path_params.level_id,
-                cat_id_str
-            );
-            data
-        }
-        Err(e) => {
-            log::error!("Question query error: {:?}", e);
-            vec![]
-        }
+        .collect();
+
+    let shown = hints.len() as u32;
+    if shown > 0 {
+        let previous = db.read::<HintUsage>("hint_usages", &usage_id).await?;
+        let usage = HintUsage {
+            id: usage_id.clone(),
+            user_id: claims.user_id.clone(),
+            question_id: question.id.clone(),
+            sub_question_id: sub_q.id,
+            level: previous.map_or(shown, |p| p.level.max(shown)),
+            used_at: chrono::Utc::now().timestamp(),
+        };
+        db.update("hint_usages", &usage_id, &usage).await?;
     }
-}
 
+    Ok(response_handler(
+        StatusCode::OK,
+        "ok".to_string(),
+        Some(json!({
+            "question_id": question.id,
+            "sub_question_id": sub_q.id,
+            "level": shown,
+            "max_level": max_level,
+            "hints": hints,
+        })),
+        None,
+    ))
+}
@@ -1,11 +1,16 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
 use serde_json::json;
-use log::error;
 
-use crate::api::utils::response_handler;
-use crate::common::database::Database;
+use crate::api::utils::{PageQuery, page_response_handler, response_handler};
+use crate::common::database::{Cursor, DEFAULT_PAGE_SIZE, Database, MAX_PAGE_SIZE};
+use crate::common::error::AppError;
 use crate::models::claim::{AdminClaims, Claims};
 use crate::models::report::QuestionReport;
 
@@ -15,73 +20,90 @@ pub async fn report_question(
     State(db): State<Arc<Database>>,
     claims: Claims,
     Path(question_id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let doc_id = QuestionReport::doc_id(&question_id, &claims.user_id);
 
     // 重複チェック
-    if let Ok(Some(_)) = db.read::<QuestionReport>("reports", &doc_id).await {
-        return response_handler(
-            StatusCode::CONFLICT,
-            "error".to_string(),
-            None,
-            Some("既に報告済みです".to_string()),
-        );
+    if db
+        .read::<QuestionReport>("reports", &doc_id)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict("既に報告済みです".to_string()));
     }
 
     let report = QuestionReport::new(question_id, claims.user_id);
-    match db.create::<QuestionReport>("reports", &doc_id, report).await {
-        Ok(_) => response_handler(
-            StatusCode::OK,
-            "success".to_string(),
-            Some(json!({"reported": true})),
-            None,
-        ),
-        Err(e) => {
-            error!("報告保存失敗: {:?}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some("報告の保存に失敗しました".to_string()),
-            )
-        }
-    }
+    // 同時リクエストで先に作成された場合も create が Conflict を返す
+    db.create::<QuestionReport>("reports", &doc_id, report)
+        .await
+        .map_err(|e| match e {
+            AppError::Conflict(_) => AppError::Conflict("既に報告済みです".to_string()),
+            e => e,
+        })?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({"reported": true})),
+        None,
+    ))
 }
 
-/// GET /api/admin/reports
-/// Admin専用。question_id ごとの報告件数を降順で返す。
+/// GET /api/admin/reports?limit=50&cursor=...
+/// Admin専用。question_id ごとの報告件数を降順 (同数は question_id 昇順) で返す。
 pub async fn list_reports(
     State(db): State<Arc<Database>>,
     _claims: AdminClaims,
-) -> impl IntoResponse {
-    // 全 reports を取得して question_id 別に集計
-    match db.read_all::<QuestionReport>("reports", Some(1000)).await {
-        Ok(reports) => {
-            let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-            for r in &reports {
-                *counts.entry(r.question_id.clone()).or_insert(0) += 1;
-            }
-            let mut sorted: Vec<_> = counts.into_iter().collect();
-            sorted.sort_by(|a, b| b.1.cmp(&a.1));
+    Query(page): Query<PageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE) as usize;
+    let after = page.cursor.as_deref().map(Cursor::decode).transpose()?;
 
-            let items: Vec<serde_json::Value> = sorted.iter()
-                .map(|(id, count)| json!({"question_id": id, "report_count": count}))
-                .collect();
-            response_handler(
-                StatusCode::OK,
-                "success".to_string(),
-                Some(json!(items)),
-                None,
-            )
-        }
-        Err(e) => {
-            error!("報告一覧取得失敗: {:?}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some("報告一覧の取得に失敗しました".to_string()),
-            )
+    // 全 reports をページ単位で読み、question_id 別に集計
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    db.scan::<QuestionReport, _>("reports", |r| {
+        *counts.entry(r.question_id).or_insert(0) += 1;
+    })
+    .await?;
+
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    // カーソル (件数, question_id) より後ろから limit 件
+    let start = match &after {
+        Some(cursor) => {
+            let count = cursor.value.as_ref().and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            sorted.partition_point(|(id, c)| {
+                *c > count || (*c == count && id.as_str() <= cursor.id.as_str())
+            })
         }
-    }
+        None => 0,
+    };
+    let rest = &sorted[start..];
+    let items = &rest[..limit.min(rest.len())];
+    let next_cursor = match items.last() {
+        Some((id, count)) if rest.len() > limit => Some(
+            Cursor {
+                value: Some(json!(count)),
+                id: id.clone(),
+            }
+            .encode(),
+        ),
+        _ => None,
+    };
+
+    let items: Vec<serde_json::Value> = items
+        .iter()
+        .map(|(id, count)| json!({"question_id": id, "report_count": count}))
+        .collect();
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(items),
+        next_cursor,
+    ))
 }
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use firestore::path;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    api::{
+        admin::{normalize_question, validate_question},
+        utils::{PageQuery, page_response_handler, response_handler},
+    },
+    common::{
+        database::{DEFAULT_PAGE_SIZE, Database, Direction, StoreQuery, WriteOp},
+        error::{AppError, AppResult},
+    },
+    models::{
+        claim::AdminClaims,
+        question::Question,
+        revision::{BASELINE_EDITOR, QuestionRevision, RevisionAction, diff_questions},
+    },
+};
+
+/// 問題を変更するエンドポイント共通のクエリ
+#[derive(Debug, Deserialize, Default)]
+pub struct ReasonQuery {
+    /// 変更理由 (任意)
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RevisionPath {
+    id: String,
+    revision: u32,
+}
+
+/// 改訂を記録する編集者と理由
+pub struct RevisionContext {
+    pub editor: String,
+    pub reason: Option<String>,
+}
+
+impl RevisionContext {
+    pub fn new(admin: &AdminClaims, query: ReasonQuery) -> Self {
+        Self {
+            editor: admin.0.user_id.clone(),
+            reason: query.reason.filter(|r| !r.trim().is_empty()),
+        }
+    }
+}
+
+/// 問題への書き込みと改訂の追記を1トランザクションでコミットし、新しい版番号を返す。
+pub async fn commit_with_revision(
+    db: &Database,
+    ctx: &RevisionContext,
+    action: RevisionAction,
+    previous: Option<&Question>,
+    snapshot: &Question,
+    question_write: WriteOp,
+) -> AppResult<u32> {
+    let (revisions, number) = revision_writes(db, ctx, action, previous, snapshot).await?;
+    let mut writes = vec![question_write];
+    writes.extend(revisions);
+    commit_revision_batch(db, writes).await?;
+    Ok(number)
+}
+
+/// `snapshot` を新しい版として追記する書き込みと、その版番号を返す。
+///
+/// 履歴の無い既存の問題 (`previous` が `Some`) を初めて変更するときは、
+/// 変更前の内容を `baseline` の版として先に記録する。
+pub async fn revision_writes(
+    db: &Database,
+    ctx: &RevisionContext,
+    action: RevisionAction,
+    previous: Option<&Question>,
+    snapshot: &Question,
+) -> AppResult<(Vec<WriteOp>, u32)> {
+    let now = chrono::Utc::now().timestamp();
+    let revision = |number: u32, action, editor: &str, reason, snapshot: &Question| {
+        let revision = QuestionRevision {
+            id: QuestionRevision::doc_id(&snapshot.id, number),
+            question_id: snapshot.id.clone(),
+            revision: number,
+            action,
+            editor: editor.to_string(),
+            reason,
+            created_at: now,
+            snapshot: snapshot.clone(),
+        };
+        WriteOp::create("question_revisions", &revision.id, &revision)
+    };
+
+    let mut writes = Vec::new();
+    let mut number = latest_revision(db, &snapshot.id).await? + 1;
+    if number == 1
+        && let Some(previous) = previous
+    {
+        writes.push(revision(
+            number,
+            RevisionAction::Baseline,
+            BASELINE_EDITOR,
+            None,
+            previous,
+        )?);
+        number += 1;
+    }
+    writes.push(revision(
+        number,
+        action,
+        &ctx.editor,
+        ctx.reason.clone(),
+        snapshot,
+    )?);
+    Ok((writes, number))
+}
+
+/// 改訂を含む書き込みをコミットする
+pub async fn commit_revision_batch(db: &Database, writes: Vec<WriteOp>) -> AppResult<()> {
+    // 同時に編集されると同じ版番号の作成が衝突する
+    db.commit(writes).await.map_err(|e| match e {
+        AppError::Conflict(_) => {
+            AppError::Conflict("他の編集と競合しました。再度お試しください".to_string())
+        }
+        e => e,
+    })
+}
+
+/// 最新の版番号。履歴が無ければ 0
+async fn latest_revision(db: &Database, question_id: &str) -> AppResult<u32> {
+    let query = StoreQuery::new()
+        .eq(path!(QuestionRevision::question_id), question_id)
+        .order_by(path!(QuestionRevision::revision), Direction::Descending)
+        .limit(1);
+    Ok(db
+        .query::<QuestionRevision>("question_revisions", query)
+        .await?
+        .first()
+        .map_or(0, |r| r.revision))
+}
+
+/// GET /api/admin/questions/{id}/revisions?limit=50&cursor=...
+/// 改訂履歴を新しい順に返す。各版には直前の版とのフィールド単位の差分を付ける
+pub async fn list_revisions(
+    _admin: AdminClaims,
+    Path(id): Path<String>,
+    Query(page): Query<PageQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let query = StoreQuery::new()
+        .eq(path!(QuestionRevision::question_id), &id)
+        .order_by(path!(QuestionRevision::revision), Direction::Descending);
+    let page = db
+        .query_page::<QuestionRevision>(
+            "question_revisions",
+            query,
+            page.cursor.as_deref(),
+            page.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+
+    let by_number: HashMap<u32, &QuestionRevision> =
+        page.items.iter().map(|r| (r.revision, r)).collect();
+    let mut items: Vec<Value> = Vec::new();
+    for revision in &page.items {
+        // 直前の版がこのページに無ければ個別に読む (ページ末尾の版)
+        let previous = match revision.revision.checked_sub(1) {
+            None | Some(0) => None,
+            Some(n) => match by_number.get(&n) {
+                Some(r) => Some((*r).clone()),
+                None => {
+                    db.read::<QuestionRevision>(
+                        "question_revisions",
+                        &QuestionRevision::doc_id(&id, n),
+                    )
+                    .await?
+                }
+            },
+        };
+        items.push(json!({
+            "revision": revision.revision,
+            "action": revision.action,
+            "editor": revision.editor,
+            "reason": revision.reason,
+            "created_at": revision.created_at,
+            "changes": diff_questions(previous.as_ref().map(|r| &r.snapshot), &revision.snapshot),
+        }));
+    }
+
+    Ok(page_response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        json!(items),
+        page.next_cursor,
+    ))
+}
+
+/// POST /api/admin/questions/{id}/revisions/{revision}/rollback?reason=...
+/// 指定した版の内容に戻し、`rollback` の版として記録する。削除済みの問題も復元できる。
+/// 問題が存在する場合、状態とレビューの記録は現在の値を引き継ぐ
+pub async fn rollback(
+    admin: AdminClaims,
+    Path(path): Path<RevisionPath>,
+    Query(query): Query<ReasonQuery>,
+    State(db): State<Arc<Database>>,
+) -> Result<impl IntoResponse, AppError> {
+    let target = db
+        .read::<QuestionRevision>(
+            "question_revisions",
+            &QuestionRevision::doc_id(&path.id, path.revision),
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("revision not found".to_string()))?;
+
+    let previous = db.read::<Question>("questions", &path.id).await?;
+    let mut question = target.snapshot;
+    question.id = path.id.clone();
+    if let Some(previous) = &previous {
+        question.status = previous.status;
+        question.review = previous.review.clone();
+    }
+    normalize_question(&mut question);
+    validate_question(&db, &question).await?;
+
+    let mut ctx = RevisionContext::new(&admin, query);
+    ctx.reason = ctx
+        .reason
+        .or_else(|| Some(format!("第{}版に戻す", path.revision)));
+    let revision = commit_with_revision(
+        &db,
+        &ctx,
+        RevisionAction::Rollback,
+        previous.as_ref(),
+        &question,
+        WriteOp::upsert("questions", &question.id, &question)?,
+    )
+    .await?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        Some(json!({
+            "revision": revision,
+            "question": question,
+        })),
+        None,
+    ))
+}
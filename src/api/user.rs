@@ -7,14 +7,14 @@ use axum::{
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 
-use log::error;
 use serde::Deserialize;
 use serde_json::{self, json};
 use uuid::Uuid;
 
 use crate::{
     api::utils::response_handler,
-    models::claim::{hash_password, verify_password, Claims},
+    common::error::AppError,
+    models::claim::{Claims, hash_password, verify_password},
 };
 
 /// ダミーハッシュ: ユーザー未存在時のタイミング攻撃防止用
@@ -71,144 +71,74 @@ fn build_auth_cookie(token: &str) -> Cookie<'static> {
 pub async fn signup(
     State(db): State<Arc<crate::common::database::Database>>,
     Json(req): Json<SignupRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     // メールアドレスバリデーション
     if !is_valid_email(&req.email) {
-        return response_handler(
-            StatusCode::BAD_REQUEST,
-            "error".to_string(),
-            None,
-            Some("有効なメールアドレスを入力してください".to_string()),
-        );
+        return Err(AppError::Validation(
+            "有効なメールアドレスを入力してください".to_string(),
+        ));
     }
 
     // パスワード強度チェック
     if req.password.len() < 8 || req.password.len() > 128 {
-        return response_handler(
-            StatusCode::BAD_REQUEST,
-            "error".to_string(),
-            None,
-            Some("パスワードは8〜128文字で入力してください".to_string()),
-        );
+        return Err(AppError::Validation(
+            "パスワードは8〜128文字で入力してください".to_string(),
+        ));
     }
 
     let mut user = crate::models::user::User::new();
     user.id = Uuid::now_v7().to_string();
     user.email = req.email.clone();
-    user.password = match hash_password(&req.password) {
-        Ok(hashed) => hashed,
-        Err(e) => {
-            error!("パスワードハッシュ化エラー: {}", e);
-            return response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some("登録処理に失敗しました".to_string()),
-            );
-        }
-    };
+    user.password = hash_password(&req.password)
+        .map_err(|e| AppError::Internal(format!("パスワードハッシュ化エラー: {}", e)))?;
     user.created_at = Some(chrono::Utc::now());
 
     // ユーザー情報をDBに登録
     let key = user.email.clone();
-    match db
-        .create::<crate::models::user::User>("users", key.as_str(), user)
+    db.create::<crate::models::user::User>("users", key.as_str(), user)
         .await
-    {
-        Ok(_) => response_handler(StatusCode::OK, "success".to_string(), None, None),
-        Err(e) => {
-            error!("create to database error: {:?}", e);
-            response_handler(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "error".to_string(),
-                None,
-                Some("登録処理に失敗しました".to_string()),
-            )
-        }
-    }
+        .map_err(|e| match e {
+            AppError::Conflict(_) => {
+                AppError::Conflict("このメールアドレスは既に登録されています".to_string())
+            }
+            e => e,
+        })?;
+
+    Ok(response_handler(
+        StatusCode::OK,
+        "success".to_string(),
+        None,
+        None,
+    ))
 }
 
 pub async fn signin(
     State(db): State<Arc<crate::common::database::Database>>,
     jar: CookieJar,
     Json(req): Json<SigninRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
+    let auth_error = || AppError::Unauthorized(AUTH_ERROR_MSG.to_string());
+
     // メールアドレスバリデーション
     if !is_valid_email(&req.email) {
         let _ = verify_password(&DUMMY_HASH, &req.password);
-        return (
-            jar,
-            response_handler(
-                StatusCode::UNAUTHORIZED,
-                "error".to_string(),
-                None,
-                Some(AUTH_ERROR_MSG.to_string()),
-            ),
-        );
+        return Err(auth_error());
     }
 
     // emailでユーザーを検索
-    let db_user = match db
+    let Some(db_user) = db
         .read::<crate::models::user::User>("users", &req.email)
-        .await
-    {
-        Ok(user) => user,
-        Err(_) => {
-            let _ = verify_password(&DUMMY_HASH, &req.password);
-            return (
-                jar,
-                response_handler(
-                    StatusCode::UNAUTHORIZED,
-                    "error".to_string(),
-                    None,
-                    Some(AUTH_ERROR_MSG.to_string()),
-                ),
-            );
-        }
-    };
-
-    let db_user = match db_user {
-        Some(user) => user,
-        None => {
-            let _ = verify_password(&DUMMY_HASH, &req.password);
-            return (
-                jar,
-                response_handler(
-                    StatusCode::UNAUTHORIZED,
-                    "error".to_string(),
-                    None,
-                    Some(AUTH_ERROR_MSG.to_string()),
-                ),
-            );
-        }
+        .await?
+    else {
+        let _ = verify_password(&DUMMY_HASH, &req.password);
+        return Err(auth_error());
     };
 
     // パスワードの検証
-    match verify_password(&db_user.password, &req.password) {
-        Ok(true) => {}
-        Ok(false) => {
-            return (
-                jar,
-                response_handler(
-                    StatusCode::UNAUTHORIZED,
-                    "error".to_string(),
-                    None,
-                    Some(AUTH_ERROR_MSG.to_string()),
-                ),
-            );
-        }
-        Err(e) => {
-            error!("パスワード検証エラー: {}", e);
-            return (
-                jar,
-                response_handler(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "error".to_string(),
-                    None,
-                    Some("認証処理に失敗しました".to_string()),
-                ),
-            );
-        }
+    let verified = verify_password(&db_user.password, &req.password)
+        .map_err(|e| AppError::Internal(format!("パスワード検証エラー: {}", e)))?;
+    if !verified {
+        return Err(auth_error());
     }
 
     // データベースの値でユーザー情報を構築
@@ -240,27 +170,15 @@ pub async fn signin(
     // クレーム発行
     let claims = Claims::new(effective_user_id.clone(), user.email.clone(), role.clone());
 
-    let to_token = match claims.to_token() {
-        Ok(token) => token,
-        Err(e) => {
-            error!("token creation error: {:?}", e);
-            return (
-                jar,
-                response_handler(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "error".to_string(),
-                    None,
-                    Some("認証処理に失敗しました".to_string()),
-                ),
-            );
-        }
-    };
+    let to_token = claims
+        .to_token()
+        .map_err(|e| AppError::Internal(format!("token creation error: {}", e)))?;
 
     // httpOnly Cookie を設定
     let cookie = build_auth_cookie(&to_token);
     let jar = jar.add(cookie);
 
-    (
+    Ok((
         jar,
         response_handler(
             StatusCode::OK,
@@ -272,7 +190,7 @@ pub async fn signin(
             })),
             None,
         ),
-    )
+    ))
 }
 
 /// GET /api/auth/me — 認証状態を確認
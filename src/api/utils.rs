@@ -1,6 +1,15 @@
 use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::Deserialize;
 use serde_json::{Value, json};
 
+/// 一覧エンドポイント共通のページングパラメータ (`?limit=20&cursor=...`)
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u32>,
+    /// 前ページの `next_cursor`
+    pub cursor: Option<String>,
+}
+
 pub fn response_handler(
     code: StatusCode,
     message: String,
@@ -20,6 +29,22 @@ pub fn response_handler(
     (code, Json(body))
 }
 
+/// 一覧用のレスポンス。`next_cursor` は続きがある場合のみ文字列、最終ページでは null。
+pub fn page_response_handler(
+    code: StatusCode,
+    message: String,
+    data: Value,
+    next_cursor: Option<String>,
+) -> impl IntoResponse {
+    let body = json!({
+        "message": message,
+        "data": data,
+        "next_cursor": next_cursor,
+    });
+
+    (code, Json(body))
+}
+
 // 漢字の文字数でソートする関数
 pub fn kanji_len(s: &str) -> usize {
     s.chars()
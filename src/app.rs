@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    http::{HeaderValue, Method},
+    routing::{get, post, put},
+};
+use log::error;
+use tower_governor::{
+    GovernorLayer, governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor,
+};
+use tower_http::cors::CorsLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use crate::{
+    api,
+    common::{
+        database::Database,
+        llm::{GeminiClient, LlmClient, SharedLlm},
+    },
+};
+
+const DEFAULT_FRONTEND_URL: &str = "https://jlpt.howlrs.net";
+
+/// ルーター構築に必要な設定
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// CORS 許可オリジン
+    pub frontend_origin: HeaderValue,
+    /// 問題生成に使う LLM クライアント。未設定なら生成ジョブを起動できない
+    pub llm: SharedLlm,
+}
+
+impl AppConfig {
+    pub fn new(frontend_url: &str) -> Self {
+        let frontend_origin = frontend_url.parse().unwrap_or_else(|e| {
+            error!("FRONTEND_URLのパース失敗: {} - デフォルト使用", e);
+            HeaderValue::from_static(DEFAULT_FRONTEND_URL)
+        });
+        Self {
+            frontend_origin,
+            llm: None,
+        }
+    }
+
+    pub fn with_llm(mut self, llm: Arc<dyn LlmClient>) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    /// 環境変数 `FRONTEND_URL` から構築する
+    pub fn from_env() -> Self {
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| {
+            error!("FRONTEND_URL未設定。デフォルト: {}", DEFAULT_FRONTEND_URL);
+            DEFAULT_FRONTEND_URL.to_string()
+        });
+        let config = Self::new(&frontend_url);
+        match GeminiClient::from_env() {
+            Some(client) => config.with_llm(Arc::new(client)),
+            None => {
+                error!("GEMINI_API_KEY未設定。問題生成は無効");
+                config
+            }
+        }
+    }
+}
+
+/// 全エンドポイント・レート制限・セキュリティヘッダー・CORS を組み立てたルーターを返す
+pub fn build_app(state: Arc<Database>, config: AppConfig) -> Router {
+    // レート制限設定: 認証エンドポイント用 (5回/秒バースト, 2秒に1回持続)
+    let auth_governor_conf = Arc::new(
+        GovernorConfigBuilder::default()
+            .key_extractor(SmartIpKeyExtractor)
+            .per_second(2)
+            .burst_size(5)
+            .finish()
+            .expect("Failed to build auth rate limiter config"),
+    );
+
+    // レート制限設定: evaluate用 (10回/秒バースト)
+    let evaluate_governor_conf = Arc::new(
+        GovernorConfigBuilder::default()
+            .key_extractor(SmartIpKeyExtractor)
+            .per_second(1)
+            .burst_size(10)
+            .finish()
+            .expect("Failed to build evaluate rate limiter config"),
+    );
+
+    // 認証エンドポイント（レート制限付き）
+    let auth_routes = Router::new()
+        .route("/api/signup", post(api::user::signup))
+        .route("/api/signin", post(api::user::signin))
+        .layer(GovernorLayer::new(auth_governor_conf));
+
+    // evaluateエンドポイント（レート制限付き）
+    let evaluate_routes = Router::new()
+        .route("/api/evaluate/{vote}", get(api::evaluate::vote))
+        .layer(GovernorLayer::new(evaluate_governor_conf));
+
+    Router::new()
+        .route("/api/public/health", get(api::initial::public_health))
+        .route("/api/private/health", get(api::initial::private_health))
+        .route("/api/meta", get(api::meta::get))
+        .route(
+            "/api/level/{level_id}/categories/{category_id}/questions",
+            get(api::question::get),
+        )
+        .route("/api/questions/{id}", get(api::question::get_by_id))
+        .route(
+            "/api/questions/{id}/sub/{sub_id}/hint",
+            get(api::question::get_hint),
+        )
+        .route("/api/auth/me", get(api::user::auth_me))
+        .route("/api/auth/logout", post(api::user::auth_logout))
+        .route("/api/answers", post(api::answers::record_answer))
+        .route("/api/users/me/history", get(api::answers::history))
+        .route("/api/users/me/stats", get(api::answers::stats))
+        .route("/api/users/me/mistakes", get(api::answers::mistakes))
+        .route(
+            "/api/users/me/mistakes/export",
+            get(api::answers::export_mistakes),
+        )
+        .route("/api/users/me/review", get(api::answers::review))
+        .route("/api/users/me/exams", get(api::exam::list_exams))
+        .route("/api/exams", post(api::exam::create_exam))
+        .route("/api/exams/{id}", get(api::exam::get_exam))
+        .route(
+            "/api/exams/{id}/sections/{index}",
+            get(api::exam::get_section),
+        )
+        .route("/api/exams/{id}/answers", post(api::exam::submit_answer))
+        .route("/api/exams/{id}/finish", post(api::exam::finish_exam))
+        .route("/api/admin/votes/summary", get(api::admin::votes_summary))
+        .route("/api/admin/questions/bad", get(api::admin::bad_questions))
+        .route("/api/admin/stats", get(api::admin::stats))
+        .route("/api/admin/coverage-stats", get(api::admin::coverage_stats))
+        .route(
+            "/api/admin/questions/bulk-delete",
+            post(api::admin::bulk_delete),
+        )
+        .route(
+            "/api/admin/questions/normalize-markup",
+            post(api::admin::normalize_markup),
+        )
+        .route(
+            "/api/admin/questions/reindex-dedup-keys",
+            post(api::admin::reindex_dedup_keys),
+        )
+        .route("/api/admin/questions", post(api::admin::create_question))
+        .route(
+            "/api/admin/questions/export",
+            get(api::export::export_questions),
+        )
+        .route(
+            "/api/admin/questions/import",
+            post(api::import::import_questions),
+        )
+        .route(
+            "/api/admin/questions/{id}",
+            get(api::admin::question_detail)
+                .put(api::admin::replace_question)
+                .patch(api::admin::patch_question)
+                .delete(api::admin::delete_question),
+        )
+        .route(
+            "/api/admin/questions/{id}/revisions",
+            get(api::revision::list_revisions),
+        )
+        .route(
+            "/api/admin/questions/{id}/revisions/{revision}/rollback",
+            post(api::revision::rollback),
+        )
+        .route(
+            "/api/admin/questions/{id}/submit",
+            post(api::lifecycle::submit),
+        )
+        .route(
+            "/api/admin/questions/{id}/approve",
+            post(api::lifecycle::approve),
+        )
+        .route(
+            "/api/admin/questions/{id}/reject",
+            post(api::lifecycle::reject),
+        )
+        .route(
+            "/api/admin/questions/{id}/retire",
+            post(api::lifecycle::retire),
+        )
+        .route("/api/admin/review-queue", get(api::lifecycle::review_queue))
+        .route(
+            "/api/admin/monitor-quality",
+            post(api::monitor::monitor_quality),
+        )
+        .route(
+            "/api/questions/{id}/report",
+            post(api::report::report_question),
+        )
+        .route("/api/admin/reports", get(api::report::list_reports))
+        .route("/api/admin/duplicates", get(api::admin::duplicates))
+        .route(
+            "/api/admin/prompt-templates",
+            get(api::generation::list_templates),
+        )
+        .route(
+            "/api/admin/prompt-templates/{id}",
+            put(api::generation::put_template),
+        )
+        .route("/api/admin/quality-rules", get(api::quality::list_rules))
+        .route(
+            "/api/admin/quality-rules/{id}",
+            put(api::quality::put_rule).delete(api::quality::delete_rule),
+        )
+        .route(
+            "/api/admin/generation-jobs",
+            post(api::generation::start_job),
+        )
+        .route(
+            "/api/admin/generation-jobs/{id}",
+            get(api::generation::get_job),
+        )
+        .route(
+            "/api/admin/questions/{id}/explanations/draft",
+            post(api::generation::draft_explanations),
+        )
+        .merge(auth_routes)
+        .merge(evaluate_routes)
+        // セキュリティヘッダー
+        .layer(SetResponseHeaderLayer::overriding(
+            axum::http::header::X_FRAME_OPTIONS,
+            HeaderValue::from_static("DENY"),
+        ))
+        .layer(SetResponseHeaderLayer::overriding(
+            axum::http::header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ))
+        .layer(SetResponseHeaderLayer::overriding(
+            axum::http::header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000; includeSubDomains"),
+        ))
+        .layer(
+            CorsLayer::new()
+                .allow_methods([
+                    Method::GET,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                    Method::OPTIONS,
+                ])
+                .allow_origin(vec![config.frontend_origin])
+                .allow_headers([
+                    "Content-Type".parse().unwrap(),
+                    "Authorization".parse().unwrap(),
+                ])
+                .allow_credentials(true),
+        )
+        .layer(Extension(config.llm))
+        .with_state(state)
+}
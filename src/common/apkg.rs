@@ -0,0 +1,265 @@
+//! Anki のデッキパッケージ (`.apkg`) を作る。
+//!
+//! `.apkg` は SQLite のコレクション (`collection.anki2`、スキーマ 11) とメディア一覧 (`media`) を
+//! ZIP にまとめたもの。ノートタイプは表・裏の2フィールドに1枚のカードを持つ基本型で、
+//! ノートの guid に小問ID を使うため、同じデッキを取り込み直すとカードが重複せず更新される。
+
+use std::io::{Cursor, Write};
+
+use rusqlite::{Connection, DatabaseName, params};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::common::{
+    error::{AppError, AppResult},
+    export::AnkiCard,
+};
+
+/// ノートタイプのID。取り込み直しても同じノートタイプになるよう固定する
+const MODEL_ID: i64 = 1_700_000_000_001;
+/// デッキのID
+const DECK_ID: i64 = 1_700_000_000_002;
+
+const SCHEMA: &str = "
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null, scm integer not null,
+    ver integer not null, dty integer not null, usn integer not null, ls integer not null,
+    conf text not null, models text not null, decks text not null, dconf text not null,
+    tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null, mod integer not null,
+    usn integer not null, tags text not null, flds text not null, sfld integer not null,
+    csum integer not null, flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null, ord integer not null,
+    mod integer not null, usn integer not null, type integer not null, queue integer not null,
+    due integer not null, ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null, odid integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null, ease integer not null,
+    ivl integer not null, lastIvl integer not null, factor real not null, time integer not null,
+    type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn on notes (usn);
+CREATE INDEX ix_cards_usn on cards (usn);
+CREATE INDEX ix_revlog_usn on revlog (usn);
+CREATE INDEX ix_cards_nid on cards (nid);
+CREATE INDEX ix_cards_sched on cards (did, queue, due);
+CREATE INDEX ix_revlog_cid on revlog (cid);
+CREATE INDEX ix_notes_csum on notes (csum);
+";
+
+fn sqlite_err(e: rusqlite::Error) -> AppError {
+    AppError::Internal(format!("apkg の作成に失敗しました: {}", e))
+}
+
+/// 重複判定用のチェックサム (表の先頭フィールドの SHA-1 の先頭 8 桁)
+fn field_checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(strip_html(field).as_bytes());
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ]))
+}
+
+fn strip_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn collection_json(deck_name: &str, now: i64) -> (String, String, String, String) {
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id, "name": name, "desc": "", "mod": now, "usn": -1,
+            "lrnToday": [0, 0], "revToday": [0, 0], "newToday": [0, 0], "timeToday": [0, 0],
+            "collapsed": false, "browserCollapsed": false, "dyn": 0, "conf": 1,
+            "extendNew": 0, "extendRev": 0,
+        })
+    };
+    let field = |name: &str, ord: u32| {
+        json!({
+            "name": name, "ord": ord, "sticky": false, "rtl": false,
+            "font": "Arial", "size": 20, "media": [],
+        })
+    };
+
+    let conf = json!({
+        "activeDecks": [1], "curDeck": 1, "newSpread": 0, "collapseTime": 1200, "timeLim": 0,
+        "estTimes": true, "dueCounts": true, "curModel": MODEL_ID.to_string(), "nextPos": 1,
+        "sortType": "noteFld", "sortBackwards": false, "addToCur": true,
+    });
+    let models = json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID, "name": "JLPT 基本", "type": 0, "mod": now, "usn": -1,
+            "sortf": 0, "did": DECK_ID,
+            "tmpls": [{
+                "name": "Card 1", "ord": 0,
+                "qfmt": "{{表}}", "afmt": "{{FrontSide}}<hr id=answer>{{裏}}",
+                "did": null, "bqfmt": "", "bafmt": "",
+            }],
+            "flds": [field("表", 0), field("裏", 1)],
+            "css": ".card { font-family: sans-serif; font-size: 20px; text-align: left; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "req": [[0, "any", [0]]],
+            "tags": [], "vers": [],
+        }
+    });
+    let decks = json!({
+        "1": deck(1, "Default"),
+        DECK_ID.to_string(): deck(DECK_ID, deck_name),
+    });
+    let dconf = json!({
+        "1": {
+            "id": 1, "name": "Default", "mod": 0, "usn": 0, "dyn": false,
+            "maxTaken": 60, "timer": 0, "autoplay": true, "replayq": true,
+            "new": {
+                "delays": [1, 10], "ints": [1, 4, 7], "initialFactor": 2500,
+                "order": 1, "perDay": 20, "bury": false, "separate": true,
+            },
+            "rev": {
+                "perDay": 200, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1,
+                "maxIvl": 36500, "bury": false, "minSpace": 1,
+            },
+            "lapse": {
+                "delays": [10], "mult": 0, "minInt": 1, "leechFails": 8, "leechAction": 0,
+            },
+        }
+    });
+    (
+        conf.to_string(),
+        models.to_string(),
+        decks.to_string(),
+        dconf.to_string(),
+    )
+}
+
+/// SQLite のコレクションを作り、そのバイト列を返す
+fn collection(deck_name: &str, cards: &[AnkiCard], now_ms: i64) -> rusqlite::Result<Vec<u8>> {
+    let now = now_ms / 1000;
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(SCHEMA)?;
+
+    let (conf, models, decks, dconf) = collection_json(deck_name, now);
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![now - now % 86_400, now_ms, conf, models, decks, dconf],
+    )?;
+
+    for (i, card) in cards.iter().enumerate() {
+        // ノート・カードのIDはミリ秒単位の作成時刻が慣例。件数分ずらして一意にする
+        let id = now_ms + i as i64;
+        conn.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+            params![
+                id,
+                card.guid,
+                MODEL_ID,
+                now,
+                format!(" {} ", card.tags.join(" ")),
+                format!("{}\u{1f}{}", card.front, card.back),
+                strip_html(&card.front),
+                field_checksum(&card.front),
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO cards VALUES (?1, ?1, ?2, 0, ?3, -1, 0, 0, ?4, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+            params![id, DECK_ID, now, i as i64 + 1],
+        )?;
+    }
+
+    Ok(conn.serialize(DatabaseName::Main)?.to_vec())
+}
+
+/// `.apkg` のバイト列
+pub fn anki_package(deck_name: &str, cards: &[AnkiCard]) -> AppResult<Vec<u8>> {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let collection = collection(deck_name, cards, now_ms).map_err(sqlite_err)?;
+
+    let zip_err =
+        |e: zip::result::ZipError| AppError::Internal(format!("apkg の作成に失敗しました: {}", e));
+    let io_err =
+        |e: std::io::Error| AppError::Internal(format!("apkg の作成に失敗しました: {}", e));
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file("collection.anki2", options)
+        .map_err(zip_err)?;
+    zip.write_all(&collection).map_err(io_err)?;
+    zip.start_file("media", options).map_err(zip_err)?;
+    zip.write_all(b"{}").map_err(io_err)?;
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_package_contains_notes_and_cards() {
+        let cards: Vec<AnkiCard> = (1..=2)
+            .map(|i| AnkiCard {
+                guid: format!("q1_{}", i),
+                front: format!("問題{}<br>1. あ", i),
+                back: "正解: 1. あ".to_string(),
+                tags: vec!["JLPT::N3".to_string(), "文法".to_string()],
+            })
+            .collect();
+        let package = anki_package("JLPT 間違えた問題", &cards).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut bytes = Vec::new();
+        archive
+            .by_name("collection.anki2")
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("{}.anki2", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &bytes).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        let notes: Vec<(String, String, String)> = conn
+            .prepare("SELECT guid, flds, tags FROM notes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].0, "q1_1");
+        assert_eq!(notes[0].1, "問題1<br>1. あ\u{1f}正解: 1. あ");
+        assert_eq!(notes[0].2, " JLPT::N3 文法 ");
+
+        let (deck, decks): (i64, String) = conn
+            .query_row(
+                "SELECT DISTINCT did, (SELECT decks FROM col) FROM cards",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(deck, DECK_ID);
+        assert!(decks.contains("JLPT 間違えた問題"));
+        drop(conn);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_checksum_ignores_html() {
+        assert_eq!(field_checksum("<b>あ</b>"), field_checksum("あ"));
+    }
+}
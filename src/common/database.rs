@@ -1,130 +1,390 @@
-use firestore::FirestoreDb;
-use serde::Serialize;
-use tokio_stream::StreamExt;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use log::warn;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use super::{
+    error::{AppError, AppResult},
+    firestore_store::FirestoreStore,
+    memory_store::MemoryStore,
+};
+
+/// 一覧取得の既定ページサイズ
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// 1ページあたりの上限
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+/// クエリの並び順
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// ページング位置。直前ページ最後のドキュメントの並び替え値とドキュメントID。
+///
+/// クライアントには [`Cursor::encode`] した不透明な文字列 (`next_cursor`) として渡す。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> AppResult<Self> {
+        URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::Validation("cursor が不正です".to_string()))
+    }
+}
+
+/// カーソルページングの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// 続きがある場合のみ `Some`
+    pub next_cursor: Option<String>,
+}
+
+/// ストアから読み出したドキュメントとそのID
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredDoc {
+    pub id: String,
+    pub data: Value,
+}
+
+/// ハンドラが利用する絞り込み・並び替えクエリ。
+///
+/// 条件は等価比較と、配列フィールドの包含 (`contains_any`) のみ (すべて AND)。
+/// 並び替えは1フィールドまでで、同値の場合はドキュメントIDを同じ向きで比較する。
+#[derive(Debug, Clone, Default)]
+pub struct StoreQuery {
+    pub filters: Vec<(String, Value)>,
+    /// 配列フィールドがいずれかの値を含む。Firestore の制約で1クエリに1つ、値は30個まで
+    pub contains_any: Option<(String, Vec<Value>)>,
+    pub order_by: Option<(String, Direction)>,
+    pub limit: Option<u32>,
+    /// このカーソルより後ろのドキュメントだけを返す
+    pub start_after: Option<Cursor>,
+}
+
+impl StoreQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eq<V: Serialize>(mut self, field: impl Into<String>, value: V) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.filters.push((field.into(), value));
+        self
+    }
+
+    /// 配列フィールド `field` が `values` のいずれかを含むドキュメントに絞る
+    pub fn contains_any<V: Serialize>(mut self, field: impl Into<String>, values: &[V]) -> Self {
+        let values = values
+            .iter()
+            .map(|v| serde_json::to_value(v).unwrap_or(Value::Null))
+            .collect();
+        self.contains_any = Some((field.into(), values));
+        self
+    }
+
+    pub fn order_by(mut self, field: impl Into<String>, direction: Direction) -> Self {
+        self.order_by = Some((field.into(), direction));
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn start_after(mut self, cursor: Cursor) -> Self {
+        self.start_after = Some(cursor);
+        self
+    }
+}
+
+/// [`Store::commit`] でまとめて適用する書き込み操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOp {
+    /// 新規作成する。既に存在する場合はコミット全体が `Conflict` で失敗する
+    Create {
+        collection: String,
+        id: String,
+        doc: Value,
+    },
+    /// ドキュメントを丸ごと保存する (upsert)
+    Upsert {
+        collection: String,
+        id: String,
+        doc: Value,
+    },
+    /// `merge` のトップレベルフィールドを上書きし、`increments` の数値フィールドを
+    /// ストア側で加算する。ドキュメントや途中のマップが無ければ作成する。
+    ///
+    /// フィールドパスはセグメントの列で指定する (例: `["levels", "N3", "total"]`)。
+    Increment {
+        collection: String,
+        id: String,
+        merge: serde_json::Map<String, Value>,
+        increments: Vec<(Vec<String>, i64)>,
+    },
+    /// 既存のドキュメントの `fields` のトップレベルフィールドだけを上書きする。
+    /// ドキュメントが無ければコミット全体が `NotFound` で失敗する (削除されたドキュメントを作り直さない)
+    Merge {
+        collection: String,
+        id: String,
+        fields: serde_json::Map<String, Value>,
+    },
+    Delete {
+        collection: String,
+        id: String,
+    },
+}
+
+impl WriteOp {
+    pub fn create<T: Serialize>(collection: &str, id: &str, data: &T) -> AppResult<Self> {
+        Ok(WriteOp::Create {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            doc: to_doc(data)?,
+        })
+    }
+
+    pub fn upsert<T: Serialize>(collection: &str, id: &str, data: &T) -> AppResult<Self> {
+        Ok(WriteOp::Upsert {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            doc: to_doc(data)?,
+        })
+    }
+
+    pub fn merge(collection: &str, id: &str, fields: serde_json::Map<String, Value>) -> Self {
+        WriteOp::Merge {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            fields,
+        }
+    }
+
+    pub fn delete(collection: &str, id: &str) -> Self {
+        WriteOp::Delete {
+            collection: collection.to_string(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// ドキュメントストアの抽象。
+///
+/// ドキュメントは `serde_json::Value` でやり取りし、型付けは [`Database`] 側で行う。
+/// `update` は Firestore の UpdateDocument と同じく、存在しなければ作成する (upsert)。
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// 新規作成。同じIDが既に存在する場合はエラー。
+    async fn create(&self, collection: &str, id: &str, doc: Value) -> AppResult<()>;
+    async fn read(&self, collection: &str, id: &str) -> AppResult<Option<Value>>;
+    /// コレクション全件を返す。件数が増えうるものは [`Database::read_page`] / [`Database::scan`] を使う。
+    async fn read_all(&self, collection: &str) -> AppResult<Vec<Value>>;
+    async fn update(&self, collection: &str, id: &str, doc: Value) -> AppResult<()>;
+    async fn delete(&self, collection: &str, id: &str) -> AppResult<()>;
+    async fn query(&self, collection: &str, query: &StoreQuery) -> AppResult<Vec<StoredDoc>>;
+    /// 複数の書き込みをアトミックに適用する。いずれかが失敗した場合は何も反映しない。
+    async fn commit(&self, writes: Vec<WriteOp>) -> AppResult<()>;
+}
+
+/// ハンドラから利用する型付きのデータアクセス層。
+///
+/// 実体は [`Store`] 実装 (Firestore / インメモリ) に委譲する。
+#[derive(Clone)]
 pub struct Database {
-    pub client: FirestoreDb,
+    store: Arc<dyn Store>,
 }
 
 impl Database {
+    /// Firestore バックエンドで初期化する (`PROJECT_ID` 必須)
     pub async fn new() -> Self {
         let project_id = std::env::var("PROJECT_ID").expect("PROJECT_ID must be set");
-        let client = match FirestoreDb::new(&project_id).await {
-            Ok(client) => client,
-            Err(e) => panic!("Failed to create Firestore client: {}", e),
-        };
-        Database { client }
+        Self::with_store(Arc::new(FirestoreStore::new(&project_id).await))
+    }
+
+    /// インメモリバックエンドで初期化する (テスト・ローカル開発用)
+    pub fn memory() -> Self {
+        Self::with_store(Arc::new(MemoryStore::new()))
+    }
+
+    pub fn with_store(store: Arc<dyn Store>) -> Self {
+        Database { store }
+    }
+
+    /// 環境変数 `STORE_BACKEND` に応じてバックエンドを選択する (`memory` 以外は Firestore)
+    pub async fn from_env() -> Self {
+        match std::env::var("STORE_BACKEND").as_deref() {
+            Ok("memory") => Self::memory(),
+            _ => Self::new().await,
+        }
     }
 
     // 型汎用的なCRUDの操作を実装する
-    pub async fn create<T>(&self, collection: &str, key: &str, data: T) -> Result<(), String>
+    pub async fn create<T>(&self, collection: &str, key: &str, data: T) -> AppResult<()>
     where
-        T: serde::Serialize
-            + std::fmt::Debug
-            + Clone
-            + Send
-            + Sync
-            + Serialize
-            + for<'de> serde::Deserialize<'de>,
+        T: Serialize + Send + Sync,
     {
-        match self
-            .client
-            .fluent()
-            .insert()
-            .into(collection)
-            .document_id(key)
-            .object(&data)
-            .execute::<T>()
-            .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to create document: {}", e)),
+        let doc = to_doc(&data)?;
+        self.store.create(collection, key, doc).await
+    }
+
+    pub async fn read<T>(&self, collection: &str, id: &str) -> AppResult<Option<T>>
+    where
+        T: DeserializeOwned + Send + Sync,
+    {
+        match self.store.read(collection, id).await? {
+            Some(doc) => Ok(Some(serde_json::from_value(doc)?)),
+            None => Ok(None),
         }
     }
 
-    pub async fn read<T>(&self, collection: &str, id: &str) -> Result<Option<T>, String>
+    pub async fn read_all<T>(&self, collection: &str) -> AppResult<Vec<T>>
     where
-        T: serde::de::DeserializeOwned + Send + Sync,
+        T: DeserializeOwned + Send + Sync,
     {
-        match self
-            .client
-            .fluent()
-            .select()
-            .by_id_in(collection)
-            .obj()
-            .one(id)
-            .await
-        {
-            Ok(data) => Ok(data),
-            Err(e) => Err(format!("Failed to read document: {}", e)),
+        let docs = self.store.read_all(collection).await?;
+        Ok(from_docs(collection, docs))
+    }
+
+    pub async fn update<T>(&self, collection: &str, id: &str, data: T) -> AppResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        let doc = to_doc(&data)?;
+        self.store.update(collection, id, doc).await
+    }
+
+    pub async fn delete(&self, collection: &str, id: &str) -> AppResult<()> {
+        self.store.delete(collection, id).await
+    }
+
+    /// 複数の書き込みをアトミックに適用する (Firestore ではトランザクション)
+    pub async fn commit(&self, writes: Vec<WriteOp>) -> AppResult<()> {
+        if writes.is_empty() {
+            return Ok(());
         }
+        self.store.commit(writes).await
+    }
+
+    /// 条件に一致するドキュメントを取得する。デシリアライズできないものは警告を出して読み飛ばす。
+    pub async fn query<T>(&self, collection: &str, query: StoreQuery) -> AppResult<Vec<T>>
+    where
+        T: DeserializeOwned + Send + Sync,
+    {
+        let docs = self.store.query(collection, &query).await?;
+        Ok(from_docs(
+            collection,
+            docs.into_iter().map(|doc| doc.data).collect(),
+        ))
     }
 
-    pub async fn read_all<T>(
+    /// `query` の結果を `page_size` 件ずつ返す。`cursor` には前ページの `next_cursor` を渡す。
+    pub async fn query_page<T>(
         &self,
         collection: &str,
-        limit: Option<usize>,
-    ) -> Result<Vec<T>, String>
+        mut query: StoreQuery,
+        cursor: Option<&str>,
+        page_size: u32,
+    ) -> AppResult<Page<T>>
     where
-        T: serde::de::DeserializeOwned + Send + Sync,
+        T: DeserializeOwned + Send + Sync,
     {
-        match self
-            .client
-            .fluent()
-            .list()
-            .from(collection)
-            .obj::<T>()
-            .stream_all()
-            .await
-        {
-            Ok(mut data) => {
-                let mut result = Vec::new();
-                while let Some(item) = data.next().await {
-                    if let Some(l) = limit {
-                        if result.len() >= l {
-                            break;
-                        }
-                    }
-                    result.push(item);
-                }
-                Ok(result)
-            }
-            Err(e) => Err(format!("Failed to read documents: {}", e)),
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        if let Some(token) = cursor {
+            query.start_after = Some(Cursor::decode(token)?);
         }
+        // 1件多く取得して続きの有無を判定する
+        query.limit = Some(page_size + 1);
+
+        let mut docs = self.store.query(collection, &query).await?;
+        let has_more = docs.len() > page_size as usize;
+        docs.truncate(page_size as usize);
+
+        let next_cursor = match docs.last() {
+            Some(last) if has_more => Some(
+                Cursor {
+                    value: query
+                        .order_by
+                        .as_ref()
+                        .and_then(|(field, _)| last.data.get(field).cloned()),
+                    id: last.id.clone(),
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+
+        Ok(Page {
+            items: from_docs(collection, docs.into_iter().map(|doc| doc.data).collect()),
+            next_cursor,
+        })
     }
 
-    pub async fn update<T>(&self, collection: &str, id: &str, data: T) -> Result<(), String>
+    /// コレクション全体をドキュメントID順にページングして返す
+    pub async fn read_page<T>(
+        &self,
+        collection: &str,
+        cursor: Option<&str>,
+        page_size: u32,
+    ) -> AppResult<Page<T>>
     where
-        T: serde::Serialize + Send + Sync + for<'de> serde::Deserialize<'de> + Serialize,
+        T: DeserializeOwned + Send + Sync,
     {
-        match self
-            .client
-            .fluent()
-            .update()
-            .in_col(collection)
-            .document_id(id)
-            .object(&data)
-            .execute::<T>()
+        self.query_page(collection, StoreQuery::new(), cursor, page_size)
             .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to update document: {}", e)),
-        }
     }
 
-    pub async fn delete(&self, collection: &str, id: &str) -> Result<(), String> {
-        match self
-            .client
-            .fluent()
-            .delete()
-            .from(collection)
-            .document_id(id)
-            .execute()
-            .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to delete document: {}", e)),
+    /// コレクション全体を1ページずつ読み、各ドキュメントに `f` を適用する。
+    ///
+    /// 集計処理で全件を一度にメモリへ載せないために使う。
+    pub async fn scan<T, F>(&self, collection: &str, mut f: F) -> AppResult<()>
+    where
+        T: DeserializeOwned + Send + Sync,
+        F: FnMut(T) + Send,
+    {
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = self
+                .read_page::<T>(collection, cursor.as_deref(), MAX_PAGE_SIZE)
+                .await?;
+            page.items.into_iter().for_each(&mut f);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(()),
+            }
         }
     }
 }
+
+fn to_doc<T: Serialize>(data: &T) -> AppResult<Value> {
+    Ok(serde_json::to_value(data)?)
+}
+
+fn from_docs<T: DeserializeOwned>(collection: &str, docs: Vec<Value>) -> Vec<T> {
+    docs.into_iter()
+        .filter_map(|doc| match serde_json::from_value(doc) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                warn!("{} ドキュメント読取エラー: {}", collection, e);
+                None
+            }
+        })
+        .collect()
+}
@@ -1,10 +1,12 @@
 //! 重複検出用の共通ヘルパー。
 //!
 //! scripts リポジトリの `bin/dedup_common.rs` と同ロジック。
-//! 管理者向けエンドポイント (`GET /api/admin/duplicates`) で利用する。
+//! 管理者向けエンドポイント (`GET /api/admin/duplicates`) と、問題の作成・一括インポートの重複判定で利用する。
 
 use unicode_normalization::UnicodeNormalization;
 
+use crate::models::question::{Question, SubQuestion};
+
 /// 選択肢値・正解値などのテキストを正規化する。
 ///
 /// - Unicode NFKC 正規化 (全角→半角、半角カナ→全角カナ等)
@@ -22,10 +24,24 @@ pub struct SubLike {
     pub answer: String,
 }
 
+impl From<&SubQuestion> for SubLike {
+    fn from(sub: &SubQuestion) -> Self {
+        SubLike {
+            options: sub
+                .select_answer
+                .iter()
+                .map(|sa| (sa.key.clone(), sa.value.clone()))
+                .collect(),
+            answer: sub.answer.clone(),
+        }
+    }
+}
+
 /// `dedup_key` が `Err` で返す理由。
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KeySkipReason {
-    /// 正規化後の選択肢値がすべて "1","2","3","4" (並び替え問題のプレースホルダ)
+    /// 正規化後の選択肢値がすべて "1","2","3","4" (`question_type` を持たない旧形式の並び替え問題のプレースホルダ)。
+    /// `ordering` 型の問題は選択肢に断片の文字列を持つため通常どおりキーを作れる
     NumericPlaceholder,
     /// `answer` キーに対応する value が選択肢に存在しない、または選択肢が空
     AnswerNotInOptions,
@@ -77,6 +93,21 @@ pub fn dedup_key(level_id: u32, sub: &SubLike) -> Result<String, KeySkipReason>
     ))
 }
 
+/// 問題の小問の dedup キー (小問の順、重複を除く)。問題の `dedup_keys` に保存する索引。
+///
+/// キーを作れない小問 (数字だけの選択肢等) は含まない。
+pub fn question_dedup_keys(question: &Question) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for sub in &question.sub_questions {
+        if let Ok(key) = dedup_key(question.level_id, &SubLike::from(sub))
+            && !keys.contains(&key)
+        {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
 use chrono::{DateTime, Utc};
 
 /// tiebreaker のための候補情報。
@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use firestore::errors::FirestoreError;
+use log::error;
+use serde_json::json;
+
+/// データ層・ハンドラ共通のエラー型。
+///
+/// レスポンスは `{"message": "error", "code": "<機械可読コード>", "error": "<説明>"}`。
+/// `Upstream` / `Internal` の詳細はログにのみ出力し、クライアントには汎用メッセージを返す。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// 対象リソースが存在しない
+    NotFound(String),
+    /// 既に存在する・状態が競合している
+    Conflict(String),
+    /// リクエスト内容が不正
+    Validation(String),
+    /// 認証されていない
+    Unauthorized(String),
+    /// 権限がない
+    Forbidden(String),
+    /// Firestore など外部サービスとの通信失敗
+    Upstream(String),
+    /// サーバー内部の不整合 (シリアライズ失敗など)
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Upstream(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// クライアント向けの安定したエラーコード
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Validation(_) => "validation_error",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Upstream(_) => "upstream_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            AppError::NotFound(m)
+            | AppError::Conflict(m)
+            | AppError::Validation(m)
+            | AppError::Unauthorized(m)
+            | AppError::Forbidden(m)
+            | AppError::Upstream(m)
+            | AppError::Internal(m) => m,
+        }
+    }
+
+    /// クライアントに返すメッセージ
+    fn public_message(&self) -> &str {
+        match self {
+            AppError::Upstream(_) => "データベースとの通信に失敗しました",
+            AppError::Internal(_) => "サーバー内部でエラーが発生しました",
+            _ => self.detail(),
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.detail())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if matches!(self, AppError::Upstream(_) | AppError::Internal(_)) {
+            error!("{}", self);
+        }
+
+        let body = Json(json!({
+            "message": "error",
+            "code": self.code(),
+            "error": self.public_message(),
+        }));
+
+        (self.status(), body).into_response()
+    }
+}
+
+impl From<FirestoreError> for AppError {
+    fn from(e: FirestoreError) -> Self {
+        match e {
+            FirestoreError::DataNotFoundError(_) => AppError::NotFound(e.to_string()),
+            FirestoreError::DataConflictError(_) => AppError::Conflict(e.to_string()),
+            FirestoreError::SerializeError(_) | FirestoreError::DeserializeError(_) => {
+                AppError::Internal(e.to_string())
+            }
+            _ => AppError::Upstream(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Internal(format!("Failed to convert document: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_code() {
+        let e = AppError::NotFound("question not found".to_string());
+        assert_eq!(e.status(), StatusCode::NOT_FOUND);
+        assert_eq!(e.code(), "not_found");
+        assert_eq!(e.public_message(), "question not found");
+    }
+
+    #[test]
+    fn test_upstream_detail_is_hidden() {
+        let e = AppError::Upstream("grpc status: Unavailable, secret-host:443".to_string());
+        assert_eq!(e.code(), "upstream_error");
+        assert!(!e.public_message().contains("secret-host"));
+    }
+}
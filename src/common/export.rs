@@ -0,0 +1,454 @@
+//! 問題バンクを外部の学習ツール向けの形式に書き出す。
+//!
+//! - JSONL: `Question` をそのまま1行1件 (バックアップ・再インポート用)
+//! - Anki CSV: 小問1件を1枚のカード (表: 問題文と選択肢 / 裏: 正解と解説 / タグ: レベルとカテゴリ)
+//! - Moodle GIFT: 小問1件を1問の多肢選択問題
+//! - IMS QTI 2.1: 小問1件を1つの `assessmentItem` とし、`imsmanifest.xml` と共に ZIP にまとめる
+//!
+//! 問題文は `sentence` (問題全体の指示)・`passage` (読解の本文)・`prerequisites` (本文)・
+//! 小問の `prerequisites` と `sentence` の順に、空でないものを改行でつなぐ。
+//! 文字装飾 (下線部・ルビ等) は HTML を使える Anki・GIFT の問題文では HTML に、
+//! それ以外 (GIFT の選択肢・QTI) では装飾を除いた文字列にする。
+
+use std::io::{Cursor, Write};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    common::{
+        error::{AppError, AppResult},
+        rich_text::RichText,
+    },
+    models::question::{Question, SelectAnswer, SubQuestion},
+};
+
+/// Anki の CSV インポート用ヘッダー (Anki 2.1.55 以降)。列は 表, 裏, タグ
+pub const ANKI_CSV_HEADER: &str = "#separator:Comma\n#html:true\n#tags column:3\n";
+
+/// Anki のカード1枚
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnkiCard {
+    /// 小問を識別する `{question_id}_{sub_question_id}`。apkg ではノートの guid に使う
+    pub guid: String,
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+}
+
+/// 問題文の各部分 (空のものは除く)
+pub fn stem_parts<'a>(question: &'a Question, sub_q: &'a SubQuestion) -> Vec<&'a str> {
+    [
+        Some(question.sentence.as_str()),
+        question.passage.as_deref(),
+        question.prerequisites.as_deref(),
+        sub_q.prerequisites.as_deref(),
+        sub_q.sentence.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+    .collect()
+}
+
+/// 正解の選択肢
+pub fn correct_option(sub_q: &SubQuestion) -> Option<&SelectAnswer> {
+    sub_q.select_answer.iter().find(|sa| sa.key == sub_q.answer)
+}
+
+/// レベル (`JLPT::N3`) とカテゴリのタグ。Anki のタグは空白を含められないため `_` に置き換える
+pub fn tags(question: &Question) -> Vec<String> {
+    let mut tags = vec![format!("JLPT::{}", level_name(question))];
+    let category = question
+        .category_name
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    if !category.is_empty() {
+        tags.push(category.join("_"));
+    }
+    tags
+}
+
+fn level_name(question: &Question) -> String {
+    if question.level_name.is_empty() {
+        format!("N{}", question.level_id)
+    } else {
+        question.level_name.clone()
+    }
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 文字装飾を HTML にした各行を `<br>` でつなぐ
+fn html_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> String {
+    lines
+        .into_iter()
+        .map(|line| RichText::parse(line).to_html())
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+fn plain(s: &str) -> String {
+    RichText::parse(s).to_plain()
+}
+
+/// 選択肢の一覧 (`1. ので`)
+pub fn option_lines(sub_q: &SubQuestion) -> Vec<String> {
+    sub_q
+        .select_answer
+        .iter()
+        .map(|sa| format!("{}. {}", sa.key, sa.value))
+        .collect()
+}
+
+pub fn jsonl_line(question: &Question) -> AppResult<String> {
+    Ok(format!("{}\n", serde_json::to_string(question)?))
+}
+
+/// 表: 問題文と選択肢 / 裏: 正解と解説
+pub fn anki_cards(question: &Question) -> Vec<AnkiCard> {
+    question
+        .sub_questions
+        .iter()
+        .map(|sub_q| {
+            let back = correct_option(sub_q)
+                .map(|sa| html_lines([format!("{}. {}", sa.key, sa.value).as_str()]))
+                .unwrap_or_default();
+            anki_card(question, sub_q, back)
+        })
+        .collect()
+}
+
+/// 間違えた小問のカード。裏に正解と学習者の解答 (その選択肢の解説があれば併せて) を並べる
+pub fn mistake_card(question: &Question, sub_q: &SubQuestion, selected_key: &str) -> AnkiCard {
+    let label = |key: &str| {
+        sub_q
+            .select_answer
+            .iter()
+            .find(|sa| sa.key == key)
+            .map_or_else(|| key.to_string(), |sa| format!("{}. {}", sa.key, sa.value))
+    };
+    let mut back = format!(
+        "正解: {}<br>あなたの解答: {}",
+        html_lines([label(&sub_q.answer).as_str()]),
+        html_lines([label(selected_key).as_str()])
+    );
+    if let Some(rationale) = sub_q.option_explanations.get(selected_key) {
+        back.push_str("<br>");
+        back.push_str(&html_lines([rationale.as_str()]));
+    }
+    anki_card(question, sub_q, back)
+}
+
+/// 表に問題文と選択肢、裏に `back` と解説を置いたカード
+fn anki_card(question: &Question, sub_q: &SubQuestion, mut back: String) -> AnkiCard {
+    let options = option_lines(sub_q);
+    let mut front = html_lines(stem_parts(question, sub_q));
+    front.push_str("<br><br>");
+    front.push_str(&html_lines(options.iter().map(String::as_str)));
+
+    if let Some(explanation) = sub_q.explanation.as_deref() {
+        back.push_str("<br><br>");
+        back.push_str(&html_lines([explanation]));
+    }
+
+    AnkiCard {
+        guid: format!("{}_{}", question.id, sub_q.id),
+        front,
+        back,
+        tags: tags(question),
+    }
+}
+
+/// カードを CSV の行にする ([`ANKI_CSV_HEADER`] は含まない)
+pub fn anki_csv_rows(cards: &[AnkiCard]) -> AppResult<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    for card in cards {
+        writer
+            .write_record([&card.front, &card.back, &card.tags.join(" ")])
+            .map_err(|e| AppError::Internal(format!("CSV の書き出しに失敗しました: {}", e)))?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| AppError::Internal(format!("CSV の書き出しに失敗しました: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// GIFT の特殊文字をエスケープする
+fn escape_gift(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '~' | '=' | '#' | '{' | '}' | ':' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 問題1件分の GIFT。カテゴリは `JLPT/N3/文法`
+pub fn gift(question: &Question) -> String {
+    let mut out = format!(
+        "$CATEGORY: $course$/JLPT/{}/{}\n\n",
+        level_name(question),
+        question.category_name
+    );
+    for sub_q in &question.sub_questions {
+        let stem = escape_gift(&html_lines(stem_parts(question, sub_q)));
+        out.push_str(&format!(
+            "::{}::[html]{} {{\n",
+            escape_gift(&format!("{}_{}", question.id, sub_q.id)),
+            stem
+        ));
+        for sa in &sub_q.select_answer {
+            let mark = if sa.key == sub_q.answer { '=' } else { '~' };
+            out.push_str(&format!("\t{}{}\n", mark, escape_gift(&plain(&sa.value))));
+        }
+        if let Some(explanation) = sub_q.explanation.as_deref() {
+            out.push_str(&format!("\t####{}\n", escape_gift(&plain(explanation))));
+        }
+        out.push_str("}\n\n");
+    }
+    out
+}
+
+pub fn escape_xml(s: &str) -> String {
+    escape_html(s).replace('\'', "&apos;")
+}
+
+/// XML の識別子 (NCName) に使えない文字を `_` に置き換える
+fn qti_identifier(question_id: &str, sub_question_id: u32) -> String {
+    let id: String = question_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("q_{}_{}", id, sub_question_id)
+}
+
+/// 小問1件分の QTI 2.1 `assessmentItem`
+pub fn qti_item(question: &Question, sub_q: &SubQuestion) -> (String, String) {
+    let identifier = qti_identifier(&question.id, sub_q.id);
+    let parts = stem_parts(question, sub_q);
+    let (prompt, body) = parts.split_last().unwrap_or((&"", &[]));
+    let body: String = body
+        .iter()
+        .map(|part| format!("    <p>{}</p>\n", escape_xml(&plain(part))))
+        .collect();
+    let choices: String = sub_q
+        .select_answer
+        .iter()
+        .map(|sa| {
+            format!(
+                "      <simpleChoice identifier=\"choice_{}\">{}</simpleChoice>\n",
+                escape_xml(&sa.key),
+                escape_xml(&plain(&sa.value))
+            )
+        })
+        .collect();
+
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<assessmentItem xmlns="http://www.imsglobal.org/xsd/imsqti_v2p1" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imsqti_v2p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/imsqti_v2p1.xsd" identifier="{identifier}" title="{title}" adaptive="false" timeDependent="false">
+  <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="identifier">
+    <correctResponse>
+      <value>choice_{answer}</value>
+    </correctResponse>
+  </responseDeclaration>
+  <outcomeDeclaration identifier="SCORE" cardinality="single" baseType="float"/>
+  <itemBody>
+{body}    <choiceInteraction responseIdentifier="RESPONSE" shuffle="false" maxChoices="1">
+      <prompt>{prompt}</prompt>
+{choices}    </choiceInteraction>
+  </itemBody>
+  <responseProcessing template="http://www.imsglobal.org/question/qti_v2p1/rptemplates/match_correct"/>
+</assessmentItem>
+"#,
+        title = escape_xml(&format!(
+            "{} {} {}",
+            level_name(question),
+            question.category_name,
+            identifier
+        )),
+        answer = escape_xml(&sub_q.answer),
+        prompt = escape_xml(&plain(prompt)),
+    );
+    (identifier, xml)
+}
+
+/// QTI 2.1 のコンテンツパッケージ (ZIP)
+pub fn qti_package(questions: &[Question]) -> AppResult<Vec<u8>> {
+    let zip_err = |e: zip::result::ZipError| {
+        AppError::Internal(format!("QTI パッケージの作成に失敗しました: {}", e))
+    };
+    let io_err = |e: std::io::Error| {
+        AppError::Internal(format!("QTI パッケージの作成に失敗しました: {}", e))
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let mut resources = String::new();
+    for question in questions {
+        for sub_q in &question.sub_questions {
+            let (identifier, xml) = qti_item(question, sub_q);
+            let href = format!("items/{}.xml", identifier);
+            zip.start_file(href.as_str(), options).map_err(zip_err)?;
+            zip.write_all(xml.as_bytes()).map_err(io_err)?;
+            resources.push_str(&format!(
+                "    <resource identifier=\"{identifier}\" type=\"imsqti_item_xmlv2p1\" href=\"{href}\">\n      <file href=\"{href}\"/>\n    </resource>\n"
+            ));
+        }
+    }
+
+    let manifest = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest xmlns="http://www.imsglobal.org/xsd/imscp_v1p1" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imscp_v1p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/qtiv2p1_imscpv1p2_v1p0.xsd" identifier="MANIFEST-jlpt-questions">
+  <metadata>
+    <schema>QTIv2.1 Package</schema>
+    <schemaversion>1.0.0</schemaversion>
+  </metadata>
+  <organizations/>
+  <resources>
+{resources}  </resources>
+</manifest>
+"#
+    );
+    zip.start_file("imsmanifest.xml", options)
+        .map_err(zip_err)?;
+    zip.write_all(manifest.as_bytes()).map_err(io_err)?;
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question() -> Question {
+        Question {
+            id: "q1".to_string(),
+            level_id: 3,
+            level_name: "N3".to_string(),
+            category_id: Some("8".to_string()),
+            category_name: "文法 形式".to_string(),
+            sentence: "（　　）に入れるのに最もよいものを選びなさい。".to_string(),
+            prerequisites: None,
+            sub_questions: vec![SubQuestion {
+                id: 1,
+                sentence: Some("雨が降った<から>、行かない。".to_string()),
+                prerequisites: None,
+                select_answer: ["ので", "のに", "けど", "から"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| SelectAnswer {
+                        key: (i + 1).to_string(),
+                        value: v.to_string(),
+                    })
+                    .collect(),
+                answer: "1".to_string(),
+                explanation: Some("理由を表す".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_anki_card_layout_and_tags() {
+        let cards = anki_cards(&question());
+        assert_eq!(cards.len(), 1);
+        assert!(
+            cards[0]
+                .front
+                .contains("雨が降った&lt;から&gt;、行かない。<br><br>1. ので<br>2. のに")
+        );
+        assert_eq!(cards[0].back, "1. ので<br><br>理由を表す");
+        assert_eq!(cards[0].tags, vec!["JLPT::N3", "文法_形式"]);
+        assert_eq!(cards[0].guid, "q1_1");
+
+        let csv = anki_csv_rows(&cards).unwrap();
+        assert!(csv.ends_with(",JLPT::N3 文法_形式\n"));
+        assert_eq!(csv.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_mistake_card_shows_selected_and_correct_choice() {
+        let q = question();
+        let card = mistake_card(&q, &q.sub_questions[0], "2");
+        assert_eq!(
+            card.back,
+            "正解: 1. ので<br>あなたの解答: 2. のに<br><br>理由を表す"
+        );
+        assert!(card.front.ends_with("4. から"));
+
+        let mut q = q;
+        q.sub_questions[0]
+            .option_explanations
+            .insert("2".to_string(), "「のに」は不満を表す".to_string());
+        let card = mistake_card(&q, &q.sub_questions[0], "2");
+        assert!(
+            card.back.starts_with(
+                "正解: 1. ので<br>あなたの解答: 2. のに<br>「のに」は不満を表す<br><br>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_markup_is_html_in_anki_and_plain_in_qti() {
+        let mut q = question();
+        q.sub_questions[0].sentence = Some("<u>雨</u>が｜降《ふ》った（　　）".to_string());
+        q.sub_questions[0].select_answer[0].value = "<u>ので</u>".to_string();
+        let cards = anki_cards(&q);
+        assert!(cards[0].front.contains(
+            "<u>雨</u>が<ruby>降<rt>ふ</rt></ruby>った<span class=\"blank\">（　　）</span>"
+        ));
+        assert!(cards[0].back.starts_with("1. <u>ので</u>"));
+
+        let (_, xml) = qti_item(&q, &q.sub_questions[0]);
+        assert!(xml.contains("<prompt>雨が降った（　　）</prompt>"));
+        assert!(xml.contains("<simpleChoice identifier=\"choice_1\">ので</simpleChoice>"));
+        assert!(gift(&q).contains("\t=ので\n"));
+    }
+
+    #[test]
+    fn test_gift_marks_answer_and_escapes() {
+        let mut q = question();
+        q.sub_questions[0].select_answer[1].value = "a=b".to_string();
+        let gift = gift(&q);
+        assert!(gift.starts_with("$CATEGORY: $course$/JLPT/N3/文法 形式\n"));
+        assert!(gift.contains("::q1_1::[html]"));
+        assert!(gift.contains("\t=ので\n\t~a\\=b\n"));
+        assert!(gift.contains("\t####理由を表す\n}"));
+    }
+
+    #[test]
+    fn test_qti_item_declares_correct_choice() {
+        let q = question();
+        let (identifier, xml) = qti_item(&q, &q.sub_questions[0]);
+        assert_eq!(identifier, "q_q1_1");
+        assert!(xml.contains("<value>choice_1</value>"));
+        assert!(xml.contains("<prompt>雨が降った&lt;から&gt;、行かない。</prompt>"));
+        assert!(xml.contains("<simpleChoice identifier=\"choice_4\">から</simpleChoice>"));
+
+        let package = qti_package(&[q]).unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert!(names.contains(&"imsmanifest.xml"));
+        assert!(names.contains(&"items/q_q1_1.xml"));
+    }
+}
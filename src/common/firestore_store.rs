@@ -0,0 +1,308 @@
+use async_trait::async_trait;
+use firestore::{
+    FirestoreDb, FirestoreQueryCursor, FirestoreQueryDirection, FirestoreReference, FirestoreValue,
+    FirestoreWritePrecondition,
+};
+use log::warn;
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use super::database::{Direction, Store, StoreQuery, StoredDoc, WriteOp};
+use super::error::AppResult;
+
+/// ドキュメントIDで並べるときの特殊フィールド名
+const DOCUMENT_ID_FIELD: &str = "__name__";
+
+/// Firestore をバックエンドとする [`Store`] 実装
+#[derive(Debug, Clone)]
+pub struct FirestoreStore {
+    pub client: FirestoreDb,
+}
+
+impl FirestoreStore {
+    pub async fn new(project_id: &str) -> Self {
+        let client = match FirestoreDb::new(project_id).await {
+            Ok(client) => client,
+            Err(e) => panic!("Failed to create Firestore client: {}", e),
+        };
+        FirestoreStore { client }
+    }
+}
+
+/// フィールドパスのセグメントを Firestore の表記に変換する。
+///
+/// 英数字と `_` 以外を含むセグメント (カテゴリ名の「文法」など) はバッククォートで囲む。
+fn field_path<S: AsRef<str>>(segments: &[S]) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let segment = segment.as_ref();
+            let is_simple = segment
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if is_simple {
+                segment.to_string()
+            } else {
+                format!("`{}`", segment.replace('\\', "\\\\").replace('`', "\\`"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[async_trait]
+impl Store for FirestoreStore {
+    async fn create(&self, collection: &str, id: &str, doc: Value) -> AppResult<()> {
+        match self
+            .client
+            .fluent()
+            .insert()
+            .into(collection)
+            .document_id(id)
+            .object(&doc)
+            .execute::<Value>()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read(&self, collection: &str, id: &str) -> AppResult<Option<Value>> {
+        match self
+            .client
+            .fluent()
+            .select()
+            .by_id_in(collection)
+            .obj()
+            .one(id)
+            .await
+        {
+            Ok(data) => Ok(data),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_all(&self, collection: &str) -> AppResult<Vec<Value>> {
+        match self
+            .client
+            .fluent()
+            .list()
+            .from(collection)
+            .obj::<Value>()
+            .stream_all()
+            .await
+        {
+            Ok(data) => Ok(data.collect().await),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn update(&self, collection: &str, id: &str, doc: Value) -> AppResult<()> {
+        match self
+            .client
+            .fluent()
+            .update()
+            .in_col(collection)
+            .document_id(id)
+            .object(&doc)
+            .execute::<Value>()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> AppResult<()> {
+        match self
+            .client
+            .fluent()
+            .delete()
+            .from(collection)
+            .document_id(id)
+            .execute()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn query(&self, collection: &str, query: &StoreQuery) -> AppResult<Vec<StoredDoc>> {
+        let mut builder = self.client.fluent().select().from(collection);
+        if !query.filters.is_empty() || query.contains_any.is_some() {
+            builder = builder.filter(|q| {
+                q.for_all(
+                    query
+                        .filters
+                        .iter()
+                        .map(|(field, value)| q.field(field).eq(value))
+                        .chain(query.contains_any.iter().map(|(field, values)| {
+                            q.field(field)
+                                .array_contains_any(Value::Array(values.clone()))
+                        })),
+                )
+            });
+        }
+
+        // カーソルで位置を一意に決められるよう、同値はドキュメントID (__name__) で並べる
+        let direction = match query.order_by.as_ref().map(|(_, d)| d) {
+            Some(Direction::Descending) => FirestoreQueryDirection::Descending,
+            _ => FirestoreQueryDirection::Ascending,
+        };
+        match &query.order_by {
+            Some((field, _)) => {
+                builder = builder.order_by([
+                    (field.clone(), direction.clone()),
+                    (DOCUMENT_ID_FIELD.to_string(), direction),
+                ]);
+            }
+            None if query.start_after.is_some() => {
+                builder = builder.order_by([(DOCUMENT_ID_FIELD.to_string(), direction)]);
+            }
+            None => {}
+        }
+        if let Some(cursor) = &query.start_after {
+            let mut values: Vec<FirestoreValue> = Vec::new();
+            if query.order_by.is_some() {
+                values.push(cursor.value.clone().unwrap_or(Value::Null).into());
+            }
+            let reference = format!(
+                "{}/{}/{}",
+                self.client.get_documents_path(),
+                collection,
+                cursor.id
+            );
+            values.push(FirestoreReference(reference).into());
+            builder = builder.start_at(FirestoreQueryCursor::AfterValue(values));
+        }
+        if let Some(limit) = query.limit {
+            builder = builder.limit(limit);
+        }
+
+        match builder.stream_query_with_errors().await {
+            Ok(mut stream) => {
+                let mut result = Vec::new();
+                while let Some(item) = stream.next().await {
+                    let doc = item.and_then(|doc| {
+                        let id = doc.name.rsplit('/').next().unwrap_or_default().to_string();
+                        FirestoreDb::deserialize_doc_to::<Value>(&doc)
+                            .map(|data| StoredDoc { id, data })
+                    });
+                    match doc {
+                        Ok(doc) => result.push(doc),
+                        Err(e) => warn!("{} ドキュメント読取エラー: {}", collection, e),
+                    }
+                }
+                Ok(result)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn commit(&self, writes: Vec<WriteOp>) -> AppResult<()> {
+        let mut transaction = self.client.begin_transaction().await?;
+        for write in &writes {
+            match write {
+                WriteOp::Create {
+                    collection,
+                    id,
+                    doc,
+                } => {
+                    self.client
+                        .fluent()
+                        .update()
+                        .in_col(collection)
+                        .precondition(FirestoreWritePrecondition::Exists(false))
+                        .document_id(id)
+                        .object(doc)
+                        .add_to_transaction(&mut transaction)?;
+                }
+                WriteOp::Upsert {
+                    collection,
+                    id,
+                    doc,
+                } => {
+                    self.client
+                        .fluent()
+                        .update()
+                        .in_col(collection)
+                        .document_id(id)
+                        .object(doc)
+                        .add_to_transaction(&mut transaction)?;
+                }
+                WriteOp::Increment {
+                    collection,
+                    id,
+                    merge,
+                    increments,
+                } => {
+                    // merge のフィールドだけを更新マスクに含め、既存の集計値は上書きしない
+                    let mask: Vec<String> = merge.keys().map(|k| field_path(&[k])).collect();
+                    self.client
+                        .fluent()
+                        .update()
+                        .fields(mask)
+                        .in_col(collection)
+                        .document_id(id)
+                        .object(merge)
+                        .transforms(|t| {
+                            t.fields(
+                                increments.iter().map(|(path, value)| {
+                                    t.field(field_path(path)).increment(*value)
+                                }),
+                            )
+                        })
+                        .add_to_transaction(&mut transaction)?;
+                }
+                WriteOp::Merge {
+                    collection,
+                    id,
+                    fields,
+                } => {
+                    // fields だけを更新マスクに含め、存在しなければ作らずに失敗させる
+                    let mask: Vec<String> = fields.keys().map(|k| field_path(&[k])).collect();
+                    self.client
+                        .fluent()
+                        .update()
+                        .fields(mask)
+                        .in_col(collection)
+                        .precondition(FirestoreWritePrecondition::Exists(true))
+                        .document_id(id)
+                        .object(fields)
+                        .add_to_transaction(&mut transaction)?;
+                }
+                WriteOp::Delete { collection, id } => {
+                    self.client
+                        .fluent()
+                        .delete()
+                        .from(collection)
+                        .document_id(id)
+                        .add_to_transaction(&mut transaction)?;
+                }
+            }
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_path_quotes_non_identifier_segments() {
+        assert_eq!(field_path(&["levels", "N3", "total"]), "levels.N3.total");
+        assert_eq!(
+            field_path(&["levels", "N3", "categories", "文法", "correct"]),
+            "levels.N3.categories.`文法`.correct"
+        );
+        assert_eq!(field_path(&["a`b", "1x"]), "`a\\`b`.`1x`");
+    }
+}
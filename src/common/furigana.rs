@@ -0,0 +1,874 @@
+//! ふりがな (ルビ) の自動付与。
+//!
+//! 文を語に区切り、漢字を含む語の漢字の部分に [`Segment::Ruby`] を付ける。語の区切りと読みは
+//! 形態素解析 (lindera、IPADIC 形式の辞書) で求める。辞書は `embed-ipadic` フィーチャでバイナリに組み込むか、
+//! `FURIGANA_DICTIONARY` にビルド済みの辞書の場所を指定する。どちらも無いときは同梱の単語辞書
+//! (`data/furigana_dict.tsv`、約460語) だけを使う。このとき区切り方は辞書の語の数が最も少ないもの
+//! (辞書に無い漢字は大きく減点) を選び、同点なら長く一致する語を優先する。
+//! 動詞・形容詞は辞書形で登録し、送り仮名が活用した形にも一致させる。外部サービスは使わない。
+//!
+//! 読みが分からずふりがなを付けられなかった語は付けずに残し、[`Annotated::unresolved`] で返す。
+//!
+//! 漢字読み・表記の問題では、下線部と選択肢に現れる漢字にはルビを付けない (答えが分かってしまうため)。
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use lindera::{dictionary::load_dictionary, mode::Mode, segmenter::Segmenter};
+use log::{error, info, warn};
+use serde::Deserialize;
+
+use super::{
+    jlpt_kanji::is_above_level,
+    quality::{RuleSet, find_rule},
+    rich_text::{RichText, Segment, is_kanji},
+};
+use crate::models::question::{PublicQuestion, PublicSubQuestion, QuestionType};
+
+/// ふりがなを付ける範囲 (`?furigana=`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FuriganaMode {
+    #[default]
+    None,
+    /// 問題のレベルより難しい漢字を含む語だけ
+    AboveLevel,
+    All,
+}
+
+/// 形態素解析の辞書 (lindera の URI またはビルド済みの辞書のディレクトリ) を指す環境変数
+pub const DICTIONARY_ENV: &str = "FURIGANA_DICTIONARY";
+
+/// `embed-ipadic` フィーチャで組み込んだ辞書
+const EMBEDDED_DICTIONARY: &str = "embedded://ipadic";
+
+/// 辞書に無い漢字1文字の費用。辞書の語1つは 1、かな等は 0
+const UNKNOWN_KANJI_COST: u32 = 10;
+
+/// 辞書の語を漢字の並びとそれ以外の並びに分けたもの
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Kanji { base: String, reading: String },
+    Kana(String),
+}
+
+impl Part {
+    fn text(&self) -> &str {
+        match self {
+            Part::Kanji { base, .. } => base,
+            Part::Kana(text) => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// 辞書の表記 (辞書形)
+    surface: String,
+    /// 最後の漢字の並びまで。文とそのまま一致させる
+    stem: Vec<Part>,
+    /// 語末の送り仮名
+    okurigana: Vec<char>,
+}
+
+struct Dictionary {
+    entries: Vec<Entry>,
+    /// 先頭の文字 -> `entries` の添字 (辞書の行順)
+    index: HashMap<char, Vec<usize>>,
+}
+
+/// 文を語に区切る方法
+enum Analyzer {
+    /// 形態素解析。辞書は IPADIC 形式 (`base_form`・`reading` の欄を持つ)
+    Morphological(Box<Segmenter>),
+    /// 同梱の単語辞書。収録語にしか読みが付かない
+    WordList(Dictionary),
+}
+
+static ANALYZER: LazyLock<Analyzer> = LazyLock::new(Analyzer::load);
+
+impl Analyzer {
+    /// `FURIGANA_DICTIONARY`、組み込みの辞書の順に形態素解析の辞書を探し、無ければ同梱の単語辞書を使う
+    fn load() -> Self {
+        let uri = std::env::var(DICTIONARY_ENV)
+            .ok()
+            .or_else(|| cfg!(feature = "embed-ipadic").then(|| EMBEDDED_DICTIONARY.to_string()));
+        if let Some(uri) = uri {
+            match load_dictionary(&uri) {
+                Ok(dictionary) => {
+                    info!("ふりがなに形態素解析の辞書を使います: {}", uri);
+                    return Analyzer::Morphological(Box::new(Segmenter::new(
+                        Mode::Normal,
+                        dictionary,
+                        None,
+                    )));
+                }
+                Err(e) => error!("ふりがなの辞書 {} の読み込みに失敗: {}", uri, e),
+            }
+        }
+        warn!("形態素解析の辞書が無いため、ふりがなは同梱の単語辞書の語にだけ付けます");
+        Self::word_list()
+    }
+
+    fn word_list() -> Self {
+        Analyzer::WordList(Dictionary::parse(include_str!(
+            "../../data/furigana_dict.tsv"
+        )))
+    }
+
+    /// 文を区切り、(長さ, 読みの分かった語) を先頭から順に返す。語が None の区間は読みが分からない
+    fn segment(&self, text: &[char]) -> Vec<(usize, Option<Cow<'_, Entry>>)> {
+        match self {
+            Analyzer::WordList(dictionary) => dictionary
+                .segment(text)
+                .into_iter()
+                .map(|(len, entry)| (len, entry.map(Cow::Borrowed)))
+                .collect(),
+            Analyzer::Morphological(segmenter) => morphemes(segmenter, text),
+        }
+    }
+}
+
+/// 形態素解析で区切る。解析器が飛ばした空白などは1文字ずつの区間にする
+fn morphemes(segmenter: &Segmenter, text: &[char]) -> Vec<(usize, Option<Cow<'static, Entry>>)> {
+    let source: String = text.iter().collect();
+    let tokens = match segmenter.segment(Cow::Borrowed(source.as_str())) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            error!("形態素解析に失敗: {}", e);
+            return vec![(1, None); text.len()];
+        }
+    };
+    let char_at: HashMap<usize, usize> = source
+        .char_indices()
+        .enumerate()
+        .map(|(i, (byte, _))| (byte, i))
+        .chain([(source.len(), text.len())])
+        .collect();
+
+    let mut words = Vec::new();
+    let mut i = 0;
+    for mut token in tokens {
+        let (Some(&start), Some(&end)) =
+            (char_at.get(&token.byte_start), char_at.get(&token.byte_end))
+        else {
+            continue;
+        };
+        words.extend((i..start).map(|_| (1, None)));
+        let entry = if token.word_id.is_unknown() {
+            None
+        } else {
+            let surface = token.surface.to_string();
+            let base_form = token.get("base_form").map(str::to_string);
+            token
+                .get("reading")
+                .filter(|r| *r != "*")
+                .map(|r| r.chars().map(hiragana).collect::<String>())
+                .and_then(|reading| Entry::new(&surface, &reading))
+                .map(|mut entry| {
+                    if let Some(base_form) = base_form.filter(|b| b != "*") {
+                        entry.surface = base_form;
+                    }
+                    Cow::Owned(entry)
+                })
+        };
+        words.push((end - start, entry));
+        i = end;
+    }
+    words.extend((i..text.len()).map(|_| (1, None)));
+    words
+}
+
+impl Dictionary {
+    fn parse(data: &str) -> Self {
+        let mut dictionary = Dictionary {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        };
+        for line in data.lines().filter(|l| !l.starts_with('#')) {
+            let Some((surface, reading)) = line.split_once('\t') else {
+                continue;
+            };
+            match Entry::new(surface.trim(), reading.trim()) {
+                Some(entry) => {
+                    let first = entry.stem[0].text().chars().next().unwrap_or_default();
+                    dictionary
+                        .index
+                        .entry(first)
+                        .or_default()
+                        .push(dictionary.entries.len());
+                    dictionary.entries.push(entry);
+                }
+                None => warn!("ふりがな辞書の読みが表記と合いません: {}", line),
+            }
+        }
+        dictionary
+    }
+
+    /// 文を区切り、(長さ, 一致した語) を先頭から順に返す。語が None の区間は1文字
+    fn segment(&self, text: &[char]) -> Vec<(usize, Option<&Entry>)> {
+        segment_words(text, |i| {
+            self.index
+                .get(&text[i])
+                .into_iter()
+                .flatten()
+                .filter_map(|&e| Some((self.entries[e].match_at(text, i)?, &self.entries[e])))
+                .collect()
+        })
+    }
+}
+
+/// 文を語に区切り、(長さ, 一致した語) を先頭から順に返す。語が None の区間は1文字。
+///
+/// `candidates(i)` は位置 `i` から始まる語を (一致した文字数, 語) で優先する順に返す。
+/// 語の数が最も少ない区切り方 (語に当たらない漢字は大きく減点) を選び、同点なら長く一致する語を優先する
+pub(super) fn segment_words<T: Copy>(
+    text: &[char],
+    candidates: impl Fn(usize) -> Vec<(usize, T)>,
+) -> Vec<(usize, Option<T>)> {
+    let n = text.len();
+    let mut cost = vec![0u32; n + 1];
+    let mut pick: Vec<(usize, Option<T>)> = vec![(1, None); n];
+    for i in (0..n).rev() {
+        let unknown = if is_kanji(text[i]) {
+            UNKNOWN_KANJI_COST
+        } else {
+            0
+        };
+        let mut best = (unknown + cost[i + 1], 1, None);
+        for (len, word) in candidates(i) {
+            let c = 1 + cost[i + len];
+            if c < best.0 || (c == best.0 && len > best.1) {
+                best = (c, len, Some(word));
+            }
+        }
+        cost[i] = best.0;
+        pick[i] = (best.1, best.2);
+    }
+
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < n {
+        words.push(pick[i]);
+        i += pick[i].0;
+    }
+    words
+}
+
+/// `text[i..]` が語幹 `stem` と語末の送り仮名 `okurigana` の語 (活用した形を含む) で始まれば、一致した文字数を返す
+pub(super) fn match_word(
+    stem: impl IntoIterator<Item = char>,
+    okurigana: &[char],
+    text: &[char],
+    i: usize,
+) -> Option<usize> {
+    let mut j = i;
+    for c in stem {
+        if text.get(j) != Some(&c) {
+            return None;
+        }
+        j += 1;
+    }
+    let Some((&last, literal)) = okurigana.split_last() else {
+        return Some(j - i);
+    };
+    for c in literal {
+        if text.get(j) != Some(c) {
+            return None;
+        }
+        j += 1;
+    }
+    match text.get(j) {
+        Some(&next) if inflects(last, next) => Some(j + 1 - i),
+        // 一段動詞は語幹だけでも一致させる (食べ物 等)
+        _ if last == 'る' && !literal.is_empty() => Some(j - i),
+        _ => None,
+    }
+}
+
+impl Entry {
+    fn new(surface: &str, reading: &str) -> Option<Self> {
+        let reading: Vec<char> = reading.chars().collect();
+        let mut runs: Vec<(bool, Vec<char>)> = Vec::new();
+        for c in surface.chars() {
+            match runs.last_mut() {
+                Some((kanji, run)) if *kanji == is_kanji(c) => run.push(c),
+                _ => runs.push((is_kanji(c), vec![c])),
+            }
+        }
+        let mut stem = align(&runs, &reading)?;
+        let okurigana = match stem.last() {
+            Some(Part::Kana(text)) => {
+                let text = text.chars().collect();
+                stem.pop();
+                text
+            }
+            _ => Vec::new(),
+        };
+        stem.iter()
+            .any(|p| matches!(p, Part::Kanji { .. }))
+            .then(|| Entry {
+                surface: surface.to_string(),
+                stem,
+                okurigana,
+            })
+    }
+
+    /// `text[i..]` がこの語で始まれば、一致した文字数を返す
+    fn match_at(&self, text: &[char], i: usize) -> Option<usize> {
+        let stem = self.stem.iter().flat_map(|p| p.text().chars());
+        match_word(stem, &self.okurigana, text, i)
+    }
+
+    fn kanji(&self) -> impl Iterator<Item = char> + '_ {
+        self.stem.iter().flat_map(|p| match p {
+            Part::Kanji { base, .. } => base.chars().collect(),
+            Part::Kana(_) => Vec::new(),
+        })
+    }
+}
+
+/// 表記の並び (漢字か否か, 文字) に読みを割り当てる。漢字の並びは1文字以上の読みを持つ
+fn align(runs: &[(bool, Vec<char>)], reading: &[char]) -> Option<Vec<Part>> {
+    let Some(((kanji, run), rest)) = runs.split_first() else {
+        return reading.is_empty().then(Vec::new);
+    };
+    if !kanji {
+        let n = run.len();
+        if reading.len() < n || !run.iter().zip(reading).all(|(c, r)| hiragana(*c) == *r) {
+            return None;
+        }
+        let mut parts = align(rest, &reading[n..])?;
+        parts.insert(0, Part::Kana(run.iter().collect()));
+        return Some(parts);
+    }
+    (1..=reading.len()).find_map(|k| {
+        let mut parts = align(rest, &reading[k..])?;
+        parts.insert(
+            0,
+            Part::Kanji {
+                base: run.iter().collect(),
+                reading: reading[..k].iter().collect(),
+            },
+        );
+        Some(parts)
+    })
+}
+
+fn hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn is_hiragana(c: char) -> bool {
+    matches!(c, 'ぁ'..='ゖ')
+}
+
+/// 語末の送り仮名 `last` が活用して `next` になりうるか。活用しない語末は同じ文字のときだけ
+fn inflects(last: char, next: char) -> bool {
+    let row = match last {
+        'う' => "わいうえおっ",
+        'く' => "かきくけこい",
+        'ぐ' => "がぎぐげごい",
+        'す' => "さしすせそ",
+        'つ' => "たちつてとっ",
+        'ぬ' => "なにぬねのん",
+        'ぶ' => "ばびぶべぼん",
+        'む' => "まみむめもん",
+        // 形容詞 (高い・高かった・高く・高ければ・高さ・高そう)
+        'い' => "いかくけさそ",
+        'る' => return is_hiragana(next),
+        _ => return next == last,
+    };
+    row.contains(next)
+}
+
+/// ふりがなを付けた結果
+#[derive(Debug, Default, PartialEq)]
+pub struct Annotated {
+    /// 記法の文字列
+    pub text: String,
+    /// 付けるべきなのに読みが分からず付けなかった漢字の並び (出てきた順)
+    pub unresolved: Vec<String>,
+}
+
+/// `text` (記法の文字列) にふりがなを付ける。
+///
+/// `level_id` は `AboveLevel` の判定に使う。下線部・既存のルビと、`protected` の漢字を含む語には付けない
+pub fn annotate(
+    text: &str,
+    mode: FuriganaMode,
+    level_id: u32,
+    protected: &HashSet<char>,
+) -> Annotated {
+    ANALYZER.annotate(text, mode, level_id, protected)
+}
+
+impl Analyzer {
+    fn annotate(
+        &self,
+        text: &str,
+        mode: FuriganaMode,
+        level_id: u32,
+        protected: &HashSet<char>,
+    ) -> Annotated {
+        if mode == FuriganaMode::None {
+            return Annotated {
+                text: text.to_string(),
+                unresolved: Vec::new(),
+            };
+        }
+        let wanted = |kanji: &str| {
+            !kanji.chars().any(|c| protected.contains(&c))
+                && (mode == FuriganaMode::All || kanji.chars().any(|c| is_above_level(c, level_id)))
+        };
+
+        let mut segments = Vec::new();
+        let mut unresolved = Vec::new();
+        for segment in RichText::parse(text).0 {
+            let Segment::Text { text } = segment else {
+                segments.push(segment);
+                continue;
+            };
+            let chars: Vec<char> = text.chars().collect();
+            let mut plain = String::new();
+            let mut unknown = String::new();
+            let mut i = 0;
+            for (len, entry) in self.segment(&chars) {
+                let word = &chars[i..i + len];
+                i += len;
+                let Some(entry) = entry else {
+                    // 読みの分からない漢字は続く限り1語として扱う
+                    for &c in word {
+                        if is_kanji(c) {
+                            unknown.push(c);
+                        } else {
+                            take_unknown(&mut unknown, &mut unresolved, wanted);
+                        }
+                    }
+                    plain.extend(word);
+                    continue;
+                };
+                take_unknown(&mut unknown, &mut unresolved, wanted);
+                if !wanted(&entry.kanji().collect::<String>()) {
+                    plain.extend(word);
+                    continue;
+                }
+                for part in &entry.stem {
+                    match part {
+                        Part::Kanji { base, reading } => {
+                            flush(&mut segments, &mut plain);
+                            segments.push(Segment::Ruby {
+                                base: base.clone(),
+                                reading: reading.clone(),
+                            });
+                        }
+                        Part::Kana(kana) => plain.push_str(kana),
+                    }
+                }
+                let stem_len: usize = entry.stem.iter().map(|p| p.text().chars().count()).sum();
+                plain.extend(&word[stem_len..]);
+            }
+            take_unknown(&mut unknown, &mut unresolved, wanted);
+            flush(&mut segments, &mut plain);
+        }
+        Annotated {
+            text: RichText(segments).to_markup(),
+            unresolved,
+        }
+    }
+}
+
+/// 読みの分からない漢字の並びを、ふりがなが必要なら `unresolved` に加える
+fn take_unknown(unknown: &mut String, unresolved: &mut Vec<String>, wanted: impl Fn(&str) -> bool) {
+    let run = std::mem::take(unknown);
+    if !run.is_empty() && wanted(&run) && !unresolved.contains(&run) {
+        unresolved.push(run);
+    }
+}
+
+fn flush(segments: &mut Vec<Segment>, plain: &mut String) {
+    if !plain.is_empty() {
+        segments.push(Segment::Text {
+            text: std::mem::take(plain),
+        });
+    }
+}
+
+/// 配信する問題の問題文・本文・小問・選択肢にふりがなを付ける。
+///
+/// 漢字読み・表記の問題 ([`hides_target`]) は、全小問の下線部と選択肢に現れる漢字を含む語に付けず、
+/// 選択肢にも付けない。下線部の無い旧来の問題は問われている語が分からないため、小問の文にも付けない。
+/// 読みが分からず付けられなかった語は `furigana_unresolved` に入れる
+pub fn furigana_question(question: &mut PublicQuestion, mode: FuriganaMode, rules: &RuleSet) {
+    if mode == FuriganaMode::None {
+        return;
+    }
+    let level_id = question.level_id;
+    let reading_question = hides_target(question, rules);
+
+    let mut protected = HashSet::new();
+    if reading_question {
+        for sub_q in &question.sub_questions {
+            let sentence = RichText::parse(sub_q.sentence.as_deref().unwrap_or(""));
+            for segment in sentence.segments() {
+                if let Segment::Underline { text } = segment {
+                    protected.extend(text.chars().filter(|c| is_kanji(*c)));
+                }
+            }
+            for sa in &sub_q.select_answer {
+                protected.extend(sa.value.chars().filter(|c| is_kanji(*c)));
+            }
+        }
+    }
+
+    let mut unresolved = Vec::new();
+    let mut annotate = |text: &mut String| {
+        let annotated = annotate(text, mode, level_id, &protected);
+        *text = annotated.text;
+        for word in annotated.unresolved {
+            if !unresolved.contains(&word) {
+                unresolved.push(word);
+            }
+        }
+    };
+    annotate(&mut question.sentence);
+    if let Some(passage) = question.passage.as_mut() {
+        annotate(passage);
+    }
+    for sub_q in &mut question.sub_questions {
+        if let Some(sentence) = sub_q.sentence.as_mut()
+            && (!reading_question || RichText::parse(sentence).underline_count() > 0)
+        {
+            annotate(sentence);
+        }
+        if !reading_question {
+            for sa in &mut sub_q.select_answer {
+                annotate(&mut sa.value);
+            }
+        }
+    }
+    question.furigana_unresolved = unresolved;
+}
+
+/// 問われている語を隠す問題 (漢字読み・表記) か。
+///
+/// 品質規則で下線部が必要 (`missing_underline` が有効) なレベル・カテゴリの問題、下線部のある小問を持つ問題、
+/// 多肢選択で空欄の無い漢字の文に対して選択肢がすべて読み (ひらがな) の小問を持つ問題が当てはまる
+pub fn hides_target(question: &PublicQuestion, rules: &RuleSet) -> bool {
+    let underline_required = find_rule("missing_underline").is_some_and(|rule| {
+        rules
+            .effective(rule, question.level_id, question.category_id.as_deref())
+            .enabled
+    });
+    underline_required
+        || question.sub_questions.iter().any(|sub_q| {
+            let sentence = RichText::parse(sub_q.sentence.as_deref().unwrap_or(""));
+            sentence.underline_count() > 0
+                || (question.question_type == QuestionType::MultipleChoice
+                    && asks_reading(&sentence, sub_q))
+        })
+}
+
+/// 空欄の無い漢字を含む文に対し、選択肢がすべてひらがなの読み
+fn asks_reading(sentence: &RichText, sub_q: &PublicSubQuestion) -> bool {
+    sentence.blank_count() == 0
+        && sentence.to_plain().chars().any(is_kanji)
+        && !sub_q.select_answer.is_empty()
+        && sub_q.select_answer.iter().all(|sa| {
+            let value = sa.value.trim();
+            !value.is_empty() && value.chars().all(|c| is_hiragana(c) || c == 'ー')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::quality::RuleConfig;
+    use crate::models::question::{Question, SelectAnswer, SubQuestion};
+
+    static WORD_LIST: LazyLock<Analyzer> = LazyLock::new(Analyzer::word_list);
+
+    fn annotate(
+        text: &str,
+        mode: FuriganaMode,
+        level_id: u32,
+        protected: &HashSet<char>,
+    ) -> String {
+        WORD_LIST.annotate(text, mode, level_id, protected).text
+    }
+
+    fn all(text: &str) -> String {
+        annotate(text, FuriganaMode::All, 5, &HashSet::new())
+    }
+
+    #[test]
+    fn test_bundled_dictionary_aligns() {
+        let data = include_str!("../../data/furigana_dict.tsv");
+        let lines = data
+            .lines()
+            .filter(|l| !l.starts_with('#') && l.contains('\t'))
+            .count();
+        let Analyzer::WordList(dictionary) = &*WORD_LIST else {
+            unreachable!()
+        };
+        assert_eq!(dictionary.entries.len(), lines);
+    }
+
+    #[test]
+    fn test_reports_unresolved_words() {
+        let none = HashSet::new();
+        let annotated = WORD_LIST.annotate("雨が鬱陶しい。薔薇と雨", FuriganaMode::All, 5, &none);
+        assert_eq!(annotated.text, "｜雨《あめ》が鬱陶しい。薔薇と｜雨《あめ》");
+        assert_eq!(annotated.unresolved, vec!["鬱陶", "薔薇"]);
+
+        // 付けなくてよい語は数えない
+        let protected = HashSet::from(['鬱']);
+        let annotated = WORD_LIST.annotate("鬱陶しい", FuriganaMode::All, 5, &protected);
+        assert!(annotated.unresolved.is_empty());
+        let annotated = WORD_LIST.annotate("鬱陶しい", FuriganaMode::None, 5, &none);
+        assert!(annotated.unresolved.is_empty());
+    }
+
+    /// 語を数語だけ収めた IPADIC 形式の辞書をビルドする
+    fn morphological() -> Analyzer {
+        use lindera::dictionary::{DictionaryBuilder, Metadata};
+
+        let root = std::env::temp_dir().join(format!("furigana-{}", uuid::Uuid::new_v4()));
+        let source = root.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        let files = [
+            (
+                "char.def",
+                "DEFAULT 0 1 0\nSPACE 0 1 0\nKANJI 0 0 2\nHIRAGANA 1 1 0\n\
+                 0x0020 SPACE\n0x4E00..0x9FFF KANJI\n0x3041..0x309F HIRAGANA\n",
+            ),
+            (
+                "unk.def",
+                "DEFAULT,0,0,10000,記号,一般,*,*,*,*,*\nSPACE,0,0,10000,記号,空白,*,*,*,*,*\n\
+                 KANJI,0,0,20000,名詞,一般,*,*,*,*,*\nHIRAGANA,0,0,10000,記号,一般,*,*,*,*,*\n",
+            ),
+            ("matrix.def", "1 1\n0 0 0\n"),
+            (
+                "words.csv",
+                "雨,0,0,100,名詞,一般,*,*,*,*,雨,アメ,アメ\n\
+                 が,0,0,100,助詞,格助詞,一般,*,*,*,が,ガ,ガ\n\
+                 降っ,0,0,100,動詞,自立,*,*,五段・ラ行,連用タ接続,降る,フッ,フッ\n\
+                 た,0,0,100,助動詞,*,*,*,特殊・タ,基本形,た,タ,タ\n\
+                 お茶,0,0,100,名詞,一般,*,*,*,*,お茶,オチャ,オチャ\n",
+            ),
+        ];
+        for (name, data) in files {
+            std::fs::write(source.join(name), data).unwrap();
+        }
+        let output = root.join("dictionary");
+        DictionaryBuilder::new(Metadata::default())
+            .build_dictionary(&source, &output)
+            .unwrap();
+        let dictionary = load_dictionary(output.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        Analyzer::Morphological(Box::new(Segmenter::new(Mode::Normal, dictionary, None)))
+    }
+
+    #[test]
+    fn test_morphological_analyzer() {
+        let analyzer = morphological();
+        let annotated =
+            analyzer.annotate("雨が降った お茶 鬱", FuriganaMode::All, 5, &HashSet::new());
+        assert_eq!(
+            annotated.text,
+            "｜雨《あめ》が｜降《ふ》った お｜茶《ちゃ》 鬱"
+        );
+        assert_eq!(annotated.unresolved, vec!["鬱"]);
+
+        let chars: Vec<char> = "雨が降った".chars().collect();
+        let words: Vec<String> = analyzer
+            .segment(&chars)
+            .into_iter()
+            .filter_map(|(_, entry)| entry.map(|e| e.surface.clone()))
+            .collect();
+        assert_eq!(words, vec!["雨", "降る"]);
+    }
+
+    #[test]
+    fn test_annotates_words_and_okurigana() {
+        assert_eq!(
+            all("雨が降った（　　）、試合は中止になった。"),
+            "｜雨《あめ》が｜降《ふ》った（　　）、｜試合《しあい》は｜中止《ちゅうし》になった。"
+        );
+        // 複合語は長い語を優先し、送り仮名の途中のかなはそのまま
+        assert_eq!(all("取り消した"), "｜取《と》り｜消《け》した");
+        assert_eq!(all("食べ物"), "｜食《た》べ物");
+    }
+
+    #[test]
+    fn test_distinguishes_noun_and_verb() {
+        assert_eq!(all("話をする"), "｜話《はなし》をする");
+        assert_eq!(all("話した"), "｜話《はな》した");
+        let chars: Vec<char> = "話を話した".chars().collect();
+        let words: Vec<String> = WORD_LIST
+            .segment(&chars)
+            .into_iter()
+            .filter_map(|(_, entry)| entry.map(|e| e.surface.clone()))
+            .collect();
+        assert_eq!(words, vec!["話", "話す"]);
+    }
+
+    #[test]
+    fn test_above_level_and_protected() {
+        let none = HashSet::new();
+        // 「雨」は N5、「試」「験」は N4
+        assert_eq!(
+            annotate("雨の試験", FuriganaMode::AboveLevel, 5, &none),
+            "雨の｜試験《しけん》"
+        );
+        assert_eq!(
+            annotate("雨の試験", FuriganaMode::AboveLevel, 4, &none),
+            "雨の試験"
+        );
+        let protected = HashSet::from(['試']);
+        assert_eq!(
+            annotate("雨の試合", FuriganaMode::All, 5, &protected),
+            "｜雨《あめ》の試合"
+        );
+        // 下線部と既存のルビには付けない
+        assert_eq!(all("<u>雨</u>と｜雪《ゆき》"), "<u>雨</u>と｜雪《ゆき》");
+        assert_eq!(annotate("雨", FuriganaMode::None, 5, &none), "雨");
+    }
+
+    fn options(values: [&str; 4]) -> Vec<SelectAnswer> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| SelectAnswer {
+                key: (i + 1).to_string(),
+                value: v.to_string(),
+            })
+            .collect()
+    }
+
+    fn single(category_id: &str, sentence: &str, values: [&str; 4]) -> PublicQuestion {
+        PublicQuestion::from(Question {
+            level_id: 5,
+            category_id: Some(category_id.to_string()),
+            sub_questions: vec![SubQuestion {
+                id: 1,
+                sentence: Some(sentence.to_string()),
+                select_answer: options(values),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_reading_question_hides_target() {
+        let question = Question {
+            level_id: 5,
+            category_id: Some("2".to_string()),
+            sentence: "の言葉の読み方を選びなさい。".to_string(),
+            sub_questions: vec![
+                SubQuestion {
+                    id: 1,
+                    sentence: Some("<u>雨</u>が降る。毎日雨だ。".to_string()),
+                    select_answer: options(["あめ", "ゆき", "かぜ", "そら"]),
+                    ..Default::default()
+                },
+                SubQuestion {
+                    id: 2,
+                    sentence: Some("雨が降る。".to_string()),
+                    select_answer: options(["あめ", "ゆき", "かぜ", "そら"]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut public = PublicQuestion::from(question);
+        furigana_question(&mut public, FuriganaMode::All, &RuleSet::builtin());
+        assert_eq!(
+            public.sentence,
+            "の｜言葉《ことば》の｜読《よ》み｜方《かた》を｜選《えら》びなさい。"
+        );
+        assert_eq!(
+            public.sub_questions[0].sentence.as_deref(),
+            Some("<u>雨</u>が｜降《ふ》る。｜毎日《まいにち》雨だ。")
+        );
+        // 下線部の無い小問には付けない
+        assert_eq!(
+            public.sub_questions[1].sentence.as_deref(),
+            Some("雨が降る。")
+        );
+    }
+
+    #[test]
+    fn test_hides_target_outside_reading_categories() {
+        let rules = RuleSet::builtin();
+
+        // 下線部のある問題はカテゴリにかかわらず下線部と選択肢の漢字に付けない
+        let mut question = single("5", "<u>雨</u>が降る。", ["雨", "雪", "風", "空"]);
+        assert!(hides_target(&question, &rules));
+        furigana_question(&mut question, FuriganaMode::All, &rules);
+        assert_eq!(
+            question.sub_questions[0].sentence.as_deref(),
+            Some("<u>雨</u>が｜降《ふ》る。")
+        );
+        assert_eq!(question.sub_questions[0].select_answer[1].value, "雪");
+
+        // 下線部の無い旧来の漢字読みは、選択肢が読みであることで判定する
+        let mut question = single("7", "雨が降る。", ["あめ", "ゆき", "かぜ", "そら"]);
+        assert!(hides_target(&question, &rules));
+        furigana_question(&mut question, FuriganaMode::All, &rules);
+        assert_eq!(
+            question.sub_questions[0].sentence.as_deref(),
+            Some("雨が降る。")
+        );
+
+        // 空欄を埋める文法の問題は選択肢がひらがなでも隠さない
+        let mut question = single(
+            "8",
+            "雨が降った（　　）、中止だ。",
+            ["ので", "のに", "けど", "から"],
+        );
+        assert!(!hides_target(&question, &rules));
+        furigana_question(&mut question, FuriganaMode::All, &rules);
+        assert_eq!(
+            question.sub_questions[0].sentence.as_deref(),
+            Some("｜雨《あめ》が｜降《ふ》った（　　）、｜中止《ちゅうし》だ。")
+        );
+    }
+
+    #[test]
+    fn test_hides_target_follows_rule_config() {
+        let question = single("9", "雨が降る。", ["雨", "雪", "風", "空"]);
+        assert!(!hides_target(&question, &RuleSet::builtin()));
+
+        // 下線部が必要なカテゴリを設定で広げると、その問題も隠す
+        let rules = RuleSet::new(vec![RuleConfig {
+            id: "underline-9".to_string(),
+            rule: "missing_underline".to_string(),
+            enabled: Some(true),
+            category_ids: vec!["9".to_string()],
+            ..Default::default()
+        }]);
+        assert!(hides_target(&question, &rules));
+
+        // 漢字読みのカテゴリでも規則を無効にすれば問題自体から判定する
+        let question = single(
+            "2",
+            "雨が降った（　　）、中止だ。",
+            ["ので", "のに", "けど", "から"],
+        );
+        assert!(hides_target(&question, &RuleSet::builtin()));
+        let rules = RuleSet::new(vec![RuleConfig {
+            id: "no-underline-2".to_string(),
+            rule: "missing_underline".to_string(),
+            enabled: Some(false),
+            category_ids: vec!["2".to_string()],
+            ..Default::default()
+        }]);
+        assert!(!hides_target(&question, &rules));
+    }
+}
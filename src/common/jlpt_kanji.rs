@@ -0,0 +1,62 @@
+//! JLPT レベル別の漢字一覧 (`data/jlpt_kanji.tsv` を同梱)。
+//!
+//! レベルは `level_id` と同じ数字 (5 = N5 が最も易しい)。一覧に無い漢字は N1 とみなす。
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use super::rich_text::is_kanji;
+
+static KANJI_LEVELS: LazyLock<HashMap<char, u32>> =
+    LazyLock::new(|| parse_levels(include_str!("../../data/jlpt_kanji.tsv")));
+
+/// `N5<TAB>漢字の並び` の行を読む。同じ漢字は先に現れたレベルを使う
+fn parse_levels(data: &str) -> HashMap<char, u32> {
+    let mut levels = HashMap::new();
+    for (level, kanji) in level_lines(data) {
+        for c in kanji.chars() {
+            levels.entry(c).or_insert(level);
+        }
+    }
+    levels
+}
+
+/// `N5<TAB>...` 形式の一覧の各行を (レベル, 残り) にする。`#` の行は読み飛ばす
+pub(crate) fn level_lines(data: &str) -> impl Iterator<Item = (u32, &str)> {
+    data.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|line| {
+            let (level, rest) = line.split_once('\t')?;
+            let level = level.trim_start_matches('N').parse::<u32>().ok()?;
+            Some((level, rest))
+        })
+}
+
+/// 漢字のレベル。漢字でなければ None。々 などの記号は None
+pub fn kanji_level(c: char) -> Option<u32> {
+    if !is_kanji(c) || matches!(c, '々' | '〆' | 'ヶ') {
+        return None;
+    }
+    Some(KANJI_LEVELS.get(&c).copied().unwrap_or(1))
+}
+
+/// `level_id` の問題にとって難しすぎる漢字か
+pub fn is_above_level(c: char, level_id: u32) -> bool {
+    kanji_level(c).is_some_and(|level| level < level_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kanji_levels() {
+        assert_eq!(kanji_level('日'), Some(5));
+        assert_eq!(kanji_level('試'), Some(4));
+        assert_eq!(kanji_level('鬱'), Some(1));
+        assert_eq!(kanji_level('あ'), None);
+        assert_eq!(kanji_level('々'), None);
+        assert!(is_above_level('試', 5));
+        assert!(!is_above_level('試', 4));
+        assert!(!is_above_level('鬱', 1));
+    }
+}
@@ -0,0 +1,136 @@
+//! JLPT レベル別の語彙 (`data/jlpt_vocab.tsv` を同梱)。
+//!
+//! 語は辞書形で引く。文中の語はこの一覧の語 (全レベル) で区切り、動詞・形容詞は活用した形にも一致させる。
+//! ふりがなの辞書とは独立している。
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use super::{
+    furigana::{match_word, segment_words},
+    jlpt_kanji::level_lines,
+    rich_text::is_kanji,
+};
+
+/// 一覧の語
+#[derive(Debug)]
+struct Word {
+    /// 辞書形
+    surface: &'static str,
+    level: u32,
+    /// 最後の漢字まで。文とそのまま一致させる
+    stem: Vec<char>,
+    /// 語末の送り仮名
+    okurigana: Vec<char>,
+}
+
+struct Vocabulary {
+    words: Vec<Word>,
+    /// 辞書形 -> `words` の添字
+    by_surface: HashMap<&'static str, usize>,
+    /// 先頭の文字 -> `words` の添字 (一覧の行順)
+    index: HashMap<char, Vec<usize>>,
+}
+
+static VOCABULARY: LazyLock<Vocabulary> =
+    LazyLock::new(|| Vocabulary::parse(include_str!("../../data/jlpt_vocab.tsv")));
+
+impl Vocabulary {
+    fn parse(data: &'static str) -> Self {
+        let mut vocabulary = Vocabulary {
+            words: Vec::new(),
+            by_surface: HashMap::new(),
+            index: HashMap::new(),
+        };
+        for (level, words) in level_lines(data) {
+            for surface in words.split_whitespace() {
+                let chars: Vec<char> = surface.chars().collect();
+                let Some(last_kanji) = chars.iter().rposition(|c| is_kanji(*c)) else {
+                    continue;
+                };
+                if vocabulary.by_surface.contains_key(surface) {
+                    continue;
+                }
+                let i = vocabulary.words.len();
+                vocabulary.by_surface.insert(surface, i);
+                vocabulary.index.entry(chars[0]).or_default().push(i);
+                vocabulary.words.push(Word {
+                    surface,
+                    level,
+                    stem: chars[..=last_kanji].to_vec(),
+                    okurigana: chars[last_kanji + 1..].to_vec(),
+                });
+            }
+        }
+        vocabulary
+    }
+
+    fn segment(&self, text: &[char]) -> Vec<(usize, Option<&Word>)> {
+        segment_words(text, |i| {
+            self.index
+                .get(&text[i])
+                .into_iter()
+                .flatten()
+                .map(|&w| &self.words[w])
+                .filter_map(|word| {
+                    Some((
+                        match_word(word.stem.iter().copied(), &word.okurigana, text, i)?,
+                        word,
+                    ))
+                })
+                .collect()
+        })
+    }
+}
+
+/// 語 (辞書形) のレベル。一覧に無ければ None
+pub fn word_level(word: &str) -> Option<u32> {
+    VOCABULARY
+        .by_surface
+        .get(word)
+        .map(|&i| VOCABULARY.words[i].level)
+}
+
+/// 文 (装飾の無い文字列) の中で一覧に一致した語の辞書形 (出現順)
+pub fn vocabulary_words(text: &str) -> Vec<&'static str> {
+    let chars: Vec<char> = text.chars().collect();
+    VOCABULARY
+        .segment(&chars)
+        .into_iter()
+        .filter_map(|(_, word)| word.map(|w| w.surface))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_levels() {
+        assert_eq!(word_level("学校"), Some(5));
+        assert_eq!(word_level("試合"), Some(4));
+        assert_eq!(word_level("中止"), Some(3));
+        assert_eq!(word_level("曖昧"), Some(2));
+        assert_eq!(word_level("把握"), Some(1));
+        assert_eq!(word_level("東京"), None);
+    }
+
+    #[test]
+    fn test_every_level_is_bundled() {
+        for level in 1..=5 {
+            let count = VOCABULARY.words.iter().filter(|w| w.level == level).count();
+            assert!(count >= 500, "N{} の語が {} 語しかない", level, count);
+        }
+    }
+
+    #[test]
+    fn test_segments_with_inflection() {
+        assert_eq!(
+            vocabulary_words("会議の日程を把握して、曖昧な点を確かめた。"),
+            vec!["会議", "把握", "曖昧", "点", "確かめる"]
+        );
+        // 語は長く一致するものを優先し、同じ長さなら一覧の先の行 (易しいレベル) の語
+        assert_eq!(vocabulary_words("中学校で書いた"), vec!["中学校", "書く"]);
+        // かなだけの文や一覧に無い語は数えない
+        assert!(vocabulary_words("とても素敵").is_empty());
+    }
+}
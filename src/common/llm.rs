@@ -0,0 +1,139 @@
+//! 生成AI (LLM) クライアント。
+//!
+//! ハンドラは [`LlmClient`] トレイト越しに呼び出すため、テストではローカルのモックサーバーに向けた
+//! クライアントや固定の応答を返す実装に差し替えられる。
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use google_generative_ai_rs::v1::gemini::{
+    Content, Part, Role, request::Request, response::GeminiResponse,
+};
+use log::info;
+
+use crate::common::error::{AppError, AppResult};
+
+/// 既定のモデル
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
+/// 公開 API のベース URL
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// 1回の生成のタイムアウト (秒)
+const GENERATE_TIMEOUT_SECS: u64 = 120;
+
+/// ルーターに渡す LLM クライアント。未設定なら生成系のエンドポイントはエラーを返す
+pub type SharedLlm = Option<Arc<dyn LlmClient>>;
+
+#[async_trait]
+pub trait LlmClient: Send + Sync + fmt::Debug {
+    /// `Question.generated_by` に記録するモデル名
+    fn model_name(&self) -> &str;
+    /// プロンプトに対するテキスト出力
+    async fn generate(&self, prompt: &str) -> AppResult<String>;
+}
+
+/// `google-generative-ai-rs` を使う Gemini クライアント
+pub struct GeminiClient {
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+impl fmt::Debug for GeminiClient {
+    // API キーをログに出さない
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeminiClient")
+            .field("model", &self.model)
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+impl GeminiClient {
+    /// `base_url` は `.../v1beta` まで (モックサーバーを使う場合に差し替える)
+    pub fn new(api_key: &str, model: &str, base_url: Option<&str>) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            base_url: base_url
+                .unwrap_or(GEMINI_API_BASE)
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+
+    /// 環境変数 `GEMINI_API_KEY` (必須)・`GEMINI_MODEL`・`GEMINI_API_BASE` から構築する
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("GEMINI_API_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())?;
+        let model =
+            std::env::var("GEMINI_MODEL").unwrap_or_else(|_| DEFAULT_GEMINI_MODEL.to_string());
+        let base_url = std::env::var("GEMINI_API_BASE").ok();
+        info!("生成AI: {}", model);
+        Some(Self::new(&api_key, &model, base_url.as_deref()))
+    }
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, prompt: &str) -> AppResult<String> {
+        let url = format!("{}/models/{}:generateContent", self.base_url, self.model);
+
+        let request = Request {
+            contents: vec![Content {
+                role: Role::User,
+                parts: vec![Part {
+                    text: Some(prompt.to_string()),
+                    inline_data: None,
+                    file_data: None,
+                    video_metadata: None,
+                }],
+            }],
+            tools: vec![],
+            safety_settings: vec![],
+            generation_config: None,
+            system_instruction: None,
+        };
+
+        // API キーは URL に載せず (ログに残さないため) ヘッダで渡す
+        let response = reqwest::Client::new()
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .timeout(Duration::from_secs(GENERATE_TIMEOUT_SECS))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::Upstream(format!(
+                    "生成AIの呼び出しに失敗しました: {}",
+                    e.without_url()
+                ))
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Upstream(format!(
+                "生成AIの呼び出しに失敗しました: {} {}",
+                status, body
+            )));
+        }
+        let response: GeminiResponse = response
+            .json()
+            .await
+            .map_err(|_| AppError::Upstream("生成AIの応答形式が想定外です".to_string()))?;
+        let text: String = response
+            .candidates
+            .iter()
+            .flat_map(|c| c.content.parts.iter())
+            .filter_map(|p| p.text.as_deref())
+            .collect();
+        if text.is_empty() {
+            return Err(AppError::Upstream("生成AIの応答が空です".to_string()));
+        }
+        Ok(text)
+    }
+}
@@ -0,0 +1,567 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::database::{Direction, Store, StoreQuery, StoredDoc, WriteOp};
+use super::error::{AppError, AppResult};
+
+/// プロセス内メモリに保持する [`Store`] 実装 (テスト・ローカル開発用)。
+///
+/// Firestore の挙動に合わせ、一覧はドキュメントID順、`order_by` 指定時は
+/// 対象フィールドを持たないドキュメントを除外する。
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    collections: RwLock<HashMap<String, BTreeMap<String, Value>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// 等価比較。数値は整数/浮動小数の表現差を無視して比較する。
+fn values_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .unwrap_or_default()
+            .partial_cmp(&y.as_f64().unwrap_or_default())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        _ => Ordering::Equal,
+    }
+}
+
+/// `doc` の `path` にある数値へ `delta` を加算する。途中のマップが無ければ作成する。
+fn apply_increment(doc: &mut Value, path: &[String], delta: i64) -> AppResult<()> {
+    let Some((last, parents)) = path.split_last() else {
+        return Err(AppError::Internal("empty field path".to_string()));
+    };
+    let mut current = doc;
+    for segment in parents {
+        current = current
+            .as_object_mut()
+            .ok_or_else(|| AppError::Internal(format!("{} is not a map", segment)))?
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+    let map = current
+        .as_object_mut()
+        .ok_or_else(|| AppError::Internal(format!("parent of {} is not a map", last)))?;
+    let value = map.entry(last.clone()).or_insert(Value::from(0));
+    let base = match value {
+        Value::Number(n) => n
+            .as_i64()
+            .ok_or_else(|| AppError::Internal(format!("{} is not an integer", last)))?,
+        // Firestore の increment と同じく、数値以外は 0 として扱う
+        _ => 0,
+    };
+    *value = Value::from(base + delta);
+    Ok(())
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn create(&self, collection: &str, id: &str, doc: Value) -> AppResult<()> {
+        let mut collections = self
+            .collections
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let docs = collections.entry(collection.to_string()).or_default();
+        if docs.contains_key(id) {
+            return Err(AppError::Conflict(format!(
+                "{}/{} already exists",
+                collection, id
+            )));
+        }
+        docs.insert(id.to_string(), doc);
+        Ok(())
+    }
+
+    async fn read(&self, collection: &str, id: &str) -> AppResult<Option<Value>> {
+        let collections = self
+            .collections
+            .read()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(collections
+            .get(collection)
+            .and_then(|docs| docs.get(id))
+            .cloned())
+    }
+
+    async fn read_all(&self, collection: &str) -> AppResult<Vec<Value>> {
+        let collections = self
+            .collections
+            .read()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let docs = match collections.get(collection) {
+            Some(docs) => docs,
+            None => return Ok(vec![]),
+        };
+        Ok(docs.values().cloned().collect())
+    }
+
+    async fn update(&self, collection: &str, id: &str, doc: Value) -> AppResult<()> {
+        let mut collections = self
+            .collections
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(id.to_string(), doc);
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> AppResult<()> {
+        let mut collections = self
+            .collections
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Some(docs) = collections.get_mut(collection) {
+            docs.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn query(&self, collection: &str, query: &StoreQuery) -> AppResult<Vec<StoredDoc>> {
+        let collections = self
+            .collections
+            .read()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let docs = match collections.get(collection) {
+            Some(docs) => docs,
+            None => return Ok(vec![]),
+        };
+
+        let mut matched: Vec<(&String, &Value)> = docs
+            .iter()
+            .filter(|(_, doc)| {
+                query
+                    .filters
+                    .iter()
+                    .all(|(field, value)| doc.get(field).is_some_and(|v| values_eq(v, value)))
+                    && query.contains_any.as_ref().is_none_or(|(field, values)| {
+                        doc.get(field)
+                            .and_then(Value::as_array)
+                            .is_some_and(|items| {
+                                items
+                                    .iter()
+                                    .any(|item| values.iter().any(|v| values_eq(item, v)))
+                            })
+                    })
+            })
+            .collect();
+
+        // 並び替えキー (order_by の値, ドキュメントID) の比較。Firestore と同じく ID も同じ向きで比較する
+        let order_field = query.order_by.as_ref().map(|(field, _)| field.as_str());
+        let direction = query
+            .order_by
+            .as_ref()
+            .map(|(_, direction)| *direction)
+            .unwrap_or(Direction::Ascending);
+        let compare = |a: (Option<&Value>, &str), b: (Option<&Value>, &str)| {
+            let ord = match (a.0, b.0) {
+                (Some(x), Some(y)) => compare_values(x, y),
+                _ => Ordering::Equal,
+            }
+            .then_with(|| a.1.cmp(b.1));
+            match direction {
+                Direction::Ascending => ord,
+                Direction::Descending => ord.reverse(),
+            }
+        };
+
+        if let Some(field) = order_field {
+            matched.retain(|(_, doc)| doc.get(field).is_some());
+            matched.sort_by(|(a_id, a), (b_id, b)| {
+                compare((a.get(field), a_id), (b.get(field), b_id))
+            });
+        }
+
+        if let Some(cursor) = &query.start_after {
+            matched.retain(|(id, doc)| {
+                let key = (order_field.and_then(|f| doc.get(f)), id.as_str());
+                compare(key, (cursor.value.as_ref(), &cursor.id)) == Ordering::Greater
+            });
+        }
+
+        let limit = query.limit.map(|l| l as usize).unwrap_or(usize::MAX);
+        Ok(matched
+            .into_iter()
+            .take(limit)
+            .map(|(id, doc)| StoredDoc {
+                id: id.clone(),
+                data: doc.clone(),
+            })
+            .collect())
+    }
+
+    async fn commit(&self, writes: Vec<WriteOp>) -> AppResult<()> {
+        let mut collections = self
+            .collections
+            .write()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // 途中で失敗しても反映されないよう、変更後のドキュメントを先に組み立てる
+        let mut staged: Vec<((String, String), Option<Value>)> = Vec::new();
+        for write in writes {
+            match write {
+                WriteOp::Create {
+                    collection,
+                    id,
+                    doc,
+                } => {
+                    let key = (collection, id);
+                    let exists = match staged.iter().rev().find(|(k, _)| *k == key) {
+                        Some((_, staged_doc)) => staged_doc.is_some(),
+                        None => collections
+                            .get(&key.0)
+                            .is_some_and(|docs| docs.contains_key(&key.1)),
+                    };
+                    if exists {
+                        return Err(AppError::Conflict(format!(
+                            "{}/{} already exists",
+                            key.0, key.1
+                        )));
+                    }
+                    staged.push((key, Some(doc)));
+                }
+                WriteOp::Upsert {
+                    collection,
+                    id,
+                    doc,
+                } => staged.push(((collection, id), Some(doc))),
+                WriteOp::Delete { collection, id } => staged.push(((collection, id), None)),
+                WriteOp::Merge {
+                    collection,
+                    id,
+                    fields,
+                } => {
+                    let key = (collection, id);
+                    let mut doc = staged
+                        .iter()
+                        .rev()
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, doc)| doc.clone())
+                        .unwrap_or_else(|| {
+                            collections
+                                .get(&key.0)
+                                .and_then(|docs| docs.get(&key.1))
+                                .cloned()
+                        })
+                        .ok_or_else(|| {
+                            AppError::NotFound(format!("{}/{} does not exist", key.0, key.1))
+                        })?;
+                    doc.as_object_mut()
+                        .ok_or_else(|| {
+                            AppError::Internal(format!("{}/{} is not a map", key.0, key.1))
+                        })?
+                        .extend(fields);
+                    staged.push((key, Some(doc)));
+                }
+                WriteOp::Increment {
+                    collection,
+                    id,
+                    merge,
+                    increments,
+                } => {
+                    let key = (collection, id);
+                    let mut doc = staged
+                        .iter()
+                        .rev()
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, doc)| doc.clone())
+                        .unwrap_or_else(|| {
+                            collections
+                                .get(&key.0)
+                                .and_then(|docs| docs.get(&key.1))
+                                .cloned()
+                        })
+                        .unwrap_or_else(|| Value::Object(Default::default()));
+                    let map = doc.as_object_mut().ok_or_else(|| {
+                        AppError::Internal(format!("{}/{} is not a map", key.0, key.1))
+                    })?;
+                    map.extend(merge);
+                    for (path, delta) in &increments {
+                        apply_increment(&mut doc, path, *delta)?;
+                    }
+                    staged.push((key, Some(doc)));
+                }
+            }
+        }
+
+        for ((collection, id), doc) in staged {
+            let docs = collections.entry(collection).or_default();
+            match doc {
+                Some(doc) => {
+                    docs.insert(id, doc);
+                }
+                None => {
+                    docs.remove(&id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::database::Cursor;
+
+    #[tokio::test]
+    async fn test_create_conflict_and_upsert() {
+        let store = MemoryStore::new();
+        store.create("c", "a", json!({"v": 1})).await.unwrap();
+        assert!(store.create("c", "a", json!({"v": 2})).await.is_err());
+
+        store.update("c", "a", json!({"v": 3})).await.unwrap();
+        store.update("c", "b", json!({"v": 4})).await.unwrap();
+        assert_eq!(store.read("c", "a").await.unwrap(), Some(json!({"v": 3})));
+        assert_eq!(store.read_all("c").await.unwrap().len(), 2);
+
+        store.delete("c", "a").await.unwrap();
+        store.delete("c", "missing").await.unwrap();
+        assert_eq!(store.read("c", "a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_query_filter_order_limit() {
+        let store = MemoryStore::new();
+        for (id, user, at, ok) in [
+            ("1", "u1", 10, false),
+            ("2", "u1", 30, true),
+            ("3", "u1", 20, false),
+            ("4", "u2", 40, false),
+        ] {
+            store
+                .create(
+                    "a",
+                    id,
+                    json!({"user_id": user, "answered_at": at, "is_correct": ok}),
+                )
+                .await
+                .unwrap();
+        }
+
+        let query = StoreQuery::new()
+            .eq("user_id", "u1")
+            .eq("is_correct", false)
+            .order_by("answered_at", Direction::Descending);
+        let docs = store.query("a", &query).await.unwrap();
+        let ats: Vec<i64> = docs
+            .iter()
+            .map(|d| d.data["answered_at"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ats, vec![20, 10]);
+
+        let query = StoreQuery::new()
+            .eq("user_id", "u1")
+            .order_by("answered_at", Direction::Ascending)
+            .limit(1);
+        let docs = store.query("a", &query).await.unwrap();
+        assert_eq!(docs[0].data["answered_at"], json!(10));
+    }
+
+    #[tokio::test]
+    async fn test_commit_increment_creates_nested_fields() {
+        let store = MemoryStore::new();
+        let increment = |delta: i64| WriteOp::Increment {
+            collection: "s".to_string(),
+            id: "u1".to_string(),
+            merge: json!({"user_id": "u1"}).as_object().unwrap().clone(),
+            increments: vec![
+                (vec!["total".to_string()], 1),
+                (
+                    vec![
+                        "levels".to_string(),
+                        "N3".to_string(),
+                        "correct".to_string(),
+                    ],
+                    delta,
+                ),
+            ],
+        };
+        store.commit(vec![increment(1)]).await.unwrap();
+        store
+            .commit(vec![increment(0), WriteOp::delete("s", "missing")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.read("s", "u1").await.unwrap(),
+            Some(json!({"user_id": "u1", "total": 2, "levels": {"N3": {"correct": 1}}}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_merge_requires_existing_doc() {
+        let store = MemoryStore::new();
+        store
+            .create("s", "x", json!({"a": 1, "b": [1]}))
+            .await
+            .unwrap();
+        let merge =
+            |id: &str| WriteOp::merge("s", id, json!({"b": [2]}).as_object().unwrap().clone());
+
+        store.commit(vec![merge("x")]).await.unwrap();
+        assert_eq!(
+            store.read("s", "x").await.unwrap(),
+            Some(json!({"a": 1, "b": [2]}))
+        );
+
+        // 無いドキュメントは作らず、コミット全体を失敗させる
+        let result = store
+            .commit(vec![
+                WriteOp::upsert("s", "y", &json!({"v": 1})).unwrap(),
+                merge("missing"),
+            ])
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        assert_eq!(store.read("s", "missing").await.unwrap(), None);
+        assert_eq!(store.read("s", "y").await.unwrap(), None);
+
+        // 同じコミットで削除したドキュメントにも書かない
+        let result = store
+            .commit(vec![WriteOp::delete("s", "x"), merge("x")])
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_commit_is_all_or_nothing() {
+        let store = MemoryStore::new();
+        store.create("s", "x", json!({"v": "text"})).await.unwrap();
+
+        let result = store
+            .commit(vec![
+                WriteOp::upsert("s", "y", &json!({"v": 1})).unwrap(),
+                WriteOp::Increment {
+                    collection: "s".to_string(),
+                    id: "x".to_string(),
+                    merge: Default::default(),
+                    increments: vec![(vec!["v".to_string(), "n".to_string()], 1)],
+                },
+            ])
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(store.read("s", "y").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_commit_create_conflicts_on_existing_doc() {
+        let store = MemoryStore::new();
+        store.create("s", "x", json!({"v": 1})).await.unwrap();
+
+        let result = store
+            .commit(vec![
+                WriteOp::upsert("s", "y", &json!({"v": 2})).unwrap(),
+                WriteOp::create("s", "x", &json!({"v": 3})).unwrap(),
+            ])
+            .await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+        assert_eq!(store.read("s", "x").await.unwrap(), Some(json!({"v": 1})));
+        assert_eq!(store.read("s", "y").await.unwrap(), None);
+
+        // 同じコミット内で削除してから作成するのは可
+        store
+            .commit(vec![
+                WriteOp::delete("s", "x"),
+                WriteOp::create("s", "x", &json!({"v": 4})).unwrap(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(store.read("s", "x").await.unwrap(), Some(json!({"v": 4})));
+    }
+
+    #[tokio::test]
+    async fn test_query_start_after_breaks_ties_by_id() {
+        let store = MemoryStore::new();
+        for (id, at) in [("a", 10), ("b", 20), ("c", 20), ("d", 30)] {
+            store
+                .create("h", id, json!({"answered_at": at}))
+                .await
+                .unwrap();
+        }
+
+        let query = StoreQuery::new()
+            .order_by("answered_at", Direction::Descending)
+            .start_after(Cursor {
+                value: Some(json!(20)),
+                id: "c".to_string(),
+            });
+        let ids: Vec<String> = store
+            .query("h", &query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|d| d.id)
+            .collect();
+        assert_eq!(ids, vec!["b", "a"]);
+
+        let query = StoreQuery::new().start_after(Cursor {
+            value: None,
+            id: "b".to_string(),
+        });
+        assert_eq!(store.query("h", &query).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_numeric_equality() {
+        let store = MemoryStore::new();
+        store
+            .create("q", "x", json!({"level_id": 3}))
+            .await
+            .unwrap();
+        let docs = store
+            .query("q", &StoreQuery::new().eq("level_id", 3u32))
+            .await
+            .unwrap();
+        assert_eq!(docs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_contains_any() {
+        let store = MemoryStore::new();
+        for (id, doc) in [
+            ("a", json!({"keys": ["k1", "k2"], "level": 1})),
+            ("b", json!({"keys": ["k3"], "level": 1})),
+            ("c", json!({"keys": ["k2"], "level": 2})),
+            ("d", json!({"level": 1})),
+        ] {
+            store.create("q", id, doc).await.unwrap();
+        }
+
+        let ids = |docs: Vec<StoredDoc>| docs.into_iter().map(|d| d.id).collect::<Vec<_>>();
+        let query = StoreQuery::new().contains_any("keys", &["k2", "k3"]);
+        assert_eq!(
+            ids(store.query("q", &query).await.unwrap()),
+            ["a", "b", "c"]
+        );
+
+        let query = StoreQuery::new()
+            .eq("level", 1)
+            .contains_any("keys", &["k2"]);
+        assert_eq!(ids(store.query("q", &query).await.unwrap()), ["a"]);
+
+        let query = StoreQuery::new().contains_any("keys", &["k9"]);
+        assert!(store.query("q", &query).await.unwrap().is_empty());
+    }
+}
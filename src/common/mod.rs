@@ -1,3 +1,15 @@
+pub mod apkg;
 pub mod database;
 pub mod dedup;
+pub mod error;
+pub mod export;
+pub mod firestore_store;
+pub mod furigana;
+pub mod jlpt_kanji;
+pub mod jlpt_vocab;
+pub mod llm;
+pub mod memory_store;
+pub mod quality;
+pub mod rich_text;
+pub mod shuffle;
 pub mod similarity;
@@ -0,0 +1,403 @@
+//! 問題の品質規則。
+//!
+//! 品質監視 (`POST /api/admin/monitor-quality`) の異常検出と、
+//! 問題の作成・更新・取り込み・生成時のバリデーションで同じ規則を使う。
+//! 規則は [`QualityRule`] を実装して [`REGISTRY`] に並べる。
+//! 有効・無効、重さ ([`Severity`])、引数はレベル・カテゴリごとに設定できる。
+//! 設定は組み込みの既定値、`QUALITY_RULES_FILE` の JSON、Firestore の `quality_rules` の順に重ねる。
+
+mod rules;
+
+use std::fmt;
+
+use log::{error, warn};
+use serde::Serialize;
+use serde_json::Value;
+
+pub use rules::{LevelViolations, REGISTRY, level_violations};
+
+pub use crate::models::quality_rule::{RuleConfig, Severity};
+use crate::{
+    common::{database::Database, error::AppResult},
+    models::{
+        quality_rule::QUALITY_RULES_COLLECTION,
+        question::{Question, SubQuestion},
+    },
+};
+
+/// 規則を読み込む JSON ファイル (`RuleConfig` の配列) を指す環境変数
+pub const RULES_FILE_ENV: &str = "QUALITY_RULES_FILE";
+
+/// 品質規則。`check` は設定とは無関係に、規則に反する箇所をすべて返す
+pub trait QualityRule: Send + Sync {
+    /// 設定で参照するID (snake_case)
+    fn id(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn default_severity(&self) -> Severity;
+    /// 既定で適用するカテゴリ。空ならすべてのカテゴリ
+    fn default_categories(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// 引数の既定値 (オブジェクト)
+    fn default_params(&self) -> Value {
+        Value::Null
+    }
+    /// 設定の引数を確かめる。誤りは日本語の説明で返す
+    fn validate_params(&self, _params: &Value) -> Result<(), String> {
+        Ok(())
+    }
+    fn check(&self, question: &Question, params: &Value) -> Vec<Issue>;
+}
+
+/// 規則に反する箇所1つ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// 小問の箇所なら小問ID
+    pub sub_question_id: Option<u32>,
+    pub message: String,
+    /// 監視レポートに添える値
+    pub detail: Option<Value>,
+}
+
+impl Issue {
+    pub fn question(message: impl Into<String>) -> Self {
+        Self {
+            sub_question_id: None,
+            message: message.into(),
+            detail: None,
+        }
+    }
+
+    pub fn sub(sub_question_id: u32, message: impl Into<String>) -> Self {
+        Self {
+            sub_question_id: Some(sub_question_id),
+            message: message.into(),
+            detail: None,
+        }
+    }
+}
+
+/// 小問ごとの検査を [`Issue`] にまとめる
+pub fn per_sub_question(
+    question: &Question,
+    f: impl Fn(&SubQuestion) -> Vec<String>,
+) -> Vec<Issue> {
+    question
+        .sub_questions
+        .iter()
+        .flat_map(|sub_q| f(sub_q).into_iter().map(|m| Issue::sub(sub_q.id, m)))
+        .collect()
+}
+
+/// 設定を適用した検査結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_question_id: Option<u32>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<Value>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sub_question_id {
+            Some(id) => write!(f, "小問{}: {}", id, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// ある問題に対する規則の設定 (設定を重ねた結果)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveRule {
+    pub enabled: bool,
+    pub severity: Severity,
+    pub params: Value,
+}
+
+/// 規則の ID から登録済みの規則を引く
+pub fn find_rule(id: &str) -> Option<&'static dyn QualityRule> {
+    REGISTRY.iter().copied().find(|rule| rule.id() == id)
+}
+
+/// 設定を確かめる (規則が存在し、引数が正しいこと)
+pub fn validate_config(config: &RuleConfig) -> Result<(), String> {
+    let rule =
+        find_rule(&config.rule).ok_or_else(|| format!("規則が存在しません: {}", config.rule))?;
+    if !config.params.is_null() && !config.params.is_object() {
+        return Err("params はオブジェクトで指定してください".to_string());
+    }
+    rule.validate_params(&merge_params(rule.default_params(), &config.params))
+}
+
+/// 規則の設定一式
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    /// 後のものほど優先する
+    configs: Vec<RuleConfig>,
+}
+
+impl RuleSet {
+    /// 誤った設定 (存在しない規則・不正な引数) は読み飛ばす
+    pub fn new(configs: Vec<RuleConfig>) -> Self {
+        let configs = configs
+            .into_iter()
+            .filter(|config| match validate_config(config) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("品質規則の設定を無視します ({}): {}", config.id, e);
+                    false
+                }
+            })
+            .collect();
+        Self { configs }
+    }
+
+    /// 組み込みの既定値だけを使う
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    /// `QUALITY_RULES_FILE` と Firestore の設定を読み込む
+    pub async fn load(db: &Database) -> AppResult<Self> {
+        let mut configs = file_configs();
+        let mut stored = db.read_all::<RuleConfig>(QUALITY_RULES_COLLECTION).await?;
+        stored.sort_by(|a, b| a.id.cmp(&b.id));
+        configs.extend(stored);
+        Ok(Self::new(configs))
+    }
+
+    pub fn configs(&self) -> &[RuleConfig] {
+        &self.configs
+    }
+
+    /// `rule` を `level_id` / `category_id` の問題に適用するときの設定
+    pub fn effective(
+        &self,
+        rule: &dyn QualityRule,
+        level_id: u32,
+        category_id: Option<&str>,
+    ) -> EffectiveRule {
+        let scope = rule.default_categories();
+        let mut effective = EffectiveRule {
+            enabled: scope.is_empty() || category_id.is_some_and(|c| scope.contains(&c)),
+            severity: rule.default_severity(),
+            params: rule.default_params(),
+        };
+        let mut matching: Vec<&RuleConfig> = self
+            .configs
+            .iter()
+            .filter(|c| c.rule == rule.id() && c.matches(level_id, category_id))
+            .collect();
+        // 安定ソートなので同じ狭さなら後の設定が勝つ
+        matching.sort_by_key(|c| c.specificity());
+        for config in matching {
+            if let Some(enabled) = config.enabled {
+                effective.enabled = enabled;
+            }
+            if let Some(severity) = config.severity {
+                effective.severity = severity;
+            }
+            effective.params = merge_params(effective.params, &config.params);
+        }
+        effective
+    }
+
+    /// 有効な規則のうち `min` 以上の重さのものを適用する。
+    /// 結果は問題全体の箇所、小問ID順の順に並ぶ
+    pub fn check(&self, question: &Question, min: Severity) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for rule in REGISTRY {
+            let effective =
+                self.effective(*rule, question.level_id, question.category_id.as_deref());
+            if !effective.enabled || effective.severity < min {
+                continue;
+            }
+            findings.extend(
+                rule.check(question, &effective.params)
+                    .into_iter()
+                    .map(|issue| Finding {
+                        rule: rule.id(),
+                        severity: effective.severity,
+                        sub_question_id: issue.sub_question_id,
+                        message: issue.message,
+                        detail: issue.detail,
+                    }),
+            );
+        }
+        findings.sort_by_key(|f| f.sub_question_id);
+        findings
+    }
+}
+
+/// `QUALITY_RULES_FILE` の設定。読めなければ記録して空とする
+fn file_configs() -> Vec<RuleConfig> {
+    let Ok(path) = std::env::var(RULES_FILE_ENV) else {
+        return vec![];
+    };
+    let parsed = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<Vec<RuleConfig>>(&s).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(configs) => configs,
+        Err(e) => {
+            error!("{} の読み込みに失敗: {}", path, e);
+            vec![]
+        }
+    }
+}
+
+/// オブジェクトのキー単位で `overlay` を `base` に重ねる
+fn merge_params(base: Value, overlay: &Value) -> Value {
+    match (base, overlay) {
+        (base, Value::Null) => base,
+        (Value::Object(mut base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                base.insert(key.clone(), value.clone());
+            }
+            Value::Object(base)
+        }
+        (_, overlay) => overlay.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::question::SelectAnswer;
+
+    fn question(category_id: &str, values: &[&str]) -> Question {
+        Question {
+            level_id: 3,
+            category_id: Some(category_id.to_string()),
+            sentence: "次の文の".to_string(),
+            sub_questions: vec![SubQuestion {
+                id: 1,
+                sentence: Some("（　　）を読む".to_string()),
+                select_answer: values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| SelectAnswer {
+                        key: (i + 1).to_string(),
+                        value: v.to_string(),
+                    })
+                    .collect(),
+                answer: "1".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn config(rule: &str, f: impl FnOnce(&mut RuleConfig)) -> RuleConfig {
+        let mut config = RuleConfig {
+            rule: rule.to_string(),
+            ..Default::default()
+        };
+        f(&mut config);
+        config
+    }
+
+    fn rules(findings: &[Finding]) -> Vec<(&'static str, Severity)> {
+        findings.iter().map(|f| (f.rule, f.severity)).collect()
+    }
+
+    #[test]
+    fn test_builtin_scopes_and_severities() {
+        let rules_set = RuleSet::builtin();
+        let q = question("2", &["ほん", "ほん", "よむ"]);
+        assert_eq!(
+            rules(&rules_set.check(&q, Severity::Warn)),
+            vec![
+                ("empty_blank", Severity::Delete),
+                ("option_count", Severity::Delete),
+                ("duplicate_option", Severity::Quarantine),
+                ("missing_underline", Severity::Warn),
+            ]
+        );
+        // 空括弧と下線部は漢字読み・表記だけで確かめる
+        let q = question("8", &["ほん", "ほん", "よむ"]);
+        assert_eq!(
+            rules(&rules_set.check(&q, Severity::Quarantine)),
+            vec![
+                ("option_count", Severity::Delete),
+                ("duplicate_option", Severity::Quarantine),
+            ]
+        );
+        assert_eq!(
+            rules_set.check(&q, Severity::Delete)[0].to_string(),
+            "小問1: 選択肢3個"
+        );
+    }
+
+    #[test]
+    fn test_configs_are_layered_by_scope() {
+        let rules_set = RuleSet::new(vec![
+            config("option_count", |c| {
+                c.level_ids = vec![3];
+                c.category_ids = vec!["8".to_string()];
+                c.params = json!({"count": 3});
+            }),
+            config("option_count", |c| c.severity = Some(Severity::Warn)),
+            config("duplicate_option", |c| {
+                c.category_ids = vec!["8".to_string()];
+                c.enabled = Some(false);
+            }),
+            config("missing_underline", |c| {
+                c.category_ids = vec!["8".to_string()];
+                c.enabled = Some(true);
+            }),
+            // 存在しない規則・不正な引数は無視する
+            config("no_such_rule", |_| {}),
+            config("option_count", |c| c.params = json!({"count": 0})),
+        ]);
+        assert_eq!(rules_set.configs().len(), 4);
+
+        let rule = find_rule("option_count").unwrap();
+        assert_eq!(
+            rules_set.effective(rule, 3, Some("8")),
+            EffectiveRule {
+                enabled: true,
+                severity: Severity::Warn,
+                params: json!({"count": 3}),
+            }
+        );
+        assert_eq!(
+            rules_set.effective(rule, 2, Some("8")).params,
+            json!({"count": 4})
+        );
+
+        let q = question("8", &["ほん", "ほん", "よむ"]);
+        assert_eq!(
+            rules(&rules_set.check(&q, Severity::Warn)),
+            vec![("missing_underline", Severity::Warn)]
+        );
+        let mut q = question("8", &["ほん", "ほん", "よむ"]);
+        q.level_id = 2;
+        assert_eq!(
+            rules(&rules_set.check(&q, Severity::Warn)),
+            vec![
+                ("option_count", Severity::Warn),
+                ("missing_underline", Severity::Warn),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(validate_config(&config("option_count", |_| {})).is_ok());
+        assert_eq!(
+            validate_config(&config("nope", |_| {})).unwrap_err(),
+            "規則が存在しません: nope"
+        );
+        assert!(validate_config(&config("option_count", |c| c.params = json!(4))).is_err());
+        assert!(
+            validate_config(&config("option_count", |c| c.params = json!({"count": "4"}))).is_err()
+        );
+    }
+}
@@ -0,0 +1,843 @@
+//! 組み込みの品質規則。
+//!
+//! 規則は出題形式 (`question_type`) ごとに異なる。
+//! 文字装飾の記法は [`crate::common::rich_text`] で読み取る。
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use super::{Issue, QualityRule, Severity, per_sub_question};
+use crate::{
+    common::{
+        jlpt_kanji::is_above_level,
+        jlpt_vocab::{vocabulary_words, word_level},
+        rich_text::{RichText, Segment},
+    },
+    models::{
+        hint::HintKind,
+        question::{Question, QuestionType, SubQuestion},
+    },
+};
+
+/// 登録済みの規則。検査結果はこの順に並ぶ
+pub static REGISTRY: &[&dyn QualityRule] = &[
+    &PassageRequired,
+    &MarkupSyntax,
+    &BlankCount,
+    &EmptyBlank,
+    &OptionCount,
+    &AnswerKey,
+    &EmptyOption,
+    &EmptySentence,
+    &EliminateHint,
+    &Ordering,
+    &DuplicateOption,
+    &MissingUnderline,
+    &LevelVocabulary,
+];
+
+/// 空括弧を異常とみなし、下線部の数を確かめる規則の既定のカテゴリ (漢字読み(2)・表記(3))。
+/// 文脈規定(4)・文法(8)等の穴埋め問題では（　　）は正常。
+/// 実際に当てはまるカテゴリは設定で変わるため、[`RuleSet::effective`](super::RuleSet::effective) で判定する
+const UNDERLINE_CATEGORY_IDS: &[&str] = &["2", "3"];
+
+fn sentence(sub_q: &SubQuestion) -> &str {
+    sub_q.sentence.as_deref().unwrap_or("").trim()
+}
+
+/// 読解は本文を持ち、読解以外は本文を持たない
+struct PassageRequired;
+
+impl QualityRule for PassageRequired {
+    fn id(&self) -> &'static str {
+        "passage_required"
+    }
+    fn description(&self) -> &'static str {
+        "読解は本文を持ち、読解以外は本文を持たない"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        let has_passage = question
+            .passage
+            .as_deref()
+            .is_some_and(|p| !p.trim().is_empty());
+        match question.question_type {
+            QuestionType::Passage if !has_passage => vec![Issue::question("読解の本文なし")],
+            QuestionType::Passage => vec![],
+            _ if question.passage.is_some() => vec![Issue::question("読解以外に本文")],
+            _ => vec![],
+        }
+    }
+}
+
+/// 記法の誤りを `"{place}のマークアップ: {誤り}"` の形で返す
+fn markup_issues(place: &str, s: Option<&str>) -> Vec<String> {
+    let Some(s) = s else { return vec![] };
+    RichText::parse_with_errors(s)
+        .1
+        .into_iter()
+        .map(|e| format!("{}のマークアップ: {}", place, e))
+        .collect()
+}
+
+/// 問題文・本文・小問・選択肢の記法が正しい
+struct MarkupSyntax;
+
+impl QualityRule for MarkupSyntax {
+    fn id(&self) -> &'static str {
+        "markup_syntax"
+    }
+    fn description(&self) -> &'static str {
+        "文字装飾の記法が正しい"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        let mut issues: Vec<Issue> = markup_issues("問題文", Some(question.sentence.as_str()))
+            .into_iter()
+            .chain(markup_issues("本文", question.passage.as_deref()))
+            .map(Issue::question)
+            .collect();
+        issues.extend(per_sub_question(question, |sub_q| {
+            let mut messages = markup_issues("問題文", Some(sentence(sub_q)));
+            for sa in &sub_q.select_answer {
+                messages.extend(markup_issues(
+                    &format!("選択肢{}", sa.key),
+                    Some(sa.value.as_str()),
+                ));
+            }
+            messages
+        }));
+        issues
+    }
+}
+
+/// 穴埋めは空欄をちょうど1つ持つ
+struct BlankCount;
+
+impl QualityRule for BlankCount {
+    fn id(&self) -> &'static str {
+        "blank_count"
+    }
+    fn description(&self) -> &'static str {
+        "穴埋めの小問は空欄をちょうど1つ持つ"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        if question.question_type != QuestionType::Cloze {
+            return vec![];
+        }
+        per_sub_question(question, |sub_q| {
+            match RichText::parse(sentence(sub_q)).blank_count() {
+                1 => vec![],
+                0 => vec!["空欄なし".to_string()],
+                n => vec![format!("空欄{}個", n)],
+            }
+        })
+    }
+}
+
+/// 穴埋め以外の小問に空括弧が無い (既定では漢字読み・表記のみ)
+struct EmptyBlank;
+
+impl QualityRule for EmptyBlank {
+    fn id(&self) -> &'static str {
+        "empty_blank"
+    }
+    fn description(&self) -> &'static str {
+        "穴埋め以外の小問に空括弧が無い"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn default_categories(&self) -> &'static [&'static str] {
+        UNDERLINE_CATEGORY_IDS
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        if question.question_type == QuestionType::Cloze {
+            return vec![];
+        }
+        per_sub_question(question, |sub_q| {
+            if RichText::parse(sentence(sub_q)).blank_count() > 0 {
+                vec!["空括弧(読み/表記)".to_string()]
+            } else {
+                vec![]
+            }
+        })
+    }
+}
+
+/// 選択肢の数が `count` (既定 4) と一致する
+struct OptionCount;
+
+impl OptionCount {
+    fn count(params: &Value) -> usize {
+        params["count"].as_u64().unwrap_or(4) as usize
+    }
+}
+
+impl QualityRule for OptionCount {
+    fn id(&self) -> &'static str {
+        "option_count"
+    }
+    fn description(&self) -> &'static str {
+        "選択肢の数が count と一致する"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn default_params(&self) -> Value {
+        json!({ "count": 4 })
+    }
+    fn validate_params(&self, params: &Value) -> Result<(), String> {
+        match params["count"].as_u64() {
+            Some(n) if n >= 2 => Ok(()),
+            _ => Err("count は 2 以上の整数で指定してください".to_string()),
+        }
+    }
+    fn check(&self, question: &Question, params: &Value) -> Vec<Issue> {
+        let count = Self::count(params);
+        per_sub_question(question, |sub_q| {
+            if sub_q.select_answer.len() != count {
+                vec![format!("選択肢{}個", sub_q.select_answer.len())]
+            } else {
+                vec![]
+            }
+        })
+    }
+}
+
+/// 正解の key が選択肢にある
+struct AnswerKey;
+
+impl QualityRule for AnswerKey {
+    fn id(&self) -> &'static str {
+        "answer_key"
+    }
+    fn description(&self) -> &'static str {
+        "正解の key が選択肢にある"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            if sub_q.select_answer.iter().any(|sa| sa.key == sub_q.answer) {
+                vec![]
+            } else {
+                vec!["正解キー不在".to_string()]
+            }
+        })
+    }
+}
+
+/// 空の選択肢が無い
+struct EmptyOption;
+
+impl QualityRule for EmptyOption {
+    fn id(&self) -> &'static str {
+        "empty_option"
+    }
+    fn description(&self) -> &'static str {
+        "空の選択肢が無い"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            let empty = sub_q
+                .select_answer
+                .iter()
+                .filter(|sa| sa.value.trim().is_empty())
+                .count();
+            if empty > 0 {
+                vec![format!("空選択肢{}個", empty)]
+            } else {
+                vec![]
+            }
+        })
+    }
+}
+
+/// 小問の問題文が空でない
+struct EmptySentence;
+
+impl QualityRule for EmptySentence {
+    fn id(&self) -> &'static str {
+        "empty_sentence"
+    }
+    fn description(&self) -> &'static str {
+        "小問の問題文が空でない"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            if sentence(sub_q).is_empty() {
+                vec!["空問題文".to_string()]
+            } else {
+                vec![]
+            }
+        })
+    }
+}
+
+/// 選択肢の除外は自動で作るヒントなので登録できない
+struct EliminateHint;
+
+impl QualityRule for EliminateHint {
+    fn id(&self) -> &'static str {
+        "eliminate_hint"
+    }
+    fn description(&self) -> &'static str {
+        "eliminate のヒントを登録していない"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            if sub_q.hints.iter().any(|h| h.kind == HintKind::Eliminate) {
+                vec!["eliminate のヒント".to_string()]
+            } else {
+                vec![]
+            }
+        })
+    }
+}
+
+/// 文の組み立ては断片と ★ の欄を持ち、それ以外は断片を持たない
+struct Ordering;
+
+impl QualityRule for Ordering {
+    fn id(&self) -> &'static str {
+        "ordering"
+    }
+    fn description(&self) -> &'static str {
+        "文の組み立ての断片・★の位置・欄が揃っている"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Delete
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            match (question.question_type, &sub_q.ordering) {
+                (QuestionType::Ordering, None) => vec!["並び替えの断片なし".to_string()],
+                (QuestionType::Ordering, Some(ordering)) => {
+                    let mut messages =
+                        ordering_issues(sub_q, &ordering.fragments, ordering.star_position);
+                    messages.extend(star_slot_issues(
+                        &RichText::parse(sentence(sub_q)),
+                        ordering.fragments.len(),
+                        ordering.star_position,
+                    ));
+                    messages
+                }
+                (_, Some(_)) => vec!["並び替え以外に断片".to_string()],
+                (_, None) => vec![],
+            }
+        })
+    }
+}
+
+/// 文の組み立て: 断片が選択肢と1対1に対応し、★ の位置の断片が正解であること
+fn ordering_issues(sub_q: &SubQuestion, fragments: &[String], star_position: usize) -> Vec<String> {
+    let mut issues = Vec::new();
+    let mut values: Vec<&str> = sub_q
+        .select_answer
+        .iter()
+        .map(|sa| sa.value.trim())
+        .collect();
+    let mut expected: Vec<&str> = fragments.iter().map(|f| f.trim()).collect();
+    values.sort_unstable();
+    expected.sort_unstable();
+    if values != expected {
+        issues.push("断片と選択肢が不一致".to_string());
+    }
+    match fragments.get(star_position) {
+        None => issues.push(format!("★の位置{}が範囲外", star_position)),
+        Some(star) => {
+            let answer_value = sub_q
+                .select_answer
+                .iter()
+                .find(|sa| sa.key == sub_q.answer)
+                .map(|sa| sa.value.trim());
+            if answer_value.is_some_and(|v| v != star.trim()) {
+                issues.push("正解が★の断片と不一致".to_string());
+            }
+        }
+    }
+    issues
+}
+
+/// 文の組み立ての問題文: 欄が断片と同じ数あり、★ の欄が `star_position` にあること
+fn star_slot_issues(sentence: &RichText, fragments: usize, star_position: usize) -> Vec<String> {
+    let (stars, slots) = sentence.star_slots();
+    let mut issues = Vec::new();
+    match stars.as_slice() {
+        [] => issues.push("★の欄なし".to_string()),
+        [star] if *star != star_position => {
+            issues.push(format!("★の欄が{}番目", star + 1));
+        }
+        [_] => {}
+        _ => issues.push(format!("★の欄{}個", stars.len())),
+    }
+    if slots != fragments {
+        issues.push(format!("欄{}個と断片{}個", slots, fragments));
+    }
+    issues
+}
+
+/// 1つの小問の中で同じ値の選択肢が無い (装飾と前後の空白は無視する)
+struct DuplicateOption;
+
+impl QualityRule for DuplicateOption {
+    fn id(&self) -> &'static str {
+        "duplicate_option"
+    }
+    fn description(&self) -> &'static str {
+        "1つの小問の中で同じ値の選択肢が無い"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Quarantine
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            let mut seen = HashSet::new();
+            let mut reported = HashSet::new();
+            let mut messages = Vec::new();
+            for sa in &sub_q.select_answer {
+                let value = RichText::parse(sa.value.trim()).to_plain();
+                if !value.is_empty()
+                    && !seen.insert(value.clone())
+                    && reported.insert(value.clone())
+                {
+                    messages.push(format!("選択肢の重複: {}", value));
+                }
+            }
+            messages
+        })
+    }
+}
+
+/// 下線部がちょうど1つある (既定では漢字読み・表記のみ)。
+/// 既存の問題には下線部の記法が無いものが多いため、既定では警告に留める
+struct MissingUnderline;
+
+impl QualityRule for MissingUnderline {
+    fn id(&self) -> &'static str {
+        "missing_underline"
+    }
+    fn description(&self) -> &'static str {
+        "小問の問題文に下線部がちょうど1つある"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Warn
+    }
+    fn default_categories(&self) -> &'static [&'static str] {
+        UNDERLINE_CATEGORY_IDS
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        per_sub_question(question, |sub_q| {
+            match RichText::parse(sentence(sub_q)).underline_count() {
+                1 => vec![],
+                0 => vec!["下線部なし".to_string()],
+                n => vec![format!("下線部{}個", n)],
+            }
+        })
+    }
+}
+
+/// 問題のレベルより難しい漢字・語を使っていない
+struct LevelVocabulary;
+
+impl QualityRule for LevelVocabulary {
+    fn id(&self) -> &'static str {
+        "level_vocabulary"
+    }
+    fn description(&self) -> &'static str {
+        "問題のレベルより難しい漢字・語を使っていない"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Warn
+    }
+    fn check(&self, question: &Question, _params: &Value) -> Vec<Issue> {
+        let violations = level_violations(question);
+        if violations.is_empty() {
+            return vec![];
+        }
+        let mut parts = Vec::new();
+        if !violations.kanji.is_empty() {
+            parts.push(format!(
+                "漢字 {}",
+                violations.kanji.iter().collect::<String>()
+            ));
+        }
+        if !violations.words.is_empty() {
+            parts.push(format!("語 {}", violations.words.join("・")));
+        }
+        let mut issue = Issue::question(format!("レベル外の{}", parts.join(" / ")));
+        issue.detail = Some(json!(violations));
+        vec![issue]
+    }
+}
+
+/// 問題のレベルより難しい漢字・語 (出現順、重複なし)
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LevelViolations {
+    pub kanji: Vec<char>,
+    /// 辞書形
+    pub words: Vec<String>,
+}
+
+impl LevelViolations {
+    pub fn is_empty(&self) -> bool {
+        self.kanji.is_empty() && self.words.is_empty()
+    }
+}
+
+/// 学習者に見せる文 (問題文・本文・前提・小問・選択肢) の中で、
+/// `level_id` より難しい漢字 (一覧に無い漢字は N1) と語彙一覧で難しい語を集める。
+/// ルビの付いた語は読みが示されているため数えない
+pub fn level_violations(question: &Question) -> LevelViolations {
+    let texts = [
+        Some(question.sentence.as_str()),
+        question.passage.as_deref(),
+        question.prerequisites.as_deref(),
+    ]
+    .into_iter()
+    .chain(question.sub_questions.iter().flat_map(|sub_q| {
+        [sub_q.prerequisites.as_deref(), sub_q.sentence.as_deref()]
+            .into_iter()
+            .chain(sub_q.select_answer.iter().map(|sa| Some(sa.value.as_str())))
+    }))
+    .flatten();
+
+    let mut violations = LevelViolations::default();
+    for text in texts {
+        for segment in RichText::parse(text).0 {
+            let (Segment::Text { text } | Segment::Underline { text }) = segment else {
+                continue;
+            };
+            for c in text.chars() {
+                if is_above_level(c, question.level_id) && !violations.kanji.contains(&c) {
+                    violations.kanji.push(c);
+                }
+            }
+            for word in vocabulary_words(&text) {
+                if word_level(word).is_some_and(|level| level < question.level_id)
+                    && !violations.words.iter().any(|w| w == word)
+                {
+                    violations.words.push(word.to_string());
+                }
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::quality::RuleSet,
+        models::question::{SelectAnswer, SentenceOrdering},
+    };
+
+    fn sub(sentence: &str, values: &[&str], answer: &str) -> SubQuestion {
+        SubQuestion {
+            id: 1,
+            sentence: Some(sentence.to_string()),
+            select_answer: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| SelectAnswer {
+                    key: (i + 1).to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            answer: answer.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn question(question_type: QuestionType, sub_q: SubQuestion) -> Question {
+        Question {
+            question_type,
+            sub_questions: vec![sub_q],
+            ..Default::default()
+        }
+    }
+
+    /// 1つの規則を既定の引数で適用したメッセージ
+    fn check(rule: &dyn QualityRule, question: &Question) -> Vec<String> {
+        rule.check(question, &rule.default_params())
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    /// 既定の設定で削除対象となる規則のメッセージ (規則の登録順)
+    fn check_all(question: &Question) -> Vec<String> {
+        RuleSet::builtin()
+            .check(question, Severity::Delete)
+            .into_iter()
+            .map(|f| f.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_sub_question_has_no_issues() {
+        let sq = sub(
+            "雨が（　　）。",
+            &["ふる", "ふった", "ふらない", "ふれ"],
+            "2",
+        );
+        let q = question(QuestionType::MultipleChoice, sq);
+        assert!(RuleSet::builtin().check(&q, Severity::Warn).is_empty());
+    }
+
+    #[test]
+    fn test_detects_each_rule() {
+        let sq = sub("（　　）を読む", &["ほん", "", "よむ"], "4");
+        let mut q = question(QuestionType::MultipleChoice, sq);
+        q.category_id = Some("2".to_string());
+        assert_eq!(
+            check_all(&q),
+            vec![
+                "空括弧(読み/表記)",
+                "選択肢3個",
+                "正解キー不在",
+                "空選択肢1個"
+            ]
+        );
+        assert_eq!(
+            OptionCount.check(&q, &json!({"count": 3})),
+            Vec::<Issue>::new()
+        );
+
+        let sq = sub(" ", &["a", "b", "c", "d"], "1");
+        let q = question(QuestionType::MultipleChoice, sq);
+        assert_eq!(check_all(&q), vec!["空問題文"]);
+    }
+
+    #[test]
+    fn test_cloze_requires_one_blank() {
+        let q = question(
+            QuestionType::Cloze,
+            sub("雨が降る。", &["a", "b", "c", "d"], "1"),
+        );
+        assert_eq!(check(&BlankCount, &q), vec!["空欄なし"]);
+        // 穴埋めでは漢字読みのカテゴリでも空欄を空括弧として扱わない
+        let mut q = question(
+            QuestionType::Cloze,
+            sub("（　　）が降る。", &["a", "b", "c", "d"], "1"),
+        );
+        q.category_id = Some("2".to_string());
+        assert!(check_all(&q).is_empty());
+        let q = question(
+            QuestionType::Cloze,
+            sub("（　　）が（）。", &["a", "b", "c", "d"], "1"),
+        );
+        assert_eq!(check(&BlankCount, &q), vec!["空欄2個"]);
+    }
+
+    #[test]
+    fn test_ordering_checks_fragments_and_star() {
+        let mut sq = sub(
+            "私は ＿＿ ＿＿ ★ ＿＿ 。",
+            &["読んだ", "本を", "昨日", "ことがある"],
+            "1",
+        );
+        assert_eq!(
+            check(&Ordering, &question(QuestionType::Ordering, sq.clone())),
+            vec!["並び替えの断片なし"]
+        );
+
+        sq.ordering = Some(SentenceOrdering {
+            fragments: vec![
+                "昨日".to_string(),
+                "本を".to_string(),
+                "読んだ".to_string(),
+                "ことがある".to_string(),
+            ],
+            star_position: 2,
+        });
+        assert!(check_all(&question(QuestionType::Ordering, sq.clone())).is_empty());
+        assert_eq!(
+            check(
+                &Ordering,
+                &question(QuestionType::MultipleChoice, sq.clone())
+            ),
+            vec!["並び替え以外に断片"]
+        );
+
+        sq.answer = "2".to_string();
+        sq.ordering.as_mut().unwrap().fragments[3] = "ことも".to_string();
+        assert_eq!(
+            check(&Ordering, &question(QuestionType::Ordering, sq)),
+            vec!["断片と選択肢が不一致", "正解が★の断片と不一致"]
+        );
+    }
+
+    #[test]
+    fn test_ordering_checks_star_slot() {
+        let mut sq = sub(
+            "私は ＿＿ ★ ＿＿ ＿＿ 。",
+            &["読んだ", "本を", "昨日", "ことがある"],
+            "1",
+        );
+        sq.ordering = Some(SentenceOrdering {
+            fragments: vec![
+                "昨日".to_string(),
+                "本を".to_string(),
+                "読んだ".to_string(),
+                "ことがある".to_string(),
+            ],
+            star_position: 2,
+        });
+        assert_eq!(
+            check(&Ordering, &question(QuestionType::Ordering, sq.clone())),
+            vec!["★の欄が2番目"]
+        );
+        sq.sentence = Some("私は ＿＿ ＿＿ ＿＿ 。".to_string());
+        assert_eq!(
+            check(&Ordering, &question(QuestionType::Ordering, sq)),
+            vec!["★の欄なし", "欄3個と断片4個"]
+        );
+    }
+
+    #[test]
+    fn test_markup_errors_and_underline() {
+        let sq = sub("<u>雨が降る。", &["あめ", "ゆき", "｜空《", "かぜ"], "1");
+        let q = question(QuestionType::MultipleChoice, sq);
+        assert_eq!(
+            check(&MarkupSyntax, &q),
+            vec![
+                "問題文のマークアップ: 下線が閉じられていません",
+                "選択肢3のマークアップ: ルビが閉じられていません"
+            ]
+        );
+        assert_eq!(check(&MissingUnderline, &q), vec!["下線部なし"]);
+
+        let sq = sub("<u>雨</u>が降る。", &["あめ", "ゆき", "そら", "かぜ"], "1");
+        assert!(
+            check(
+                &MissingUnderline,
+                &question(QuestionType::MultipleChoice, sq)
+            )
+            .is_empty()
+        );
+
+        let q = Question {
+            sentence: "次の文の</u>".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&MarkupSyntax, &q),
+            vec!["問題文のマークアップ: 対応する <u> の無い </u>"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_option() {
+        let sq = sub(
+            "雨が（　　）。",
+            &["ふる", " ふる", "<u>ふる</u>", "ふった"],
+            "1",
+        );
+        let q = question(QuestionType::MultipleChoice, sq);
+        assert_eq!(check(&DuplicateOption, &q), vec!["選択肢の重複: ふる"]);
+        // 空の選択肢は empty_option で報告する
+        let sq = sub("雨が（　　）。", &["", "", "ふる", "ふった"], "3");
+        let q = question(QuestionType::MultipleChoice, sq);
+        assert!(check(&DuplicateOption, &q).is_empty());
+    }
+
+    #[test]
+    fn test_level_violations() {
+        let mut question = Question {
+            level_id: 5,
+            sentence: "正しいものを選びなさい。".to_string(),
+            sub_questions: vec![sub(
+                "雨で試合が中止になった。",
+                &["ので", "のに", "けど", "｜鬱《うつ》"],
+                "1",
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            level_violations(&question),
+            LevelViolations {
+                kanji: vec!['正', '選', '試', '合', '止'],
+                words: vec![
+                    "正しい".to_string(),
+                    "選ぶ".to_string(),
+                    "試合".to_string(),
+                    "中止".to_string()
+                ],
+            }
+        );
+        assert_eq!(
+            check(&LevelVocabulary, &question),
+            vec!["レベル外の漢字 正選試合止 / 語 正しい・選ぶ・試合・中止"]
+        );
+        question.level_id = 3;
+        assert!(level_violations(&question).is_empty());
+        assert!(check(&LevelVocabulary, &question).is_empty());
+    }
+
+    #[test]
+    fn test_level_violations_finds_advanced_words() {
+        // N2・N1 の語。漢字がすべて N5 でも語として見つける (一見)
+        let question = Question {
+            level_id: 5,
+            sentence: "一見、大きい山だ。".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            level_violations(&question),
+            LevelViolations {
+                kanji: vec![],
+                words: vec!["一見".to_string()],
+            }
+        );
+
+        let question = Question {
+            level_id: 4,
+            sentence: "説明が曖昧で、状況を把握できなかった。".to_string(),
+            sub_questions: vec![sub(
+                "事故の原因を見直して、対策を促した。",
+                &["ので", "のに", "けど", "から"],
+                "1",
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            level_violations(&question).words,
+            vec!["曖昧", "状況", "把握", "見直す", "促す"]
+        );
+    }
+
+    #[test]
+    fn test_passage_requires_text() {
+        let mut q = Question {
+            question_type: QuestionType::Passage,
+            ..Default::default()
+        };
+        assert_eq!(check(&PassageRequired, &q), vec!["読解の本文なし"]);
+        q.passage = Some("本文".to_string());
+        assert!(check(&PassageRequired, &q).is_empty());
+        q.question_type = QuestionType::Cloze;
+        assert_eq!(check(&PassageRequired, &q), vec!["読解以外に本文"]);
+    }
+}
@@ -0,0 +1,407 @@
+//! 問題文・選択肢の文字装飾 (下線部・空欄・★ の欄・ルビ)。
+//!
+//! 保存する文字列はこれまでどおり `String` で、次の記法を使う。
+//!
+//! | 記法 | 意味 |
+//! |------|------|
+//! | `<u>語</u>` | 下線部 |
+//! | `（　　）` | 空欄 |
+//! | `＿★＿` | 文の組み立ての ★ の欄 |
+//! | `｜漢字《かんじ》` | ルビ (直前が漢字だけなら `漢字《かんじ》` でもよい) |
+//!
+//! 旧来の表記 (`（）` `（ ）` などの空欄、`＿＿` の欄、単独の `★`) も読み取り、
+//! [`RichText::to_markup`] で上の正規の記法に揃える。
+
+use serde::Serialize;
+
+use super::export::escape_html;
+
+/// 文を構成する要素
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Segment {
+    Text {
+        text: String,
+    },
+    /// 下線部 (読み方・表記を問う対象の語)
+    Underline {
+        text: String,
+    },
+    /// 空欄
+    Blank,
+    /// 文の組み立ての ★ の欄
+    Star,
+    Ruby {
+        base: String,
+        reading: String,
+    },
+}
+
+/// 読み取った文
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RichText(pub Vec<Segment>);
+
+/// 配信時の文字列の形式 (`?render=`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    /// 保存している記法のまま
+    #[default]
+    Markup,
+    Html,
+    Plain,
+}
+
+const UNDERLINE_OPEN: &str = "<u>";
+const UNDERLINE_CLOSE: &str = "</u>";
+const BLANK: &str = "（　　）";
+const STAR: &str = "＿★＿";
+
+impl RichText {
+    /// 文字列を読み取る。記法の誤りは文字列のまま残す
+    pub fn parse(s: &str) -> Self {
+        Self::parse_with_errors(s).0
+    }
+
+    /// 文字列を読み取り、記法の誤り (閉じていない下線・親文字の無いルビ等) を併せて返す
+    pub fn parse_with_errors(s: &str) -> (Self, Vec<String>) {
+        let mut parser = Parser::default();
+        let chars: Vec<char> = s.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            i = parser.step(&chars, i);
+        }
+        parser.flush();
+        (RichText(parser.segments), parser.errors)
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    /// 正規の記法に戻す
+    pub fn to_markup(&self) -> String {
+        self.render(|segment, out| match segment {
+            Segment::Text { text } => out.push_str(text),
+            Segment::Underline { text } => {
+                out.push_str(UNDERLINE_OPEN);
+                out.push_str(text);
+                out.push_str(UNDERLINE_CLOSE);
+            }
+            Segment::Blank => out.push_str(BLANK),
+            Segment::Star => out.push_str(STAR),
+            Segment::Ruby { base, reading } => {
+                out.push_str(&format!("｜{}《{}》", base, reading));
+            }
+        })
+    }
+
+    /// HTML。文字列はエスケープし、改行は `<br>` にする
+    pub fn to_html(&self) -> String {
+        self.render(|segment, out| match segment {
+            Segment::Text { text } => out.push_str(&escape_html(text).replace('\n', "<br>")),
+            Segment::Underline { text } => out.push_str(&format!("<u>{}</u>", escape_html(text))),
+            Segment::Blank => out.push_str(&format!("<span class=\"blank\">{}</span>", BLANK)),
+            Segment::Star => out.push_str(&format!("<span class=\"star\">{}</span>", STAR)),
+            Segment::Ruby { base, reading } => out.push_str(&format!(
+                "<ruby>{}<rt>{}</rt></ruby>",
+                escape_html(base),
+                escape_html(reading)
+            )),
+        })
+    }
+
+    /// 装飾を除いた文字列。空欄と ★ の欄は正規の記法で残し、ルビは親文字だけにする
+    pub fn to_plain(&self) -> String {
+        self.render(|segment, out| match segment {
+            Segment::Text { text } | Segment::Underline { text } => out.push_str(text),
+            Segment::Blank => out.push_str(BLANK),
+            Segment::Star => out.push_str(STAR),
+            Segment::Ruby { base, .. } => out.push_str(base),
+        })
+    }
+
+    fn render(&self, mut f: impl FnMut(&Segment, &mut String)) -> String {
+        let mut out = String::new();
+        for segment in &self.0 {
+            f(segment, &mut out);
+        }
+        out
+    }
+
+    pub fn blank_count(&self) -> usize {
+        self.count(|s| matches!(s, Segment::Blank))
+    }
+
+    pub fn underline_count(&self) -> usize {
+        self.count(|s| matches!(s, Segment::Underline { .. }))
+    }
+
+    /// 文の組み立ての欄 (空欄と ★ の欄) の中で ★ が何番目か (0 始まり) と欄の数
+    pub fn star_slots(&self) -> (Vec<usize>, usize) {
+        let slots: Vec<&Segment> = self
+            .0
+            .iter()
+            .filter(|s| matches!(s, Segment::Blank | Segment::Star))
+            .collect();
+        let stars = slots
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| matches!(s, Segment::Star))
+            .map(|(i, _)| i)
+            .collect();
+        (stars, slots.len())
+    }
+
+    fn count(&self, f: impl Fn(&Segment) -> bool) -> usize {
+        self.0.iter().filter(|s| f(s)).count()
+    }
+}
+
+/// 文字列を `mode` の形式にする
+pub fn render(s: &str, mode: RenderMode) -> String {
+    match mode {
+        RenderMode::Markup => s.to_string(),
+        RenderMode::Html => RichText::parse(s).to_html(),
+        RenderMode::Plain => RichText::parse(s).to_plain(),
+    }
+}
+
+/// 正規の記法に揃えた文字列
+pub fn canonicalize(s: &str) -> String {
+    RichText::parse(s).to_markup()
+}
+
+pub(crate) fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々' | '〆' | 'ヶ')
+}
+
+fn is_blank_space(c: char) -> bool {
+    c == ' ' || c == '　'
+}
+
+#[derive(Default)]
+struct Parser {
+    segments: Vec<Segment>,
+    text: String,
+    errors: Vec<String>,
+    /// 閉じていないルビは以降の `《` もすべて閉じないので、1度だけ報告する
+    unclosed_ruby: bool,
+}
+
+impl Parser {
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            self.segments.push(Segment::Text {
+                text: std::mem::take(&mut self.text),
+            });
+        }
+    }
+
+    fn push(&mut self, segment: Segment) {
+        self.flush();
+        self.segments.push(segment);
+    }
+
+    /// `chars[i]` から1要素を読み、次の位置を返す
+    fn step(&mut self, chars: &[char], i: usize) -> usize {
+        let rest = || chars[i..].iter().collect::<String>();
+        match chars[i] {
+            '<' if rest().starts_with(UNDERLINE_OPEN) => {
+                let start = i + UNDERLINE_OPEN.chars().count();
+                let inner: String = chars[start..].iter().collect();
+                match inner.find(UNDERLINE_CLOSE) {
+                    Some(end) => {
+                        let text = inner[..end].to_string();
+                        if text.trim().is_empty() {
+                            self.errors.push("空の下線".to_string());
+                        }
+                        let len = text.chars().count();
+                        self.push(Segment::Underline { text });
+                        start + len + UNDERLINE_CLOSE.chars().count()
+                    }
+                    None => {
+                        self.errors.push("下線が閉じられていません".to_string());
+                        self.text.push_str(UNDERLINE_OPEN);
+                        start
+                    }
+                }
+            }
+            '<' if rest().starts_with(UNDERLINE_CLOSE) => {
+                self.errors.push("対応する <u> の無い </u>".to_string());
+                self.text.push_str(UNDERLINE_CLOSE);
+                i + UNDERLINE_CLOSE.chars().count()
+            }
+            '（' => {
+                let spaces = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| is_blank_space(**c))
+                    .count();
+                if chars.get(i + 1 + spaces) == Some(&'）') {
+                    self.push(Segment::Blank);
+                    i + spaces + 2
+                } else {
+                    self.text.push('（');
+                    i + 1
+                }
+            }
+            '＿' | '★' => {
+                let leading = chars[i..].iter().take_while(|c| **c == '＿').count();
+                if chars.get(i + leading) == Some(&'★') {
+                    let trailing = chars[i + leading + 1..]
+                        .iter()
+                        .take_while(|c| **c == '＿')
+                        .count();
+                    self.push(Segment::Star);
+                    i + leading + 1 + trailing
+                } else if leading >= 2 {
+                    self.push(Segment::Blank);
+                    i + leading
+                } else {
+                    self.text.push('＿');
+                    i + 1
+                }
+            }
+            '｜' | '《' => self.ruby(chars, i),
+            c => {
+                self.text.push(c);
+                i + 1
+            }
+        }
+    }
+
+    /// `｜親文字《読み》` または `漢字《読み》`
+    fn ruby(&mut self, chars: &[char], i: usize) -> usize {
+        let explicit = chars[i] == '｜';
+        let open = if explicit {
+            chars[i..].iter().position(|c| *c == '《').map(|p| i + p)
+        } else {
+            Some(i)
+        };
+        let close = open.and_then(|open| {
+            chars[open..]
+                .iter()
+                .position(|c| *c == '》')
+                .map(|p| open + p)
+        });
+        let (Some(open), Some(close)) = (open, close) else {
+            if !std::mem::replace(&mut self.unclosed_ruby, true) {
+                self.errors.push("ルビが閉じられていません".to_string());
+            }
+            self.text.push(chars[i]);
+            return i + 1;
+        };
+
+        let reading: String = chars[open + 1..close].iter().collect();
+        let base: String = if explicit {
+            chars[i + 1..open].iter().collect()
+        } else {
+            // 直前の漢字の並びを親文字にする
+            let kanji: Vec<char> = self
+                .text
+                .chars()
+                .rev()
+                .take_while(|c| is_kanji(*c))
+                .collect();
+            let base: String = kanji.into_iter().rev().collect();
+            self.text.truncate(self.text.len() - base.len());
+            base
+        };
+        if base.is_empty() {
+            self.errors.push("ルビの親文字がありません".to_string());
+        }
+        if reading.trim().is_empty() {
+            self.errors.push("空のルビ".to_string());
+        }
+        self.push(Segment::Ruby { base, reading });
+        close + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Segment {
+        Segment::Text {
+            text: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_all_segments() {
+        let parsed = RichText::parse("<u>雨</u>が（　　）｜今日《きょう》は＿★＿");
+        assert_eq!(
+            parsed.segments(),
+            &[
+                Segment::Underline {
+                    text: "雨".to_string()
+                },
+                text("が"),
+                Segment::Blank,
+                Segment::Ruby {
+                    base: "今日".to_string(),
+                    reading: "きょう".to_string()
+                },
+                text("は"),
+                Segment::Star,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrates_legacy_spellings() {
+        assert_eq!(canonicalize("雨が（）。"), "雨が（　　）。");
+        assert_eq!(canonicalize("雨が（ ）。"), "雨が（　　）。");
+        assert_eq!(canonicalize("雨が（  ）。"), "雨が（　　）。");
+        assert_eq!(
+            canonicalize("私は ＿＿ ＿＿ ★ ＿＿ 。"),
+            "私は （　　） （　　） ＿★＿ （　　） 。"
+        );
+        // ルビの親文字は直前の漢字の並び
+        assert_eq!(
+            canonicalize("毎日の学校《がっこう》"),
+            "毎日の｜学校《がっこう》"
+        );
+        assert_eq!(canonicalize("あ＿い（注）"), "あ＿い（注）");
+        // 正規の記法は変わらない
+        let markup = "<u>雨</u>が（　　）｜今日《きょう》＿★＿";
+        assert_eq!(canonicalize(markup), markup);
+    }
+
+    #[test]
+    fn test_star_slots() {
+        let parsed = RichText::parse("私は ＿＿ ＿＿ ★ ＿＿ 。");
+        assert_eq!(parsed.star_slots(), (vec![2], 4));
+        assert_eq!(parsed.blank_count(), 3);
+    }
+
+    #[test]
+    fn test_renders_html_and_plain() {
+        let parsed = RichText::parse("<u>A&B</u>｜漢字《かんじ》（　　）\n次");
+        assert_eq!(
+            parsed.to_html(),
+            "<u>A&amp;B</u><ruby>漢字<rt>かんじ</rt></ruby><span class=\"blank\">（　　）</span><br>次"
+        );
+        assert_eq!(parsed.to_plain(), "A&B漢字（　　）\n次");
+    }
+
+    #[test]
+    fn test_reports_markup_errors() {
+        let (parsed, errors) = RichText::parse_with_errors("<u>雨が降る");
+        assert_eq!(errors, vec!["下線が閉じられていません"]);
+        assert_eq!(parsed.to_plain(), "<u>雨が降る");
+
+        let (_, errors) = RichText::parse_with_errors("ひらがな《よみ》と</u>と<u> </u>");
+        assert_eq!(
+            errors,
+            vec![
+                "ルビの親文字がありません",
+                "対応する <u> の無い </u>",
+                "空の下線"
+            ]
+        );
+        let (_, errors) = RichText::parse_with_errors("漢字《");
+        assert_eq!(errors, vec!["ルビが閉じられていません"]);
+    }
+}
@@ -0,0 +1,166 @@
+//! 配信ごとの選択肢シャッフル。
+//!
+//! 表示順の選択肢キーは `"1"`〜 に振り直し、表示キー → 正規キーの対応を
+//! 署名付きトークン (JWT_SECRET で HS256 署名) としてクライアントに渡す。
+//! 回答時にトークンを受け取り、`record_answer` で正規キーに戻してから採点する。
+
+use std::collections::BTreeMap;
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use super::error::{AppError, AppResult};
+use crate::models::{claim::JWT_SECRET, question::PublicQuestion};
+
+/// トークンの有効期限 (秒)
+const TOKEN_TTL_SECS: i64 = 60 * 60 * 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShuffleToken {
+    /// 問題ID
+    pub qid: String,
+    /// sub_question_id -> 表示順に並べた正規キー
+    pub perms: BTreeMap<u32, Vec<String>>,
+    pub exp: i64,
+}
+
+impl ShuffleToken {
+    pub fn sign(&self) -> AppResult<String> {
+        encode(
+            &Header::default(),
+            self,
+            &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+        )
+        .map_err(|e| AppError::Internal(format!("shuffle token creation error: {}", e)))
+    }
+
+    pub fn verify(token: &str) -> AppResult<Self> {
+        decode::<Self>(
+            token,
+            &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+        .map_err(|_| AppError::Validation("shuffle_token が不正です".to_string()))
+    }
+
+    /// 表示キーを正規キーに戻す。トークンに無い小問はそのまま返す
+    pub fn to_canonical(&self, sub_question_id: u32, displayed: &str) -> AppResult<String> {
+        let Some(perm) = self.perms.get(&sub_question_id) else {
+            return Ok(displayed.to_string());
+        };
+        displayed
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| perm.get(i))
+            .cloned()
+            .ok_or_else(|| AppError::Validation("selected_answer が選択肢にありません".to_string()))
+    }
+
+    /// 正規キーを表示キーに変換する
+    pub fn to_displayed(&self, sub_question_id: u32, canonical: &str) -> Option<String> {
+        self.perms
+            .get(&sub_question_id)?
+            .iter()
+            .position(|key| key == canonical)
+            .map(|i| (i + 1).to_string())
+    }
+}
+
+/// 小問ごとに選択肢を並べ替えてキーを振り直し、対応を記録したトークンを返す
+pub fn shuffle_question<R: Rng + ?Sized>(
+    question: &mut PublicQuestion,
+    rng: &mut R,
+) -> ShuffleToken {
+    let mut perms = BTreeMap::new();
+    for sub in &mut question.sub_questions {
+        sub.select_answer.shuffle(rng);
+        let canonical: Vec<String> = sub
+            .select_answer
+            .iter_mut()
+            .enumerate()
+            .map(|(i, option)| std::mem::replace(&mut option.key, (i + 1).to_string()))
+            .collect();
+        perms.insert(sub.id, canonical);
+    }
+
+    ShuffleToken {
+        qid: question.id.clone(),
+        perms,
+        exp: chrono::Utc::now().timestamp() + TOKEN_TTL_SECS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::models::question::{PublicSubQuestion, SelectAnswer};
+
+    fn question() -> PublicQuestion {
+        PublicQuestion {
+            id: "q1".to_string(),
+            level_id: 3,
+            level_name: "N3".to_string(),
+            category_id: None,
+            category_name: "文法".to_string(),
+            sentence: String::new(),
+            prerequisites: None,
+            sub_questions: vec![PublicSubQuestion {
+                id: 1,
+                sentence: None,
+                prerequisites: None,
+                select_answer: ["ので", "のに", "けど", "から"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| SelectAnswer {
+                        key: (i + 1).to_string(),
+                        value: v.to_string(),
+                    })
+                    .collect(),
+                star_position: None,
+            }],
+            question_type: Default::default(),
+            passage: None,
+            shuffle_token: None,
+            furigana_unresolved: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_displayed_keys_map_back_to_canonical() {
+        let mut q = question();
+        let token = shuffle_question(&mut q, &mut StdRng::seed_from_u64(7));
+        let options = &q.sub_questions[0].select_answer;
+
+        let keys: Vec<&str> = options.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["1", "2", "3", "4"]);
+
+        // 表示上「ので」を選んだら正規キー "1" に戻る
+        let displayed = options.iter().find(|o| o.value == "ので").unwrap();
+        assert_eq!(token.to_canonical(1, &displayed.key).unwrap(), "1");
+        assert_eq!(
+            token.to_displayed(1, "1").as_deref(),
+            Some(displayed.key.as_str())
+        );
+        assert!(token.to_canonical(1, "5").is_err());
+    }
+
+    #[test]
+    fn test_token_roundtrip_and_tamper() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        // SAFETY: このモジュールのテストでのみ一度だけ設定する
+        INIT.call_once(|| unsafe { std::env::set_var("JWT_SECRET", "test-secret") });
+        let mut q = question();
+        let token = shuffle_question(&mut q, &mut StdRng::seed_from_u64(1));
+        let signed = token.sign().unwrap();
+        assert_eq!(ShuffleToken::verify(&signed).unwrap(), token);
+
+        let mut tampered = signed.clone();
+        tampered.pop();
+        assert!(ShuffleToken::verify(&tampered).is_err());
+    }
+}
@@ -1,3 +1,13 @@
+//! 文の類似度。
+//!
+//! 類似度そのものは Levenshtein 距離で測る ([`normalized_similarity`])。
+//! 問題集全体から似た文の組を探すときは、文字 n-gram の MinHash を LSH のバケットに分けて
+//! 候補の組を絞り込み ([`NearDuplicateIndex`])、候補だけを Levenshtein で確かめる。
+
+use std::collections::{HashMap, HashSet};
+
+use super::dedup::normalize_text;
+
 /// 類似度の閾値（0.0〜1.0）
 pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.85;
 
@@ -17,20 +27,14 @@ pub fn normalized_similarity(a: &str, b: &str) -> f64 {
 /// Levenshtein距離をDP法で計算（省メモリ版）
 fn levenshtein_distance(a: &[char], b: &[char]) -> usize {
     let (m, n) = (a.len(), b.len());
-    let mut prev = vec![0usize; n + 1];
+    let mut prev: Vec<usize> = (0..=n).collect();
     let mut curr = vec![0usize; n + 1];
 
-    for j in 0..=n {
-        prev[j] = j;
-    }
-
     for i in 1..=m {
         curr[0] = i;
         for j in 1..=n {
             let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
-            curr[j] = (prev[j] + 1)
-                .min(curr[j - 1] + 1)
-                .min(prev[j - 1] + cost);
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
         }
         std::mem::swap(&mut prev, &mut curr);
     }
@@ -38,6 +42,147 @@ fn levenshtein_distance(a: &[char], b: &[char]) -> usize {
     prev[n]
 }
 
+/// shingle (文字 n-gram) の長さ
+const SHINGLE_SIZE: usize = 2;
+/// LSH のバンド数。バンド数 × 行数が MinHash の長さになる
+const LSH_BANDS: usize = 32;
+/// 1バンドの行数。Jaccard 係数が (1/32)^(1/4) ≒ 0.42 を超える組から候補になりやすい
+const LSH_ROWS: usize = 4;
+const SIGNATURE_LEN: usize = LSH_BANDS * LSH_ROWS;
+
+/// NFKC 正規化し空白を除いた文の文字 n-gram。n 文字に満たない文は文全体を1つとする
+fn shingles(text: &str) -> HashSet<u64> {
+    let chars: Vec<char> = normalize_text(text)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if chars.len() < SHINGLE_SIZE {
+        return HashSet::from([fnv1a(&chars)]);
+    }
+    chars.windows(SHINGLE_SIZE).map(fnv1a).collect()
+}
+
+/// 実行ごとに変わらないハッシュ (FNV-1a)
+fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for c in chars {
+        for byte in (*c as u32).to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// splitmix64。`seed` ごとに別のハッシュ関数として使う
+fn mix(x: u64, seed: u64) -> u64 {
+    let mut z = x ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 文の MinHash。2つの文で値が一致する割合が shingle の Jaccard 係数の推定になる
+pub fn minhash_signature(text: &str) -> Vec<u64> {
+    let shingles = shingles(text);
+    (0..SIGNATURE_LEN as u64)
+        .map(|seed| {
+            shingles
+                .iter()
+                .map(|&s| mix(s, seed + 1))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// 確かめた類似の組。`a` は `b` より先に追加した文の番号
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearDuplicate {
+    pub a: usize,
+    pub b: usize,
+    pub similarity: f64,
+}
+
+/// 文を追加順に番号付けして LSH のバケットに入れ、似た文の組を探す索引
+#[derive(Debug, Default)]
+pub struct NearDuplicateIndex {
+    texts: Vec<String>,
+    /// (バンド, バンドのハッシュ) -> 文の番号 (昇順)
+    buckets: HashMap<(usize, u64), Vec<usize>>,
+}
+
+impl NearDuplicateIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 文を追加し、その番号を返す
+    pub fn insert(&mut self, text: &str) -> usize {
+        let index = self.texts.len();
+        let signature = minhash_signature(text);
+        for (band, rows) in signature.chunks(LSH_ROWS).enumerate() {
+            let key = rows.iter().fold(0u64, |acc, &v| mix(acc ^ v, band as u64));
+            self.buckets.entry((band, key)).or_default().push(index);
+        }
+        self.texts.push(text.to_string());
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// いずれかのバンドが一致した組 `(a, b)` (`a < b`)
+    pub fn candidate_pairs(&self) -> HashSet<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        for members in self.buckets.values() {
+            for (i, &a) in members.iter().enumerate() {
+                for &b in &members[i + 1..] {
+                    pairs.insert((a, b));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// 各文について、それより先に追加した文のうち類似度が `threshold` 以上 1.0 未満の
+    /// 最初の文を返す (完全に同じ文は類似として扱わない)。
+    /// 候補は LSH で絞り込むため、類似度が低めの組は取りこぼすことがある
+    pub fn near_duplicates(&self, threshold: f64) -> Vec<NearDuplicate> {
+        let mut candidates: Vec<Vec<usize>> = vec![Vec::new(); self.texts.len()];
+        for (a, b) in self.candidate_pairs() {
+            candidates[b].push(a);
+        }
+        let lengths: Vec<usize> = self.texts.iter().map(|t| t.chars().count()).collect();
+        candidates
+            .into_iter()
+            .enumerate()
+            .filter_map(|(b, mut earlier)| {
+                earlier.sort_unstable();
+                earlier.into_iter().find_map(|a| {
+                    // 長さの比は類似度の上限なので、比が閾値未満なら距離を計算しない
+                    let ratio = lengths[a].min(lengths[b]) as f64
+                        / lengths[a].max(lengths[b]).max(1) as f64;
+                    if ratio < threshold {
+                        return None;
+                    }
+                    let similarity = normalized_similarity(&self.texts[a], &self.texts[b]);
+                    (similarity >= threshold && similarity < 1.0).then_some(NearDuplicate {
+                        a,
+                        b,
+                        similarity,
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,9 +211,77 @@ mod tests {
         assert!(sim >= 0.85, "similarity={:.2}", sim);
     }
 
+    #[test]
+    fn test_minhash_estimates_jaccard() {
+        let a = minhash_signature("彼は忙しいのに、手伝ってくれた");
+        assert_eq!(a.len(), SIGNATURE_LEN);
+        // 空白と全角・半角の違いは無視する
+        assert_eq!(
+            minhash_signature("ＡＢＣの本を読んだ"),
+            minhash_signature("ABC の本を読んだ")
+        );
+        let agree = |b: &[u64]| a.iter().zip(b).filter(|(x, y)| x == y).count();
+        let similar = minhash_signature("彼は忙しいのに、手伝ってくれました");
+        let different = minhash_signature("明日は雨が降るそうです");
+        assert!(agree(&similar) > SIGNATURE_LEN / 2, "{}", agree(&similar));
+        assert!(
+            agree(&different) < SIGNATURE_LEN / 10,
+            "{}",
+            agree(&different)
+        );
+    }
+
+    #[test]
+    fn test_index_finds_verified_near_duplicates() {
+        let mut index = NearDuplicateIndex::new();
+        let texts = [
+            "彼は忙しいのに、手伝ってくれた",
+            "明日は雨が降るそうです",
+            "彼は忙しいのに、手伝ってくれました",
+            "彼は忙しいのに、手伝ってくれた",
+            "明日は雪が降るそうです",
+            "駅の前に新しい店ができた",
+        ];
+        for text in texts {
+            index.insert(text);
+        }
+        assert_eq!(index.len(), 6);
+        // 3 は 0 と完全に同じ文なので、次に先の 2 と組になる。4 は 1 と1文字違い
+        let found = index.near_duplicates(DEFAULT_SIMILARITY_THRESHOLD);
+        let mut pairs: Vec<(usize, usize)> = found.iter().map(|d| (d.a, d.b)).collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(0, 2), (1, 4), (2, 3)]);
+        assert!(
+            found
+                .iter()
+                .all(|d| d.similarity >= DEFAULT_SIMILARITY_THRESHOLD)
+        );
+        assert!(index.candidate_pairs().contains(&(0, 3)));
+    }
+
+    #[test]
+    fn test_index_scales_to_many_texts() {
+        let mut index = NearDuplicateIndex::new();
+        // ひらがなを疑似乱数で並べた20文字の文
+        let text = |i: u64| -> String {
+            (0..20)
+                .map(|k| char::from_u32(0x3041 + (mix(i, k) % 83) as u32).unwrap())
+                .collect()
+        };
+        for i in 0..2000 {
+            index.insert(&text(i));
+        }
+        let mut near = text(1);
+        near.push('ね');
+        let j = index.insert(&near);
+        // 候補の組は全組 (約200万) よりずっと少ない
+        assert!(index.candidate_pairs().len() < 200_000);
+        assert!(index.near_duplicates(0.9).iter().any(|d| d.b == j));
+    }
+
     #[test]
     fn test_threshold() {
-        assert!(DEFAULT_SIMILARITY_THRESHOLD > 0.0);
-        assert!(DEFAULT_SIMILARITY_THRESHOLD <= 1.0);
+        const { assert!(DEFAULT_SIMILARITY_THRESHOLD > 0.0) };
+        const { assert!(DEFAULT_SIMILARITY_THRESHOLD <= 1.0) };
     }
 }
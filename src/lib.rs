@@ -0,0 +1,4 @@
+pub mod api;
+pub mod app;
+pub mod common;
+pub mod models;
@@ -1,18 +1,11 @@
 use std::sync::Arc;
 
-use axum::{
-    Router,
-    http::Method,
-    routing::{get, post},
-};
 use log::{error, info};
-use tower_governor::{GovernorLayer, governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor};
-use tower_http::cors::CorsLayer;
-use tower_http::set_header::SetResponseHeaderLayer;
 
-mod api;
-mod common;
-mod models;
+use backend::{
+    app::{AppConfig, build_app},
+    common,
+};
 
 #[tokio::main]
 async fn main() {
@@ -22,118 +15,9 @@ async fn main() {
     };
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| {
-        error!("FRONTEND_URL未設定。デフォルト: https://jlpt.howlrs.net");
-        "https://jlpt.howlrs.net".to_string()
-    });
-
-    let origin = vec![frontend_url.parse().unwrap_or_else(|e| {
-        error!("FRONTEND_URLのパース失敗: {} - デフォルト使用", e);
-        "https://jlpt.howlrs.net".parse().unwrap()
-    })];
-
-    let db = Arc::new(common::database::Database::new().await);
-
-    // レート制限設定: 認証エンドポイント用 (5回/秒バースト, 2秒に1回持続)
-    let auth_governor_conf = Arc::new(
-        GovernorConfigBuilder::default()
-            .key_extractor(SmartIpKeyExtractor)
-            .per_second(2)
-            .burst_size(5)
-            .finish()
-            .expect("Failed to build auth rate limiter config"),
-    );
-
-    // レート制限設定: evaluate用 (10回/秒バースト)
-    let evaluate_governor_conf = Arc::new(
-        GovernorConfigBuilder::default()
-            .key_extractor(SmartIpKeyExtractor)
-            .per_second(1)
-            .burst_size(10)
-            .finish()
-            .expect("Failed to build evaluate rate limiter config"),
-    );
-
-    // 認証エンドポイント（レート制限付き）
-    let auth_routes = Router::new()
-        .route("/api/signup", post(api::user::signup))
-        .route("/api/signin", post(api::user::signin))
-        .layer(GovernorLayer::new(auth_governor_conf));
-
-    // evaluateエンドポイント（レート制限付き）
-    let evaluate_routes = Router::new()
-        .route("/api/evaluate/{vote}", get(api::evaluate::vote))
-        .layer(GovernorLayer::new(evaluate_governor_conf));
-
-    let endpoint = Router::new()
-        .route("/api/public/health", get(api::initial::public_health))
-        .route("/api/private/health", get(api::initial::private_health))
-        .route("/api/meta", get(api::meta::get))
-        .route(
-            "/api/level/{level_id}/categories/{category_id}/questions",
-            get(api::question::get),
-        )
-        .route("/api/questions/{id}", get(api::question::get_by_id))
-        .route("/api/auth/me", get(api::user::auth_me))
-        .route("/api/auth/logout", post(api::user::auth_logout))
-        .route("/api/answers", post(api::answers::record_answer))
-        .route("/api/users/me/history", get(api::answers::history))
-        .route("/api/users/me/stats", get(api::answers::stats))
-        .route("/api/users/me/mistakes", get(api::answers::mistakes))
-        .route("/api/admin/votes/summary", get(api::admin::votes_summary))
-        .route("/api/admin/questions/bad", get(api::admin::bad_questions))
-        .route("/api/admin/stats", get(api::admin::stats))
-        .route("/api/admin/coverage-stats", get(api::admin::coverage_stats))
-        .route(
-            "/api/admin/questions/bulk-delete",
-            post(api::admin::bulk_delete),
-        )
-        .route(
-            "/api/admin/questions/{id}",
-            get(api::admin::question_detail).delete(api::admin::delete_question),
-        )
-        .route(
-            "/api/admin/monitor-quality",
-            post(api::monitor::monitor_quality),
-        )
-        .route(
-            "/api/questions/{id}/report",
-            post(api::report::report_question),
-        )
-        .route("/api/admin/reports", get(api::report::list_reports))
-        .route("/api/admin/duplicates", get(api::admin::duplicates))
-        .merge(auth_routes)
-        .merge(evaluate_routes)
-        // セキュリティヘッダー
-        .layer(SetResponseHeaderLayer::overriding(
-            axum::http::header::X_FRAME_OPTIONS,
-            axum::http::HeaderValue::from_static("DENY"),
-        ))
-        .layer(SetResponseHeaderLayer::overriding(
-            axum::http::header::X_CONTENT_TYPE_OPTIONS,
-            axum::http::HeaderValue::from_static("nosniff"),
-        ))
-        .layer(SetResponseHeaderLayer::overriding(
-            axum::http::header::STRICT_TRANSPORT_SECURITY,
-            axum::http::HeaderValue::from_static("max-age=31536000; includeSubDomains"),
-        ))
-        .layer(
-            CorsLayer::new()
-                .allow_methods([
-                    Method::GET,
-                    Method::POST,
-                    Method::PUT,
-                    Method::DELETE,
-                    Method::OPTIONS,
-                ])
-                .allow_origin(origin)
-                .allow_headers([
-                    "Content-Type".parse().unwrap(),
-                    "Authorization".parse().unwrap(),
-                ])
-                .allow_credentials(true),
-        )
-        .with_state(db);
+    let config = AppConfig::from_env();
+    let db = Arc::new(common::database::Database::from_env().await);
+    let endpoint = build_app(db, config);
 
     let port = std::env::var("PORT").unwrap_or("8080".to_string());
     info!("サーバー起動: 0.0.0.0:{}", port);
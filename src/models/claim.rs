@@ -5,9 +5,9 @@ use argon2::{
     password_hash::{SaltString, rand_core::OsRng},
 };
 use axum::{
-    Json, RequestPartsExt,
-    extract::FromRequestParts,
-    http::{StatusCode, request::Parts},
+    RequestPartsExt,
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::request::Parts,
     response::{IntoResponse, Response},
 };
 use axum_extra::{
@@ -17,7 +17,8 @@ use axum_extra::{
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+
+use crate::common::error::AppError;
 
 // Axum examples/jwt 実装を踏襲
 // https://github.com/tokio-rs/axum/blob/main/examples/jwt/src/main.rs
@@ -33,20 +34,19 @@ pub enum AuthError {
     Forbidden,
 }
 
+impl From<AuthError> for AppError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::InvalidToken => AppError::Unauthorized("Invalid token".to_string()),
+            AuthError::MissingToken => AppError::Validation("Missing token".to_string()),
+            AuthError::Forbidden => AppError::Forbidden("Forbidden".to_string()),
+        }
+    }
+}
+
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AuthError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
-            AuthError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
-        };
-
-        let body = Json(json!({
-            "code": status.as_u16(),
-            "error": message.to_string(),
-        }));
-
-        (status, body).into_response()
+        AppError::from(self).into_response()
     }
 }
 
@@ -69,6 +69,10 @@ impl Claims {
         }
     }
 
+    pub fn is_admin(&self) -> bool {
+        self.role.as_deref() == Some("admin")
+    }
+
     pub fn is_ok(&self) -> bool {
         let now = chrono::Utc::now().timestamp();
         self.exp > now
@@ -122,7 +126,10 @@ where
     type Rejection = AuthError;
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         // 1. Cookie から access_token を取得（優先）
-        let jar = parts.extract::<axum_extra::extract::CookieJar>().await.unwrap();
+        let jar = parts
+            .extract::<axum_extra::extract::CookieJar>()
+            .await
+            .unwrap();
         if let Some(cookie) = jar.get("access_token") {
             return validate_jwt(cookie.value()).map_err(|_| AuthError::InvalidToken);
         }
@@ -136,6 +143,24 @@ where
     }
 }
 
+// ログイン任意のエンドポイント用。トークンが無い・不正な場合は None
+impl<S> OptionalFromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(
+            <Claims as FromRequestParts<S>>::from_request_parts(parts, state)
+                .await
+                .ok(),
+        )
+    }
+}
+
 // Admin権限を持つClaimsを抽出するExtractor
 #[derive(Debug)]
 pub struct AdminClaims(pub Claims);
@@ -146,10 +171,11 @@ where
 {
     type Rejection = AuthError;
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let claims = Claims::from_request_parts(parts, state).await?;
-        match &claims.role {
-            Some(role) if role == "admin" => Ok(AdminClaims(claims)),
-            _ => Err(AuthError::Forbidden),
+        let claims = <Claims as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+        if claims.is_admin() {
+            Ok(AdminClaims(claims))
+        } else {
+            Err(AuthError::Forbidden)
         }
     }
 }
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::question::Question;
+
+/// カテゴリの種別。カテゴリ名から [`CategoryKind::classify`] で判定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryKind {
+    /// 文字・語彙 (漢字読み・表記・語形成・文脈規定・言い換え類義・用法)
+    Vocabulary,
+    /// 文法 (文法形式の判断・文の組み立て・文章の文法)
+    Grammar,
+    Reading,
+    Listening,
+}
+
+impl CategoryKind {
+    pub fn classify(category_name: &str) -> Self {
+        const LISTENING: [&str; 6] = [
+            "聴解",
+            "課題理解",
+            "ポイント理解",
+            "概要理解",
+            "発話表現",
+            "即時応答",
+        ];
+        const READING: [&str; 5] = ["読解", "内容理解", "統合理解", "主張理解", "情報検索"];
+        const GRAMMAR: [&str; 2] = ["文法", "文の組み立て"];
+
+        let matches = |keywords: &[&str]| keywords.iter().any(|k| category_name.contains(k));
+        if matches(&LISTENING) {
+            CategoryKind::Listening
+        } else if matches(&READING) {
+            CategoryKind::Reading
+        } else if matches(&GRAMMAR) {
+            CategoryKind::Grammar
+        } else {
+            CategoryKind::Vocabulary
+        }
+    }
+}
+
+/// 試験科目 (時間区分)。`quotas` は種別ごとの出題小問数
+#[derive(Debug)]
+pub struct SectionSpec {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub time_limit_mins: u32,
+    pub quotas: &'static [(CategoryKind, u32)],
+}
+
+/// 得点区分。素点を `max_score` 点満点に換算し、`min_score` 未満は基準点割れ
+#[derive(Debug)]
+pub struct ScoreDivisionSpec {
+    pub name: &'static str,
+    pub kinds: &'static [CategoryKind],
+    pub max_score: u32,
+    pub min_score: u32,
+}
+
+/// レベルごとの試験構成と合格点
+#[derive(Debug)]
+pub struct Blueprint {
+    pub level_id: u32,
+    pub sections: &'static [SectionSpec],
+    pub divisions: &'static [ScoreDivisionSpec],
+    pub pass_mark: u32,
+}
+
+use CategoryKind::{Grammar, Listening, Reading, Vocabulary};
+
+const DIVISIONS_N1_N3: &[ScoreDivisionSpec] = &[
+    ScoreDivisionSpec {
+        name: "言語知識（文字・語彙・文法）",
+        kinds: &[Vocabulary, Grammar],
+        max_score: 60,
+        min_score: 19,
+    },
+    ScoreDivisionSpec {
+        name: "読解",
+        kinds: &[Reading],
+        max_score: 60,
+        min_score: 19,
+    },
+    ScoreDivisionSpec {
+        name: "聴解",
+        kinds: &[Listening],
+        max_score: 60,
+        min_score: 19,
+    },
+];
+
+const DIVISIONS_N4_N5: &[ScoreDivisionSpec] = &[
+    ScoreDivisionSpec {
+        name: "言語知識（文字・語彙・文法）・読解",
+        kinds: &[Vocabulary, Grammar, Reading],
+        max_score: 120,
+        min_score: 38,
+    },
+    ScoreDivisionSpec {
+        name: "聴解",
+        kinds: &[Listening],
+        max_score: 60,
+        min_score: 19,
+    },
+];
+
+/// 公式の試験科目・時間・合格点に合わせた構成 (出題数は概数)
+const BLUEPRINTS: &[Blueprint] = &[
+    Blueprint {
+        level_id: 1,
+        sections: &[
+            SectionSpec {
+                key: "language_reading",
+                name: "言語知識（文字・語彙・文法）・読解",
+                time_limit_mins: 110,
+                quotas: &[(Vocabulary, 25), (Grammar, 20), (Reading, 20)],
+            },
+            SectionSpec {
+                key: "listening",
+                name: "聴解",
+                time_limit_mins: 55,
+                quotas: &[(Listening, 30)],
+            },
+        ],
+        divisions: DIVISIONS_N1_N3,
+        pass_mark: 100,
+    },
+    Blueprint {
+        level_id: 2,
+        sections: &[
+            SectionSpec {
+                key: "language_reading",
+                name: "言語知識（文字・語彙・文法）・読解",
+                time_limit_mins: 105,
+                quotas: &[(Vocabulary, 32), (Grammar, 22), (Reading, 21)],
+            },
+            SectionSpec {
+                key: "listening",
+                name: "聴解",
+                time_limit_mins: 50,
+                quotas: &[(Listening, 32)],
+            },
+        ],
+        divisions: DIVISIONS_N1_N3,
+        pass_mark: 90,
+    },
+    Blueprint {
+        level_id: 3,
+        sections: &[
+            SectionSpec {
+                key: "vocabulary",
+                name: "言語知識（文字・語彙）",
+                time_limit_mins: 30,
+                quotas: &[(Vocabulary, 35)],
+            },
+            SectionSpec {
+                key: "grammar_reading",
+                name: "言語知識（文法）・読解",
+                time_limit_mins: 70,
+                quotas: &[(Grammar, 23), (Reading, 16)],
+            },
+            SectionSpec {
+                key: "listening",
+                name: "聴解",
+                time_limit_mins: 40,
+                quotas: &[(Listening, 28)],
+            },
+        ],
+        divisions: DIVISIONS_N1_N3,
+        pass_mark: 95,
+    },
+    Blueprint {
+        level_id: 4,
+        sections: &[
+            SectionSpec {
+                key: "vocabulary",
+                name: "言語知識（文字・語彙）",
+                time_limit_mins: 25,
+                quotas: &[(Vocabulary, 35)],
+            },
+            SectionSpec {
+                key: "grammar_reading",
+                name: "言語知識（文法）・読解",
+                time_limit_mins: 55,
+                quotas: &[(Grammar, 21), (Reading, 13)],
+            },
+            SectionSpec {
+                key: "listening",
+                name: "聴解",
+                time_limit_mins: 35,
+                quotas: &[(Listening, 28)],
+            },
+        ],
+        divisions: DIVISIONS_N4_N5,
+        pass_mark: 90,
+    },
+    Blueprint {
+        level_id: 5,
+        sections: &[
+            SectionSpec {
+                key: "vocabulary",
+                name: "言語知識（文字・語彙）",
+                time_limit_mins: 20,
+                quotas: &[(Vocabulary, 33)],
+            },
+            SectionSpec {
+                key: "grammar_reading",
+                name: "言語知識（文法）・読解",
+                time_limit_mins: 40,
+                quotas: &[(Grammar, 16), (Reading, 9)],
+            },
+            SectionSpec {
+                key: "listening",
+                name: "聴解",
+                time_limit_mins: 30,
+                quotas: &[(Listening, 24)],
+            },
+        ],
+        divisions: DIVISIONS_N4_N5,
+        pass_mark: 80,
+    },
+];
+
+pub fn blueprint(level_id: u32) -> Option<&'static Blueprint> {
+    BLUEPRINTS.iter().find(|b| b.level_id == level_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExamStatus {
+    InProgress,
+    Finished,
+}
+
+/// 模試セッション (`exams` コレクション、ドキュメントIDは `id`)。
+///
+/// 出題した問題は正解を含めてスナップショットし、採点に使う。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamSession {
+    pub id: String,
+    pub user_id: String,
+    pub level_id: u32,
+    pub status: ExamStatus,
+    pub created_at: i64,
+    pub sections: Vec<ExamSection>,
+    #[serde(default)]
+    pub finished_at: Option<i64>,
+    #[serde(default)]
+    pub result: Option<ExamResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamSection {
+    pub key: String,
+    pub name: String,
+    pub time_limit_secs: i64,
+    /// 問題を最初に配信した時刻。制限時間はここから数え、これ以降の科目を始めるとこの科目には戻れない
+    #[serde(default)]
+    pub started_at: Option<i64>,
+    pub questions: Vec<ExamQuestion>,
+}
+
+impl ExamSection {
+    pub fn deadline(&self) -> Option<i64> {
+        self.started_at.map(|t| t + self.time_limit_secs)
+    }
+
+    pub fn contains(&self, question_id: &str, sub_question_id: u32) -> bool {
+        self.questions.iter().any(|q| {
+            q.question.id == question_id
+                && q.question
+                    .sub_questions
+                    .iter()
+                    .any(|sq| sq.id == sub_question_id)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamQuestion {
+    pub kind: CategoryKind,
+    pub question: Question,
+}
+
+/// 模試内の回答 (`exam_answers` コレクション、ドキュメントIDは `{exam_id}_{question_id}_{sub_question_id}`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamAnswer {
+    pub exam_id: String,
+    pub user_id: String,
+    pub question_id: String,
+    pub sub_question_id: u32,
+    pub selected_answer: String,
+    pub answered_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionScore {
+    pub key: String,
+    pub name: String,
+    pub correct: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DivisionScore {
+    pub name: String,
+    pub correct: u32,
+    pub total: u32,
+    /// 換算点。出題が無い区分は `None` で、合否判定から除く
+    pub scaled: Option<u32>,
+    pub max_score: u32,
+    pub min_score: u32,
+    pub passed: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExamResult {
+    pub sections: Vec<SectionScore>,
+    pub divisions: Vec<DivisionScore>,
+    pub total_score: u32,
+    pub max_score: u32,
+    /// 出題のあった区分の満点に合わせて按分した合格点
+    pub pass_mark: u32,
+    pub passed: bool,
+}
+
+/// 素点を集計し、得点区分ごとの換算点と合否を求める。
+///
+/// `answers` のキーは `(question_id, sub_question_id)`、値は選択したキー。
+pub fn score_exam(
+    blueprint: &Blueprint,
+    sections: &[ExamSection],
+    answers: &HashMap<(String, u32), String>,
+) -> ExamResult {
+    let mut by_kind: HashMap<CategoryKind, (u32, u32)> = HashMap::new();
+    let sections = sections
+        .iter()
+        .map(|section| {
+            let (mut correct, mut total) = (0, 0);
+            for eq in &section.questions {
+                for sq in &eq.question.sub_questions {
+                    let is_correct = answers
+                        .get(&(eq.question.id.clone(), sq.id))
+                        .is_some_and(|a| *a == sq.answer);
+                    let entry = by_kind.entry(eq.kind).or_default();
+                    entry.0 += u32::from(is_correct);
+                    entry.1 += 1;
+                    correct += u32::from(is_correct);
+                    total += 1;
+                }
+            }
+            SectionScore {
+                key: section.key.clone(),
+                name: section.name.clone(),
+                correct,
+                total,
+            }
+        })
+        .collect();
+
+    let divisions: Vec<DivisionScore> = blueprint
+        .divisions
+        .iter()
+        .map(|spec| {
+            let (correct, total) = spec
+                .kinds
+                .iter()
+                .filter_map(|k| by_kind.get(k))
+                .fold((0, 0), |acc, (c, t)| (acc.0 + c, acc.1 + t));
+            let scaled = (total > 0)
+                .then(|| (f64::from(correct * spec.max_score) / f64::from(total)).round() as u32);
+            DivisionScore {
+                name: spec.name.to_string(),
+                correct,
+                total,
+                scaled,
+                max_score: spec.max_score,
+                min_score: spec.min_score,
+                passed: scaled.map(|s| s >= spec.min_score),
+            }
+        })
+        .collect();
+
+    let present = || divisions.iter().filter(|d| d.scaled.is_some());
+    let total_score = present().filter_map(|d| d.scaled).sum();
+    let max_score: u32 = present().map(|d| d.max_score).sum();
+    let full_max: u32 = blueprint.divisions.iter().map(|d| d.max_score).sum();
+    let pass_mark = (blueprint.pass_mark * max_score).div_ceil(full_max);
+    let passed =
+        max_score > 0 && present().all(|d| d.passed == Some(true)) && total_score >= pass_mark;
+
+    ExamResult {
+        sections,
+        divisions,
+        total_score,
+        max_score,
+        pass_mark,
+        passed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::question::SubQuestion;
+
+    fn exam_question(id: &str, kind: CategoryKind, subs: u32) -> ExamQuestion {
+        ExamQuestion {
+            kind,
+            question: Question {
+                id: id.to_string(),
+                sub_questions: (1..=subs)
+                    .map(|i| SubQuestion {
+                        id: i,
+                        answer: "1".to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn section(key: &str, questions: Vec<ExamQuestion>) -> ExamSection {
+        ExamSection {
+            key: key.to_string(),
+            name: key.to_string(),
+            time_limit_secs: 60,
+            started_at: None,
+            questions,
+        }
+    }
+
+    /// `question_id` の先頭 `n` 問を正解、残りを不正解にした回答
+    fn answers(entries: &[(&str, u32, u32)]) -> HashMap<(String, u32), String> {
+        entries
+            .iter()
+            .flat_map(|(qid, subs, correct)| {
+                (1..=*subs).map(move |i| {
+                    let key = if i <= *correct { "1" } else { "2" };
+                    ((qid.to_string(), i), key.to_string())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(CategoryKind::classify("漢字読み"), Vocabulary);
+        assert_eq!(CategoryKind::classify("文法形式の判断"), Grammar);
+        assert_eq!(CategoryKind::classify("文章の文法"), Grammar);
+        assert_eq!(CategoryKind::classify("内容理解（短文）"), Reading);
+        assert_eq!(CategoryKind::classify("課題理解"), Listening);
+    }
+
+    #[test]
+    fn test_blueprints_cover_all_levels() {
+        for level_id in 1..=5 {
+            let bp = blueprint(level_id).unwrap();
+            let kinds: Vec<_> = bp.divisions.iter().flat_map(|d| d.kinds).collect();
+            for section in bp.sections {
+                assert!(section.quotas.iter().all(|(k, _)| kinds.contains(&k)));
+            }
+        }
+        assert!(blueprint(6).is_none());
+    }
+
+    #[test]
+    fn test_score_exam_pass_and_section_minimum() {
+        let bp = blueprint(3).unwrap();
+        let sections = vec![
+            section("vocabulary", vec![exam_question("v", Vocabulary, 10)]),
+            section(
+                "grammar_reading",
+                vec![
+                    exam_question("g", Grammar, 10),
+                    exam_question("r", Reading, 10),
+                ],
+            ),
+            section("listening", vec![exam_question("l", Listening, 10)]),
+        ];
+
+        let result = score_exam(
+            bp,
+            &sections,
+            &answers(&[("v", 10, 8), ("g", 10, 6), ("r", 10, 5), ("l", 10, 7)]),
+        );
+        assert_eq!(result.sections[0].correct, 8);
+        assert_eq!(result.sections[1].correct, 11);
+        // 言語知識 14/20 → 42, 読解 5/10 → 30, 聴解 7/10 → 42
+        let scaled: Vec<_> = result.divisions.iter().map(|d| d.scaled).collect();
+        assert_eq!(scaled, vec![Some(42), Some(30), Some(42)]);
+        assert_eq!(result.total_score, 114);
+        assert_eq!(result.pass_mark, 95);
+        assert!(result.passed);
+
+        // 合計点が足りていても基準点割れの区分があれば不合格
+        let result = score_exam(
+            bp,
+            &sections,
+            &answers(&[("v", 10, 10), ("g", 10, 10), ("r", 10, 3), ("l", 10, 10)]),
+        );
+        assert_eq!(result.divisions[1].scaled, Some(18));
+        assert_eq!(result.divisions[1].passed, Some(false));
+        assert!(result.total_score >= result.pass_mark);
+        assert!(!result.passed);
+    }
+
+    #[test]
+    fn test_score_exam_excludes_empty_divisions() {
+        let bp = blueprint(5).unwrap();
+        let sections = vec![section(
+            "vocabulary",
+            vec![exam_question("v", Vocabulary, 4)],
+        )];
+
+        let result = score_exam(bp, &sections, &answers(&[("v", 4, 3)]));
+        assert_eq!(result.divisions[1].scaled, None);
+        assert_eq!(result.divisions[1].passed, None);
+        assert_eq!(result.total_score, 90);
+        assert_eq!(result.max_score, 120);
+        // 80 * 120 / 180 = 53.3 → 54
+        assert_eq!(result.pass_mark, 54);
+        assert!(result.passed);
+    }
+}
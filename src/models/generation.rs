@@ -0,0 +1,263 @@
+//! 生成AIによる問題作成ジョブ。
+//!
+//! 管理者が (レベル, カテゴリ) を指定してジョブを起動すると、保存済みのプロンプトテンプレートから
+//! プロンプトを組み立てて LLM を呼び出し、出力を `Question` として読み取る。
+//! 構造チェックと dedup キーの照合を通った候補だけをレビュー待ち (`pending_review`) の問題として作成する。
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::question::{Question, SubQuestion};
+
+/// テンプレートが保存されていない場合に使うテンプレートのID
+pub const DEFAULT_TEMPLATE_ID: &str = "default";
+
+/// 保存済みのテンプレートが無い場合のプロンプト
+pub const BUILTIN_TEMPLATE: &str = "あなたは日本語能力試験 (JLPT) の問題作成者です。
+{level} の「{category}」の問題を {count} 問作成してください。
+
+- 各問題は小問 (sub_questions) を1つ以上持ち、小問ごとに選択肢を4つ (key は \"1\"〜\"4\") 用意する
+- answer には正解の選択肢の key を入れる
+- 既存の問題と同じ選択肢の組み合わせにしない
+
+次の JSON 配列だけを出力してください。説明文やコードブロックは不要です。
+[{\"sentence\": \"問題全体の指示文\", \"prerequisites\": null, \"sub_questions\": [{\"id\": 1, \"sentence\": \"小問の文\", \"select_answer\": [{\"key\": \"1\", \"value\": \"...\"}, {\"key\": \"2\", \"value\": \"...\"}, {\"key\": \"3\", \"value\": \"...\"}, {\"key\": \"4\", \"value\": \"...\"}], \"answer\": \"1\", \"explanation\": \"解説\", \"option_explanations\": {\"1\": \"この選択肢が正しい理由\", \"2\": \"この選択肢が誤りである理由\"}}]}]
+
+{examples}";
+
+/// プロンプトテンプレート (`prompt_templates` コレクション、ドキュメントIDは `id`)。
+///
+/// 本文の `{level}` `{category}` `{count}` `{examples}` をジョブの内容で置き換える。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub body: String,
+    #[serde(default)]
+    pub updated_by: String,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+impl PromptTemplate {
+    pub fn builtin() -> Self {
+        Self {
+            id: DEFAULT_TEMPLATE_ID.to_string(),
+            body: BUILTIN_TEMPLATE.to_string(),
+            updated_by: String::new(),
+            updated_at: 0,
+        }
+    }
+
+    /// プレースホルダを置き換えたプロンプト
+    pub fn render(&self, level: &str, category: &str, count: u32, examples: &[Question]) -> String {
+        let examples = if examples.is_empty() {
+            String::new()
+        } else {
+            let lines: Vec<String> = examples
+                .iter()
+                .filter_map(|q| serde_json::to_string(q).ok())
+                .collect();
+            format!("参考 (既存の問題):\n{}", lines.join("\n"))
+        };
+        self.body
+            .replace("{level}", level)
+            .replace("{category}", category)
+            .replace("{count}", &count.to_string())
+            .replace("{examples}", &examples)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// 生成されたが取り込まなかった候補
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectedCandidate {
+    /// モデル出力内の位置 (0 始まり)
+    pub index: usize,
+    pub reason: String,
+}
+
+/// 生成ジョブ (`generation_jobs` コレクション)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationJob {
+    pub id: String,
+    pub level_id: u32,
+    pub category_id: String,
+    pub count: u32,
+    pub template_id: String,
+    pub status: JobStatus,
+    /// 起動した管理者の user_id
+    pub created_by: String,
+    pub created_at: i64,
+    #[serde(default)]
+    pub finished_at: Option<i64>,
+    /// 使用したモデル名
+    #[serde(default)]
+    pub model: Option<String>,
+    /// レビュー待ちとして作成した問題のID
+    #[serde(default)]
+    pub pending_ids: Vec<String>,
+    #[serde(default)]
+    pub rejected: Vec<RejectedCandidate>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// モデルの出力から問題の配列を読み取る。
+///
+/// コードブロックや前後の説明文が付いていても、最初の括弧から対応する最後の閉じ括弧までを JSON として読む。
+/// 1件だけのオブジェクトも受け付ける。要素ごとに読み取った結果を返す。
+pub fn parse_candidates(output: &str) -> Result<Vec<Result<Question, String>>, String> {
+    let items = extract_json_items(output)?;
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            serde_json::from_value::<Question>(item)
+                .map_err(|e| format!("問題の形式が不正です: {}", e))
+        })
+        .collect())
+}
+
+/// 出力から JSON を切り出し、配列なら要素を、単一のオブジェクトならそれだけを返す
+fn extract_json_items(output: &str) -> Result<Vec<Value>, String> {
+    let trimmed = output.trim();
+    // 先に現れた括弧の種類で配列か単一のオブジェクトかを決める
+    let close = match trimmed.find(['[', '{']).map(|i| &trimmed[i..i + 1]) {
+        Some("[") => ']',
+        Some(_) => '}',
+        None => return Err("出力に JSON が含まれていません".to_string()),
+    };
+    let json = match (trimmed.find(['[', '{']), trimmed.rfind(close)) {
+        (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+        _ => return Err("出力に JSON が含まれていません".to_string()),
+    };
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| format!("出力を JSON として読めません: {}", e))?;
+    match value {
+        Value::Array(items) => Ok(items),
+        item @ Value::Object(_) => Ok(vec![item]),
+        _ => Err("出力が配列ではありません".to_string()),
+    }
+}
+
+/// 解説の下書きを依頼するプロンプト
+pub const EXPLANATION_PROMPT: &str = "あなたは日本語能力試験 (JLPT) の問題の解説者です。
+次の {level} の問題の小問 {sub_ids} について、学習者向けの解説を日本語で書いてください。
+
+- explanation には正解の理由を2〜3文で書く
+- option_explanations には選択肢の key ごとに、その選択肢が正解または不正解である理由を1文で書く
+
+次の JSON 配列だけを出力してください。説明文やコードブロックは不要です。
+[{\"sub_question_id\": 1, \"explanation\": \"解説\", \"option_explanations\": {\"1\": \"...\", \"2\": \"...\"}}]
+
+問題:
+{question}";
+
+/// モデルが書いた小問1つ分の解説の下書き
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExplanationDraft {
+    pub sub_question_id: u32,
+    #[serde(default)]
+    pub explanation: String,
+    #[serde(default)]
+    pub option_explanations: BTreeMap<String, String>,
+}
+
+/// `targets` の小問の解説を依頼するプロンプト。問題は既存の解説を含めてそのまま渡す
+pub fn explanation_prompt(question: &Question, targets: &[&SubQuestion]) -> String {
+    let sub_ids: Vec<String> = targets.iter().map(|s| s.id.to_string()).collect();
+    EXPLANATION_PROMPT
+        .replace("{level}", &question.level_name)
+        .replace("{sub_ids}", &sub_ids.join(", "))
+        .replace(
+            "{question}",
+            &serde_json::to_string(question).unwrap_or_default(),
+        )
+}
+
+/// モデルの出力から解説の下書きを読み取る。形式が不正な要素は読み飛ばす
+pub fn parse_explanation_drafts(output: &str) -> Result<Vec<ExplanationDraft>, String> {
+    let items = extract_json_items(output)?;
+    Ok(items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value::<ExplanationDraft>(item).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let template = PromptTemplate {
+            body: "{level} {category} を {count} 問\n{examples}".to_string(),
+            ..PromptTemplate::builtin()
+        };
+        assert_eq!(template.render("N3", "文法", 5, &[]), "N3 文法 を 5 問\n");
+
+        let example = Question {
+            id: "q1".to_string(),
+            ..Default::default()
+        };
+        let prompt = template.render("N3", "文法", 5, &[example]);
+        assert!(prompt.contains("参考 (既存の問題):\n{\"id\":\"q1\""));
+    }
+
+    #[test]
+    fn test_parse_candidates_tolerates_code_fences() {
+        let output = "以下の通りです。\n```json\n[{\"sentence\": \"a\", \"sub_questions\": []}, {\"sub_questions\": 1}]\n```";
+        let parsed = parse_candidates(output).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].as_ref().unwrap().sentence, "a");
+        assert!(parsed[1].is_err());
+
+        let single = parse_candidates("{\"sentence\": \"b\", \"sub_questions\": []}").unwrap();
+        assert_eq!(single.len(), 1);
+
+        assert!(parse_candidates("ごめんなさい").is_err());
+        assert!(parse_candidates("[1, 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_explanation_drafts() {
+        let output = "```json\n[{\"sub_question_id\": 1, \"explanation\": \"理由\", \"option_explanations\": {\"2\": \"違う\"}}, {\"explanation\": \"id なし\"}]\n```";
+        let drafts = parse_explanation_drafts(output).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].sub_question_id, 1);
+        assert_eq!(drafts[0].explanation, "理由");
+        assert_eq!(drafts[0].option_explanations["2"], "違う");
+
+        assert!(parse_explanation_drafts("わかりません").is_err());
+    }
+
+    #[test]
+    fn test_explanation_prompt_lists_targets() {
+        let question = Question {
+            id: "q1".to_string(),
+            level_name: "N3".to_string(),
+            ..Default::default()
+        };
+        let subs = [
+            SubQuestion {
+                id: 1,
+                ..Default::default()
+            },
+            SubQuestion {
+                id: 3,
+                ..Default::default()
+            },
+        ];
+        let prompt = explanation_prompt(&question, &[&subs[0], &subs[1]]);
+        assert!(prompt.contains("N3 の問題の小問 1, 3 について"));
+        assert!(prompt.contains("{\"id\":\"q1\""));
+    }
+}
@@ -0,0 +1,170 @@
+//! 小問の段階的なヒント。
+//!
+//! 問題に登録したヒント (読み・文法項目・補足) を易しい順に出し、最後の段階として
+//! 誤りの選択肢を1つ除外する。ヒントを見た小問は `hint_usages` に記録し、
+//! 次の回答を「ヒントあり」として集計する。
+
+use serde::{Deserialize, Serialize};
+
+use super::question::SubQuestion;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HintKind {
+    /// 鍵になる漢字の読み
+    Reading,
+    /// 文法項目の名前
+    GrammarPoint,
+    /// その他の補足
+    Note,
+    /// 誤りの選択肢を1つ除外する (自動で作る段階。問題には登録できない)
+    Eliminate,
+}
+
+/// 問題に登録するヒント
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hint {
+    pub kind: HintKind,
+    pub text: String,
+}
+
+/// 学習者に返すヒントの1段
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HintStep {
+    /// 1 始まりの段階
+    pub level: u32,
+    pub kind: HintKind,
+    pub text: String,
+    /// `eliminate` で除外した選択肢の key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl HintStep {
+    /// 選択肢 `key` を除外する段
+    pub fn eliminate(level: u32, key: &str, value: &str) -> Self {
+        Self {
+            level,
+            kind: HintKind::Eliminate,
+            text: format!("{}. {} は正解ではありません", key, value),
+            key: Some(key.to_string()),
+        }
+    }
+}
+
+/// ヒントの利用記録 (`hint_usages` コレクション)。
+///
+/// ドキュメントIDは `{user_id}_{question_id}_{sub_question_id}`。次の回答で消費し、その回答を
+/// `assisted` として記録する。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HintUsage {
+    pub id: String,
+    pub user_id: String,
+    pub question_id: String,
+    pub sub_question_id: u32,
+    /// 見たヒントの最大の段階
+    pub level: u32,
+    pub used_at: i64,
+}
+
+impl HintUsage {
+    pub fn doc_id(user_id: &str, question_id: &str, sub_question_id: u32) -> String {
+        format!("{}_{}_{}", user_id, question_id, sub_question_id)
+    }
+}
+
+/// 小問のヒントを易しい順に並べる。
+///
+/// 登録済みのヒントのあとに、選択肢が3つ以上あれば誤りの選択肢を1つ除外する段を加える。
+/// 除外する選択肢は `seed` (ユーザーと小問) から決めるため、同じ学習者には毎回同じものを返す。
+pub fn hint_ladder(sub_q: &SubQuestion, seed: &str) -> Vec<HintStep> {
+    let mut steps: Vec<HintStep> = sub_q
+        .hints
+        .iter()
+        .filter(|hint| hint.kind != HintKind::Eliminate && !hint.text.trim().is_empty())
+        .map(|hint| HintStep {
+            level: 0,
+            kind: hint.kind,
+            text: hint.text.clone(),
+            key: None,
+        })
+        .collect();
+
+    let distractors: Vec<_> = sub_q
+        .select_answer
+        .iter()
+        .filter(|choice| choice.key != sub_q.answer)
+        .collect();
+    if sub_q.select_answer.len() >= 3 && !distractors.is_empty() {
+        let choice = distractors[seed_index(seed, distractors.len())];
+        steps.push(HintStep::eliminate(0, &choice.key, &choice.value));
+    }
+
+    for (i, step) in steps.iter_mut().enumerate() {
+        step.level = i as u32 + 1;
+    }
+    steps
+}
+
+/// 実行環境によらない FNV-1a で `seed` から添字を決める
+fn seed_index(seed: &str, len: usize) -> usize {
+    let hash = seed.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash % len as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::question::SelectAnswer;
+
+    fn sub_question(hints: Vec<Hint>) -> SubQuestion {
+        SubQuestion {
+            id: 1,
+            select_answer: ["ので", "のに", "けど", "から"]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| SelectAnswer {
+                    key: (i + 1).to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            answer: "1".to_string(),
+            hints,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ladder_ends_with_elimination() {
+        let sub_q = sub_question(vec![
+            Hint {
+                kind: HintKind::GrammarPoint,
+                text: "理由の「ので」".to_string(),
+            },
+            Hint {
+                kind: HintKind::Note,
+                text: " ".to_string(),
+            },
+        ]);
+        let steps = hint_ladder(&sub_q, "u1_q1_1");
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].level, 1);
+        assert_eq!(steps[0].kind, HintKind::GrammarPoint);
+        assert_eq!(steps[1].level, 2);
+        assert_eq!(steps[1].kind, HintKind::Eliminate);
+
+        let key = steps[1].key.clone().unwrap();
+        assert_ne!(key, "1");
+        // 同じ seed なら同じ選択肢を除外する
+        assert_eq!(hint_ladder(&sub_q, "u1_q1_1")[1].key, Some(key));
+    }
+
+    #[test]
+    fn test_ladder_without_enough_choices() {
+        let mut sub_q = sub_question(vec![]);
+        sub_q.select_answer.truncate(2);
+        assert!(hint_ladder(&sub_q, "seed").is_empty());
+    }
+}
@@ -1,6 +1,13 @@
 pub mod claim;
 pub mod evaluate;
+pub mod exam;
+pub mod generation;
+pub mod hint;
 pub mod meta;
+pub mod quality_rule;
 pub mod question;
+pub mod rating;
 pub mod report;
+pub mod review;
+pub mod revision;
 pub mod user;
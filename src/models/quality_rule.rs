@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 品質規則の設定を保存するコレクション
+pub const QUALITY_RULES_COLLECTION: &str = "quality_rules";
+
+/// 規則に違反した問題の扱い。`Warn < Quarantine < Delete` の順に重い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// 報告のみ
+    Warn,
+    /// 公開中ならレビュー待ちに戻す。作成・取り込み時は拒否する
+    Quarantine,
+    /// 品質監視で削除する。作成・取り込み時は拒否する
+    Delete,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warn => "warn",
+            Severity::Quarantine => "quarantine",
+            Severity::Delete => "delete",
+        }
+    }
+}
+
+/// 規則1つ分の設定。`level_ids` / `category_ids` が空ならすべてに当てはまる。
+///
+/// 同じ規則に複数の設定が当てはまる場合は、範囲の広いものから順に重ねる
+/// (レベルとカテゴリの両方を指定したものが最後)。省略した項目は前の値を引き継ぐ
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    /// 設定のID (Firestore のドキュメントID)
+    #[serde(default)]
+    pub id: String,
+    /// 規則のID (`option_count` 等)
+    pub rule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub level_ids: Vec<u32>,
+    #[serde(default)]
+    pub category_ids: Vec<String>,
+    /// 規則ごとの引数。オブジェクトのキー単位で既定値に上書きする
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+    #[serde(default)]
+    pub updated_by: String,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+impl RuleConfig {
+    /// `level_id` / `category_id` の問題に当てはまるか
+    pub fn matches(&self, level_id: u32, category_id: Option<&str>) -> bool {
+        (self.level_ids.is_empty() || self.level_ids.contains(&level_id))
+            && (self.category_ids.is_empty()
+                || category_id.is_some_and(|c| self.category_ids.iter().any(|id| id == c)))
+    }
+
+    /// 範囲の狭さ (レベル・カテゴリを指定した数)
+    pub fn specificity(&self) -> u8 {
+        u8::from(!self.level_ids.is_empty()) + u8::from(!self.category_ids.is_empty())
+    }
+}
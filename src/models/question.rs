@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Deserializer, Serialize};
 
+use super::hint::Hint;
+
 #[derive(Clone, Serialize, Debug, Default)]
 pub struct Question {
     #[serde(default)]
@@ -18,9 +22,127 @@ pub struct Question {
     pub prerequisites: Option<String>,
     pub sub_questions: Vec<SubQuestion>,
 
+    /// 出題形式。`question_type` を持たない既存のドキュメントは `multiple_choice` として扱う
+    #[serde(default)]
+    pub question_type: QuestionType,
+    /// 読解 (`passage`) の小問が共有する本文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passage: Option<String>,
+
     /// 生成に使用したAIモデル名（品質追跡用）
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub generated_by: Option<String>,
+
+    /// 公開状態。学習者向けの API は `published` の問題だけを返す
+    #[serde(default)]
+    pub status: QuestionStatus,
+    /// 最後のレビュー (承認・差し戻し) の記録
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewRecord>,
+    /// 小問の dedup キー。重複チェックでキーから既存の問題を引くための索引で、保存前に小問から計算する
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dedup_keys: Vec<String>,
+}
+
+/// 出題形式。いずれも小問ごとに選択肢から1つを選んで解答する
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionType {
+    /// 通常の多肢選択
+    #[default]
+    MultipleChoice,
+    /// 文の組み立て (★)。断片を並べ替え、★ の位置に入る断片を答える
+    Ordering,
+    /// 穴埋め。小問の文に空欄 (`（　　）`) を1つ含む
+    Cloze,
+    /// 読解。問題の `passage` を全小問で共有する
+    Passage,
+}
+
+impl QuestionType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuestionType::MultipleChoice => "multiple_choice",
+            QuestionType::Ordering => "ordering",
+            QuestionType::Cloze => "cloze",
+            QuestionType::Passage => "passage",
+        }
+    }
+}
+
+/// 文の組み立て (★) の小問の内容
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct SentenceOrdering {
+    /// 正しい順に並べた断片。選択肢の value と同じ文字列を使う
+    pub fragments: Vec<String>,
+    /// ★ の位置 (0 始まり)。`answer` はこの位置の断片の選択肢
+    pub star_position: usize,
+}
+
+/// 問題のライフサイクル。
+///
+/// `draft` → `pending_review` → `published` → `retired` の順に進み、差し戻しで `draft` に戻る。
+/// `status` を持たない既存のドキュメントは `published` として扱う。
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionStatus {
+    /// 作成中
+    Draft,
+    /// レビュー待ち (生成・一括取り込みした問題はここから始まる)
+    PendingReview,
+    #[default]
+    Published,
+    /// 出題を終了した
+    Retired,
+}
+
+impl QuestionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuestionStatus::Draft => "draft",
+            QuestionStatus::PendingReview => "pending_review",
+            QuestionStatus::Published => "published",
+            QuestionStatus::Retired => "retired",
+        }
+    }
+
+    /// `self` から `next` へ遷移できるか
+    pub fn can_transition_to(self, next: QuestionStatus) -> bool {
+        use QuestionStatus::*;
+        matches!(
+            (self, next),
+            (Draft, PendingReview)
+                | (PendingReview, Published)
+                | (PendingReview, Draft)
+                | (Published, Retired)
+                | (Retired, PendingReview)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    Approved,
+    Rejected,
+}
+
+/// レビューの記録
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReviewRecord {
+    /// レビューした管理者の user_id
+    pub reviewer: String,
+    pub decision: ReviewDecision,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub reviewed_at: i64,
+}
+
+impl Question {
+    /// 学習者に配信できるか
+    pub fn is_published(&self) -> bool {
+        self.status == QuestionStatus::Published
+    }
 }
 
 // QuestionのDeserializeトレイトの実装を拡張
@@ -47,7 +169,17 @@ impl<'de> Deserialize<'de> for Question {
             #[serde(default)]
             sub_questions: Vec<SubQuestion>,
             #[serde(default)]
+            question_type: QuestionType,
+            #[serde(default)]
+            passage: Option<String>,
+            #[serde(default)]
             generated_by: Option<String>,
+            #[serde(default)]
+            status: QuestionStatus,
+            #[serde(default)]
+            review: Option<ReviewRecord>,
+            #[serde(default)]
+            dedup_keys: Vec<String>,
         }
 
         #[derive(Deserialize)]
@@ -74,7 +206,12 @@ impl<'de> Deserialize<'de> for Question {
             sentence: helper.sentence,
             prerequisites: helper.prerequisites,
             sub_questions: helper.sub_questions,
+            question_type: helper.question_type,
+            passage: helper.passage,
             generated_by: helper.generated_by,
+            status: helper.status,
+            review: helper.review,
+            dedup_keys: helper.dedup_keys,
         })
     }
 }
@@ -94,4 +231,238 @@ pub struct SubQuestion {
     pub prerequisites: Option<String>,
     pub select_answer: Vec<SelectAnswer>,
     pub answer: String,
+
+    /// 正解の解説。回答後に `POST /api/answers` のレスポンスでのみ返す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    /// 選択肢ごとの解説 (選択肢の key -> その選択肢が正しい・誤っている理由)。`explanation` と同じく回答後にのみ返す
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub option_explanations: BTreeMap<String, String>,
+    /// 段階的なヒント (易しい順)。`GET /api/questions/{id}/sub/{sub_id}/hint` で1段ずつ返す
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<Hint>,
+    /// 文の組み立て (`ordering`) の小問のみ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ordering: Option<SentenceOrdering>,
+}
+
+impl SubQuestion {
+    /// 文の組み立ての正しい並びを選択肢の key で返す。断片が選択肢に無い場合は None
+    pub fn correct_order(&self) -> Option<Vec<String>> {
+        self.ordering
+            .as_ref()?
+            .fragments
+            .iter()
+            .map(|fragment| {
+                self.select_answer
+                    .iter()
+                    .find(|sa| sa.value.trim() == fragment.trim())
+                    .map(|sa| sa.key.clone())
+            })
+            .collect()
+    }
+
+    /// 文の組み立てで学習者が並べた選択肢の key (正規キー) を採点する。
+    ///
+    /// 並び全体が正しいかどうかと、★ の位置に置いた選択肢の key を返す。
+    /// 選択肢をちょうど1回ずつ使っていない並びは Err
+    pub fn grade_order(&self, order: &[String]) -> Result<(bool, String), String> {
+        let ordering = self
+            .ordering
+            .as_ref()
+            .ok_or("selected_order は文の組み立ての小問でのみ指定できます")?;
+        let mut keys: Vec<&str> = order.iter().map(String::as_str).collect();
+        let mut expected: Vec<&str> = self
+            .select_answer
+            .iter()
+            .map(|sa| sa.key.as_str())
+            .collect();
+        keys.sort_unstable();
+        expected.sort_unstable();
+        if keys != expected {
+            return Err("selected_order は選択肢をすべて1回ずつ並べてください".to_string());
+        }
+        let star = order
+            .get(ordering.star_position)
+            .cloned()
+            .ok_or("★ の位置が並びの範囲外です")?;
+        let is_correct = self.correct_order().as_deref() == Some(order);
+        Ok((is_correct, star))
+    }
+}
+
+/// 学習者向けの問題。正解 (`answer`) と解説を含まない
+#[derive(Clone, Serialize, Debug)]
+pub struct PublicQuestion {
+    pub id: String,
+    pub level_id: u32,
+    pub level_name: String,
+    pub category_id: Option<String>,
+    pub category_name: String,
+    pub sentence: String,
+    pub prerequisites: Option<String>,
+    pub sub_questions: Vec<PublicSubQuestion>,
+    pub question_type: QuestionType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passage: Option<String>,
+
+    /// `?shuffle=true` で配信したときの選択肢の並び。回答時に `POST /api/answers` へそのまま渡す
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shuffle_token: Option<String>,
+
+    /// `?furigana=` でふりがなが必要なのに読みが分からず付けなかった漢字の並び
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub furigana_unresolved: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PublicSubQuestion {
+    pub id: u32,
+    pub sentence: Option<String>,
+    pub prerequisites: Option<String>,
+    pub select_answer: Vec<SelectAnswer>,
+    /// 文の組み立ての ★ の位置 (0 始まり)。断片の正しい順は返さない
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub star_position: Option<usize>,
+}
+
+impl From<Question> for PublicQuestion {
+    fn from(q: Question) -> Self {
+        Self {
+            id: q.id,
+            level_id: q.level_id,
+            level_name: q.level_name,
+            category_id: q.category_id,
+            category_name: q.category_name,
+            sentence: q.sentence,
+            prerequisites: q.prerequisites,
+            sub_questions: q
+                .sub_questions
+                .into_iter()
+                .map(PublicSubQuestion::from)
+                .collect(),
+            question_type: q.question_type,
+            passage: q.passage,
+            shuffle_token: None,
+            furigana_unresolved: Vec::new(),
+        }
+    }
+}
+
+impl From<SubQuestion> for PublicSubQuestion {
+    fn from(sq: SubQuestion) -> Self {
+        Self {
+            id: sq.id,
+            sentence: sq.sentence,
+            prerequisites: sq.prerequisites,
+            select_answer: sq.select_answer,
+            star_position: sq.ordering.map(|o| o.star_position),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        use QuestionStatus::*;
+        assert!(Draft.can_transition_to(PendingReview));
+        assert!(PendingReview.can_transition_to(Published));
+        assert!(PendingReview.can_transition_to(Draft));
+        assert!(Published.can_transition_to(Retired));
+        assert!(Retired.can_transition_to(PendingReview));
+
+        assert!(!Draft.can_transition_to(Published));
+        assert!(!Published.can_transition_to(Draft));
+        assert!(!Retired.can_transition_to(Published));
+    }
+
+    #[test]
+    fn test_missing_status_is_published() {
+        let question: Question =
+            serde_json::from_str(r#"{"id": "q1", "sub_questions": []}"#).unwrap();
+        assert!(question.is_published());
+
+        let question: Question = serde_json::from_str(
+            r#"{"id": "q1", "sub_questions": [], "status": "pending_review"}"#,
+        )
+        .unwrap();
+        assert_eq!(question.status, QuestionStatus::PendingReview);
+    }
+
+    #[test]
+    fn test_correct_order_maps_fragments_to_keys() {
+        let sub_q = SubQuestion {
+            select_answer: ["読んだ", "本を", "昨日", "ことがある"]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| SelectAnswer {
+                    key: (i + 1).to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            answer: "1".to_string(),
+            ordering: Some(SentenceOrdering {
+                fragments: ["昨日", "本を", "読んだ", "ことがある"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+                star_position: 2,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            sub_q.correct_order(),
+            Some(vec!["3".into(), "2".into(), "1".into(), "4".into()])
+        );
+
+        let missing = SubQuestion {
+            ordering: Some(SentenceOrdering {
+                fragments: vec!["無い".to_string()],
+                star_position: 0,
+            }),
+            ..sub_q.clone()
+        };
+        assert_eq!(missing.correct_order(), None);
+        assert_eq!(SubQuestion::default().correct_order(), None);
+    }
+
+    #[test]
+    fn test_grade_order() {
+        let sub_q = SubQuestion {
+            select_answer: ["読んだ", "本を", "昨日", "ことがある"]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| SelectAnswer {
+                    key: (i + 1).to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            answer: "1".to_string(),
+            ordering: Some(SentenceOrdering {
+                fragments: ["昨日", "本を", "読んだ", "ことがある"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+                star_position: 2,
+            }),
+            ..Default::default()
+        };
+        let order = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            sub_q.grade_order(&order(&["3", "2", "1", "4"])),
+            Ok((true, "1".to_string()))
+        );
+        // ★ の断片が合っていても並び全体が違えば不正解
+        assert_eq!(
+            sub_q.grade_order(&order(&["2", "3", "1", "4"])),
+            Ok((false, "1".to_string()))
+        );
+        assert!(sub_q.grade_order(&order(&["3", "3", "1", "4"])).is_err());
+        assert!(sub_q.grade_order(&order(&["3", "2", "1"])).is_err());
+        assert!(SubQuestion::default().grade_order(&order(&["1"])).is_err());
+    }
 }
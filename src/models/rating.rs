@@ -0,0 +1,185 @@
+//! Elo 方式の難易度・能力推定。
+//!
+//! 小問の難易度 (`item_ratings`) と、ユーザーのレベル×カテゴリごとの能力 (`user_abilities`) を
+//! 同じ尺度の整数レーティングで持つ。初期値はどちらも 0 で、回答のたびに差分を加算する。
+
+use std::collections::HashMap;
+
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use super::question::Question;
+
+/// 適応出題で狙う正答確率
+pub const TARGET_SUCCESS: f64 = 0.7;
+
+/// 小問の難易度 (`item_ratings` コレクション、ドキュメントIDは `{question_id}_{sub_question_id}`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRating {
+    pub question_id: String,
+    pub sub_question_id: u32,
+    pub level_id: u32,
+    pub category_id: String,
+    #[serde(default)]
+    pub rating: i64,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl ItemRating {
+    pub fn doc_id(question_id: &str, sub_question_id: u32) -> String {
+        format!("{}_{}", question_id, sub_question_id)
+    }
+}
+
+/// ユーザーの能力 (`user_abilities` コレクション、ドキュメントIDは `{user_id}_{level_id}_{category_id}`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAbility {
+    pub user_id: String,
+    pub level_id: u32,
+    pub category_id: String,
+    #[serde(default)]
+    pub rating: i64,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl UserAbility {
+    pub fn doc_id(user_id: &str, level_id: u32, category_id: &str) -> String {
+        format!("{}_{}_{}", user_id, level_id, category_id)
+    }
+}
+
+/// 能力 `ability` のユーザーが難易度 `difficulty` の小問に正解する確率
+pub fn expected_score(ability: i64, difficulty: i64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((difficulty - ability) as f64 / 400.0))
+}
+
+/// 回答数が少ないうちは大きく動かし、推定が安定したら小さくする
+fn k_factor(attempts: u32) -> f64 {
+    (40.0 - f64::from(attempts)).max(16.0)
+}
+
+/// 1回の回答によるレーティングの変化量 `(ユーザー, 小問)`
+pub fn rating_deltas(user: (i64, u32), item: (i64, u32), is_correct: bool) -> (i64, i64) {
+    let surprise = f64::from(u8::from(is_correct)) - expected_score(user.0, item.0);
+    (
+        (k_factor(user.1) * surprise).round() as i64,
+        -(k_factor(item.1) * surprise).round() as i64,
+    )
+}
+
+/// 正答確率が [`TARGET_SUCCESS`] になる難易度
+pub fn target_difficulty(ability: i64) -> i64 {
+    ability + (400.0 * (1.0 / TARGET_SUCCESS - 1.0).log10()).round() as i64
+}
+
+/// 問題の難易度 (小問の平均)。評価の無い小問は 0 とみなす
+pub fn question_difficulty(question: &Question, ratings: &HashMap<String, i64>) -> i64 {
+    let count = question.sub_questions.len().max(1) as i64;
+    let sum: i64 = question
+        .sub_questions
+        .iter()
+        .map(|sq| {
+            ratings
+                .get(&ItemRating::doc_id(&question.id, sq.id))
+                .copied()
+                .unwrap_or_default()
+        })
+        .sum();
+    sum / count
+}
+
+/// 目標難易度に近い問題を `limit` の2倍まで候補にし、その中からランダムに `limit` 件選ぶ
+pub fn select_adaptive<R: Rng + ?Sized>(
+    questions: Vec<Question>,
+    ratings: &HashMap<String, i64>,
+    ability: i64,
+    limit: usize,
+    rng: &mut R,
+) -> Vec<Question> {
+    let target = target_difficulty(ability);
+    let mut scored: Vec<(i64, Question)> = questions
+        .into_iter()
+        .map(|q| ((question_difficulty(&q, ratings) - target).abs(), q))
+        .collect();
+    // 同じ距離の問題が毎回同じ順にならないよう、先に混ぜてから安定ソートする
+    scored.shuffle(rng);
+    scored.sort_by_key(|(distance, _)| *distance);
+
+    let mut candidates: Vec<Question> =
+        scored.into_iter().take(limit * 2).map(|(_, q)| q).collect();
+    candidates.shuffle(rng);
+    candidates.truncate(limit);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::models::question::SubQuestion;
+
+    fn question(id: &str) -> Question {
+        Question {
+            id: id.to_string(),
+            sub_questions: vec![SubQuestion {
+                id: 1,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rating_deltas() {
+        assert!((expected_score(0, 0) - 0.5).abs() < 1e-9);
+        // 初回は K=40: 互角で正解するとユーザー +20、小問 -20
+        assert_eq!(rating_deltas((0, 0), (0, 0), true), (20, -20));
+        assert_eq!(rating_deltas((0, 0), (0, 0), false), (-20, 20));
+        // 回答数が多いと K=16 まで下がる
+        assert_eq!(rating_deltas((0, 100), (0, 100), true), (8, -8));
+        // 難しい小問への正解は大きく上がる
+        let (up, _) = rating_deltas((0, 0), (400, 0), true);
+        assert!(up > 35);
+    }
+
+    #[test]
+    fn test_target_difficulty_is_below_ability() {
+        let target = target_difficulty(100);
+        assert_eq!(target, -47);
+        assert!((expected_score(100, target) - TARGET_SUCCESS).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_select_adaptive_prefers_items_near_target() {
+        let questions: Vec<Question> = (0..10).map(|i| question(&format!("q{}", i))).collect();
+        // q{i} の難易度は i * 100
+        let ratings: HashMap<String, i64> = (0..10)
+            .map(|i| (ItemRating::doc_id(&format!("q{}", i), 1), i * 100))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(3);
+        // 能力 947 → 目標難易度 800 付近: 候補は q7〜q9 と q6 (距離の近い4件)
+        let picked = select_adaptive(questions.clone(), &ratings, 947, 2, &mut rng);
+        assert_eq!(picked.len(), 2);
+        for q in &picked {
+            assert!(
+                ["q6", "q7", "q8", "q9"].contains(&q.id.as_str()),
+                "{}",
+                q.id
+            );
+        }
+
+        // 能力 147 → 目標難易度 0 付近: 候補は q0〜q3
+        let picked = select_adaptive(questions, &ratings, 147, 2, &mut rng);
+        for q in &picked {
+            assert!(
+                ["q0", "q1", "q2", "q3"].contains(&q.id.as_str()),
+                "{}",
+                q.id
+            );
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+/// 初期の易しさ係数 (SM-2)
+pub const INITIAL_EASE: f64 = 2.5;
+/// 易しさ係数の下限 (SM-2)
+pub const MIN_EASE: f64 = 1.3;
+
+const SECS_PER_DAY: i64 = 60 * 60 * 24;
+
+/// 復習カード (`review_cards` コレクション)。
+///
+/// ドキュメントIDは `{user_id}_{question_id}_{sub_question_id}` で、不正解になった小問ごとに作成し、
+/// 以降は正誤にかかわらず回答のたびに SM-2 で次回の出題日を更新する。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewCard {
+    pub id: String,
+    pub user_id: String,
+    pub question_id: String,
+    pub sub_question_id: u32,
+    pub level_id: u32,
+    pub category_name: String,
+    pub ease: f64,
+    pub interval_days: u32,
+    /// 連続正解回数
+    pub repetitions: u32,
+    pub lapses: u32,
+    pub due_at: i64,
+    pub last_reviewed_at: i64,
+}
+
+impl ReviewCard {
+    pub fn new(
+        user_id: &str,
+        question_id: &str,
+        sub_question_id: u32,
+        level_id: u32,
+        category_name: &str,
+    ) -> Self {
+        Self {
+            id: format!("{}_{}_{}", user_id, question_id, sub_question_id),
+            user_id: user_id.to_string(),
+            question_id: question_id.to_string(),
+            sub_question_id,
+            level_id,
+            category_name: category_name.to_string(),
+            ease: INITIAL_EASE,
+            interval_days: 0,
+            repetitions: 0,
+            lapses: 0,
+            due_at: 0,
+            last_reviewed_at: 0,
+        }
+    }
+
+    /// 回答結果を反映して次回の出題日を決める。
+    ///
+    /// 選択式のため自己評価の代わりに、正解を品質 4、不正解を品質 1 として扱う。
+    pub fn review(&mut self, is_correct: bool, now: i64) {
+        let quality: f64 = if is_correct { 4.0 } else { 1.0 };
+
+        if is_correct {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval_days) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.interval_days = 1;
+            self.repetitions = 0;
+            self.lapses += 1;
+        }
+
+        let penalty = 5.0 - quality;
+        self.ease = (self.ease + 0.1 - penalty * (0.08 + penalty * 0.02)).max(MIN_EASE);
+        self.due_at = now + i64::from(self.interval_days) * SECS_PER_DAY;
+        self.last_reviewed_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn card() -> ReviewCard {
+        ReviewCard::new("u1", "q1", 1, 3, "文法")
+    }
+
+    #[test]
+    fn test_sm2_intervals_grow_with_correct_answers() {
+        let mut card = card();
+        card.review(false, NOW);
+        assert_eq!(card.interval_days, 1);
+        assert_eq!(card.lapses, 1);
+        assert!((card.ease - 1.96).abs() < 1e-9);
+
+        let intervals: Vec<u32> = (0..4)
+            .map(|_| {
+                card.review(true, NOW);
+                card.interval_days
+            })
+            .collect();
+        // 品質4では易しさ係数は変わらない
+        assert_eq!(intervals, vec![1, 6, 12, 24]);
+        assert_eq!(card.due_at, NOW + 24 * SECS_PER_DAY);
+    }
+
+    #[test]
+    fn test_sm2_lapse_resets_and_ease_has_floor() {
+        let mut card = card();
+        for _ in 0..3 {
+            card.review(true, NOW);
+        }
+        card.review(false, NOW);
+        assert_eq!(card.repetitions, 0);
+        assert_eq!(card.interval_days, 1);
+
+        for _ in 0..5 {
+            card.review(false, NOW);
+        }
+        assert_eq!(card.ease, MIN_EASE);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::question::Question;
+
+/// 編集前から存在した問題を初めて変更するときに記録する版の編集者
+pub const BASELINE_EDITOR: &str = "system";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionAction {
+    /// 履歴導入前の状態 (初回編集時に自動で記録)
+    Baseline,
+    Create,
+    Update,
+    Rollback,
+    Delete,
+}
+
+/// 問題の改訂履歴 (`question_revisions` コレクション、ドキュメントIDは `{question_id}_{revision}`)。
+///
+/// 追記のみで、既存の版は更新・削除しない。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionRevision {
+    pub id: String,
+    pub question_id: String,
+    /// 1 から始まる版番号
+    pub revision: u32,
+    pub action: RevisionAction,
+    /// 編集した管理者の user_id
+    pub editor: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub created_at: i64,
+    /// この版の問題全体。削除の版では削除直前の内容
+    pub snapshot: Question,
+}
+
+impl QuestionRevision {
+    pub fn doc_id(question_id: &str, revision: u32) -> String {
+        format!("{}_{}", question_id, revision)
+    }
+}
+
+/// フィールド単位の差分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// 例: `sub_questions[id=1].select_answer[key=3].value`
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// 2つの版の差分をフィールド単位で返す。
+///
+/// 配列の要素は `id` (小問) / `key` (選択肢) で対応付けるため、並べ替えだけでは差分にならない。
+/// 小問から計算する `dedup_keys` は差分に含めない。
+pub fn diff_questions(before: Option<&Question>, after: &Question) -> Vec<FieldChange> {
+    let mut old = BTreeMap::new();
+    if let Some(before) = before {
+        flatten(
+            "",
+            &serde_json::to_value(before).unwrap_or_default(),
+            &mut old,
+        );
+    }
+    let mut new = BTreeMap::new();
+    flatten(
+        "",
+        &serde_json::to_value(after).unwrap_or_default(),
+        &mut new,
+    );
+
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter(|path| !path.starts_with("dedup_keys") && old.get(*path) != new.get(*path))
+        .map(|path| FieldChange {
+            path: path.clone(),
+            before: old.get(path).cloned(),
+            after: new.get(path).cloned(),
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, child, out);
+            }
+        }
+        Value::Array(items) if items.iter().all(|item| element_key(item).is_some()) => {
+            for item in items {
+                let key = element_key(item).unwrap_or_default();
+                flatten(&format!("{}[{}]", prefix, key), item, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// 配列要素の識別子 (`id=1` / `key=3`)
+fn element_key(item: &Value) -> Option<String> {
+    ["id", "key"].iter().find_map(|field| {
+        item.get(field).map(|v| match v {
+            Value::String(s) => format!("{}={}", field, s),
+            v => format!("{}={}", field, v),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::question::{SelectAnswer, SubQuestion};
+
+    fn question() -> Question {
+        Question {
+            id: "q1".to_string(),
+            level_id: 3,
+            sentence: "問題".to_string(),
+            sub_questions: vec![SubQuestion {
+                id: 1,
+                sentence: Some("文".to_string()),
+                select_answer: ["あ", "い", "う", "え"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| SelectAnswer {
+                        key: (i + 1).to_string(),
+                        value: v.to_string(),
+                    })
+                    .collect(),
+                answer: "1".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_option_and_answer_changes() {
+        let before = question();
+        let mut after = before.clone();
+        after.sub_questions[0].select_answer[2].value = "お".to_string();
+        after.sub_questions[0].answer = "3".to_string();
+
+        let changes = diff_questions(Some(&before), &after);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "sub_questions[id=1].answer",
+                "sub_questions[id=1].select_answer[key=3].value",
+            ]
+        );
+        assert_eq!(changes[1].before, Some(json!("う")));
+        assert_eq!(changes[1].after, Some(json!("お")));
+    }
+
+    #[test]
+    fn test_diff_ignores_reordering_and_tracks_added_sub_questions() {
+        let before = question();
+        let mut after = before.clone();
+        after.sub_questions[0].select_answer.reverse();
+        assert!(diff_questions(Some(&before), &after).is_empty());
+
+        let mut added = after.sub_questions[0].clone();
+        added.id = 2;
+        after.sub_questions.push(added);
+        let changes = diff_questions(Some(&before), &after);
+        assert!(
+            changes
+                .iter()
+                .all(|c| c.path.starts_with("sub_questions[id=2]") && c.before.is_none())
+        );
+    }
+
+    #[test]
+    fn test_diff_without_previous_lists_all_fields() {
+        let changes = diff_questions(None, &question());
+        assert!(changes.iter().any(|c| c.path == "sentence"));
+        assert!(changes.iter().all(|c| c.before.is_none()));
+    }
+}
@@ -0,0 +1,3235 @@
+//! `build_app` で組み立てたルーターをインメモリストア上で動かす E2E テスト
+
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Method, Request, StatusCode, header},
+};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+use backend::{
+    api::answers::UserAnswer,
+    app::{AppConfig, build_app},
+    common::{
+        database::{Database, StoreQuery},
+        dedup::question_dedup_keys,
+        llm::GeminiClient,
+    },
+    models::{
+        claim::Claims,
+        exam::ExamSession,
+        hint::{Hint, HintKind},
+        meta::CatValue,
+        question::{Question, QuestionStatus, SelectAnswer, SubQuestion},
+        rating::{ItemRating, UserAbility},
+        review::ReviewCard,
+    },
+};
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+fn init_env() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        // SAFETY: 各テストがスレッドを起動する前に一度だけ設定する
+        unsafe {
+            std::env::set_var("JWT_SECRET", "test-secret");
+            std::env::set_var("ADMIN_EMAILS", ADMIN_EMAIL);
+        }
+    });
+}
+
+fn setup() -> (Router, Arc<Database>) {
+    init_env();
+    let db = Arc::new(Database::memory());
+    let app = build_app(db.clone(), AppConfig::new("http://localhost:3000"));
+    (app, db)
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+    /// JSON 以外のレスポンス (書き出し等) の本文
+    raw: Vec<u8>,
+}
+
+async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+    token: Option<&str>,
+) -> TestResponse {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-forwarded-for", "203.0.113.1");
+    if let Some(token) = token {
+        builder = builder.header(header::COOKIE, format!("access_token={}", token));
+    }
+    let request = match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+    into_test_response(app, request).await
+}
+
+/// JSON 以外の本文 (CSV 等) を送る
+async fn send_text(
+    app: &Router,
+    uri: &str,
+    content_type: &str,
+    body: &str,
+    token: &str,
+) -> TestResponse {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("x-forwarded-for", "203.0.113.1")
+        .header(header::COOKIE, format!("access_token={}", token))
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    into_test_response(app, request).await
+}
+
+async fn into_test_response(app: &Router, request: Request<Body>) -> TestResponse {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    TestResponse {
+        status,
+        headers,
+        body,
+        raw: bytes.to_vec(),
+    }
+}
+
+fn user_token(user_id: &str) -> String {
+    Claims::new(
+        user_id.to_string(),
+        format!("{}@example.com", user_id),
+        None,
+    )
+    .to_token()
+    .unwrap()
+}
+
+fn admin_token() -> String {
+    Claims::new(
+        "admin".to_string(),
+        ADMIN_EMAIL.to_string(),
+        Some("admin".to_string()),
+    )
+    .to_token()
+    .unwrap()
+}
+
+fn sample_question(id: &str) -> Question {
+    Question {
+        id: id.to_string(),
+        level_id: 3,
+        level_name: "N3".to_string(),
+        category_id: Some("8".to_string()),
+        category_name: "文法".to_string(),
+        sentence: "次の文の（　　）に入れるのに最もよいものを選びなさい。".to_string(),
+        prerequisites: None,
+        sub_questions: vec![SubQuestion {
+            id: 1,
+            sentence: Some("雨が降った（　　）、試合は中止になった。".to_string()),
+            prerequisites: None,
+            select_answer: ["ので", "のに", "けど", "から"]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| SelectAnswer {
+                    key: (i + 1).to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            answer: "1".to_string(),
+            explanation: Some("理由を表す「ので」が入る。".to_string()),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// 保存時と同じく dedup キーの索引を付けて登録する
+async fn seed_question(db: &Database, id: &str) {
+    let mut question = sample_question(id);
+    question.dedup_keys = question_dedup_keys(&question);
+    db.create("questions", id, question).await.unwrap();
+}
+
+fn cookie_value(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("access_token="))
+        .map(|v| v.to_string())
+}
+
+#[tokio::test]
+async fn test_health_has_security_headers() {
+    let (app, _db) = setup();
+    let res = send(&app, Method::GET, "/api/public/health", None, None).await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["health"], "ok");
+    assert_eq!(res.headers["x-frame-options"], "DENY");
+    assert_eq!(res.headers["x-content-type-options"], "nosniff");
+    assert!(res.headers.contains_key("strict-transport-security"));
+}
+
+#[tokio::test]
+async fn test_signup_signin_cookie_flow() {
+    let (app, _db) = setup();
+    let credentials = json!({"email": "user@example.com", "password": "password123"});
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/signup",
+        Some(json!({"email": "invalid", "password": "password123"})),
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.body["code"], "validation_error");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/signup",
+        Some(credentials.clone()),
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/signup",
+        Some(credentials.clone()),
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.body["code"], "conflict");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/signin",
+        Some(json!({"email": "user@example.com", "password": "wrong-password"})),
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.body["code"], "unauthorized");
+    assert!(cookie_value(&res.headers).is_none());
+
+    let res = send(&app, Method::POST, "/api/signin", Some(credentials), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["email"], "user@example.com");
+    assert!(res.body["data"]["role"].is_null());
+    let cookie = cookie_value(&res.headers).expect("access_token cookie");
+    assert!(cookie.contains("HttpOnly"));
+    assert!(cookie.contains("Path=/api"));
+
+    let token = cookie
+        .trim_start_matches("access_token=")
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    let res = send(&app, Method::GET, "/api/auth/me", None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["email"], "user@example.com");
+
+    let res = send(&app, Method::POST, "/api/auth/logout", None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(cookie_value(&res.headers).unwrap().contains("Max-Age=0"));
+}
+
+#[tokio::test]
+async fn test_signin_admin_role() {
+    let (app, _db) = setup();
+    let credentials = json!({"email": ADMIN_EMAIL, "password": "password123"});
+    send(
+        &app,
+        Method::POST,
+        "/api/signup",
+        Some(credentials.clone()),
+        None,
+    )
+    .await;
+
+    let res = send(&app, Method::POST, "/api/signin", Some(credentials), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["role"], "admin");
+}
+
+#[tokio::test]
+async fn test_auth_rate_limit() {
+    let (app, _db) = setup();
+    // バリデーションで即時に返るリクエストを使い、補充より速くバーストを使い切る
+    let body = json!({"email": "invalid", "password": "password123"});
+
+    let mut statuses = Vec::new();
+    for _ in 0..6 {
+        let res = send(&app, Method::POST, "/api/signup", Some(body.clone()), None).await;
+        statuses.push(res.status);
+    }
+    assert_eq!(statuses[..5], [StatusCode::BAD_REQUEST; 5]);
+    assert_eq!(statuses[5], StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_claims_rejections() {
+    let (app, _db) = setup();
+
+    let res = send(&app, Method::GET, "/api/auth/me", None, None).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    let res = send(&app, Method::GET, "/api/auth/me", None, Some("not-a-jwt")).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    let request = Request::builder()
+        .uri("/api/private/health")
+        .header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", user_token("u1")),
+        )
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/stats",
+        None,
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.body["code"], "forbidden");
+
+    let res = send(&app, Method::GET, "/api/admin/stats", None, None).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.body["code"], "unauthorized");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/stats",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_record_answer_updates_stats_and_mistakes() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "2"})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["is_correct"], false);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let mistakes = res.body["data"].as_array().unwrap();
+    assert_eq!(mistakes.len(), 1);
+    assert_eq!(mistakes[0]["question_id"], "q1");
+    assert_eq!(mistakes[0]["selected_answer"], "2");
+
+    // 正解すると間違えた記録から外れる
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "1"})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"]["is_correct"], true);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "missing", "sub_question_id": 1, "selected_answer": "1"})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(res.body["code"], "not_found");
+
+    let res = send(&app, Method::GET, "/api/users/me/stats", None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let stats = &res.body["data"];
+    assert_eq!(stats["total_answers"], 2);
+    assert_eq!(stats["total_correct"], 1);
+    assert_eq!(stats["levels"][0]["level_name"], "N3");
+    assert_eq!(stats["levels"][0]["categories"][0]["category_name"], "文法");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"], json!([]));
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/history",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"], json!([]));
+
+    // 他ユーザーの統計には影響しない
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/stats",
+        None,
+        Some(&user_token("u2")),
+    )
+    .await;
+    assert_eq!(res.body["data"]["total_answers"], 0);
+}
+
+#[tokio::test]
+async fn test_record_answer_rejects_unpublished_question() {
+    let (app, db) = setup();
+    let mut question = sample_question("q1");
+    question.status = QuestionStatus::Draft;
+    db.create("questions", "q1", question).await.unwrap();
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "1"})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert!(res.body["data"]["correct_answer"].is_null());
+    // 回答は記録されず、統計も復習カードも作られない
+    let answers: Vec<Value> = db.read_all("user_answers").await.unwrap();
+    assert!(answers.is_empty());
+    let stats: Vec<Value> = db.read_all("user_stats").await.unwrap();
+    assert!(stats.is_empty());
+    let cards: Vec<Value> = db.read_all("review_cards").await.unwrap();
+    assert!(cards.is_empty());
+}
+
+#[tokio::test]
+async fn test_delivery_hides_answers_and_grading_reveals_them() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+
+    for uri in ["/api/questions/q1", "/api/level/3/categories/8/questions"] {
+        let res = send(&app, Method::GET, uri, None, None).await;
+        assert_eq!(res.status, StatusCode::OK);
+        let body = res.body["data"].to_string();
+        assert!(!body.contains("\"answer\""), "{} leaked answer", uri);
+        assert!(!body.contains("explanation"), "{} leaked explanation", uri);
+    }
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1",
+        None,
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert!(res.body["data"]["sub_questions"][0]["answer"].is_null());
+
+    // Admin は全フィールドを参照できる
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.body["data"]["sub_questions"][0]["answer"], "1");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "3"})),
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.body["data"]["is_correct"], false);
+    assert_eq!(res.body["data"]["correct_answer"], "1");
+    assert_eq!(
+        res.body["data"]["explanation"],
+        "理由を表す「ので」が入る。"
+    );
+}
+
+#[tokio::test]
+async fn test_shuffled_delivery_grades_by_canonical_key() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    seed_question(&db, "q2").await;
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1?shuffle=true",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let question = &res.body["data"];
+    let shuffle_token = question["shuffle_token"].as_str().unwrap().to_string();
+    let options = question["sub_questions"][0]["select_answer"]
+        .as_array()
+        .unwrap();
+    let keys: Vec<&str> = options.iter().map(|o| o["key"].as_str().unwrap()).collect();
+    assert_eq!(keys, vec!["1", "2", "3", "4"]);
+    let key_of = |value: &str| {
+        options
+            .iter()
+            .find(|o| o["value"] == value)
+            .unwrap()
+            .get("key")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let correct_key = key_of("ので");
+    let wrong_key = key_of("けど");
+
+    // 表示キーで回答すると正規キーに戻して採点される
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": correct_key, "shuffle_token": shuffle_token})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["is_correct"], true);
+    assert_eq!(res.body["data"]["correct_answer"], correct_key.as_str());
+
+    // 誤答は正規キーで保存される
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": wrong_key, "shuffle_token": shuffle_token})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"]["is_correct"], false);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["selected_answer"], "3");
+    assert_eq!(res.body["data"][0]["correct_answer"], "1");
+
+    // 別の問題のトークン・改ざんされたトークンは拒否する
+    for (question_id, bad_token) in [
+        ("q2", shuffle_token.clone()),
+        ("q1", format!("{}x", shuffle_token)),
+    ] {
+        let res = send(
+            &app,
+            Method::POST,
+            "/api/answers",
+            Some(json!({"question_id": question_id, "sub_question_id": 1, "selected_answer": "1", "shuffle_token": bad_token})),
+            Some(&token),
+        )
+        .await;
+        assert_eq!(res.status, StatusCode::BAD_REQUEST);
+        assert_eq!(res.body["code"], "validation_error");
+    }
+
+    // 一覧でも問題ごとにトークンが付く
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/level/3/categories/8/questions?shuffle=true",
+        None,
+        None,
+    )
+    .await;
+    let questions = res.body["data"].as_array().unwrap();
+    assert_eq!(questions.len(), 2);
+    assert!(questions.iter().all(|q| q["shuffle_token"].is_string()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_answers_do_not_lose_stats() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let token = user_token("u1");
+
+    let handles: Vec<_> = (0..20)
+        .map(|i| {
+            let app = app.clone();
+            let token = token.clone();
+            let selected = if i % 4 == 0 { "1" } else { "2" };
+            tokio::spawn(async move {
+                send(
+                    &app,
+                    Method::POST,
+                    "/api/answers",
+                    Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": selected})),
+                    Some(&token),
+                )
+                .await
+                .status
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), StatusCode::OK);
+    }
+
+    let res = send(&app, Method::GET, "/api/users/me/stats", None, Some(&token)).await;
+    let stats = &res.body["data"];
+    assert_eq!(stats["total_answers"], 20);
+    assert_eq!(stats["total_correct"], 5);
+    assert_eq!(stats["levels"][0]["total"], 20);
+    assert_eq!(stats["levels"][0]["categories"][0]["total"], 20);
+    assert_eq!(stats["levels"][0]["categories"][0]["correct"], 5);
+}
+
+#[tokio::test]
+async fn test_cursor_pagination() {
+    let (app, db) = setup();
+    let token = user_token("u1");
+    for id in ["q1", "q2", "q3"] {
+        seed_question(&db, id).await;
+        answer_wrong(&app, id, &token).await;
+        send(
+            &app,
+            Method::POST,
+            &format!("/api/questions/{}/report", id),
+            None,
+            Some(&token),
+        )
+        .await;
+    }
+
+    // mistakes: 2件ずつ、カーソルを辿って全件を重複なく取得できる
+    let mut seen = Vec::new();
+    let mut uri = "/api/users/me/mistakes?limit=2".to_string();
+    loop {
+        let res = send(&app, Method::GET, &uri, None, Some(&token)).await;
+        assert_eq!(res.status, StatusCode::OK);
+        let items = res.body["data"].as_array().unwrap();
+        assert!(items.len() <= 2);
+        seen.extend(items.iter().map(|a| a["question_id"].clone()));
+        match res.body["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/api/users/me/mistakes?limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+    seen.sort_by_key(|v| v.as_str().unwrap().to_string());
+    assert_eq!(seen, vec![json!("q1"), json!("q2"), json!("q3")]);
+
+    // 問題一覧: page_size 指定時はID順ページング
+    let uri = "/api/level/3/categories/8/questions?page_size=2";
+    let res = send(&app, Method::GET, uri, None, None).await;
+    assert_eq!(res.body["data"][1]["id"], "q2");
+    let cursor = res.body["next_cursor"].as_str().unwrap().to_string();
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("{}&cursor={}", uri, cursor),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(res.body["data"][0]["id"], "q3");
+    assert!(res.body["next_cursor"].is_null());
+
+    // 報告一覧
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/reports?limit=2",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 2);
+    let cursor = res.body["next_cursor"].as_str().unwrap().to_string();
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("/api/admin/reports?limit=2&cursor={}", cursor),
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["question_id"], "q3");
+    assert!(res.body["next_cursor"].is_null());
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/history?cursor=not-a-cursor",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.body["code"], "validation_error");
+}
+
+#[tokio::test]
+async fn test_mock_exam_flow() {
+    let (app, db) = setup();
+    for (id, name) in [(2, "漢字読み"), (8, "文法")] {
+        let category = CatValue {
+            level_id: 3,
+            id,
+            name: name.to_string(),
+            reten: None,
+        };
+        db.create("categories", &format!("c{}", id), category)
+            .await
+            .unwrap();
+    }
+    seed_question(&db, "g1").await;
+    let mut vocab = sample_question("v1");
+    vocab.category_id = Some("2".to_string());
+    vocab.category_name = "漢字読み".to_string();
+    db.create("questions", "v1", vocab).await.unwrap();
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/exams",
+        Some(json!({"level_id": 9})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/exams",
+        Some(json!({"level_id": 3})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let exam = &res.body["data"];
+    let exam_id = exam["id"].as_str().unwrap().to_string();
+    assert!(!exam.to_string().contains("\"answer\""));
+    // 出題の無い聴解は含めない
+    let sections: Vec<&str> = exam["sections"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["key"].as_str().unwrap())
+        .collect();
+    assert_eq!(sections, vec!["vocabulary", "grammar_reading"]);
+    assert_eq!(exam["sections"][0]["time_limit_secs"], 1800);
+    assert_eq!(exam["sections"][0]["question_count"], 1);
+    // 問題は科目ごとに取得するまで返さない
+    assert!(exam["sections"][0]["questions"].is_null());
+    assert!(exam["sections"][0]["started_at"].is_null());
+
+    let answer = |question_id: &str, selected: &str| json!({"question_id": question_id, "sub_question_id": 1, "selected_answer": selected});
+    let answers_uri = format!("/api/exams/{}/answers", exam_id);
+    let section_uri = |index: usize| format!("/api/exams/{}/sections/{}", exam_id, index);
+
+    // 取得していない科目には回答できない
+    let res = send(
+        &app,
+        Method::POST,
+        &answers_uri,
+        Some(answer("v1", "1")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = send(&app, Method::GET, &section_uri(0), None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let section = &res.body["data"];
+    assert_eq!(section["key"], "vocabulary");
+    assert_eq!(section["questions"][0]["id"], "v1");
+    assert!(!section.to_string().contains("\"answer\""));
+    assert!(section["started_at"].is_i64());
+    assert_eq!(section["remaining_secs"], 1800);
+
+    let res = send(
+        &app,
+        Method::POST,
+        &answers_uri,
+        Some(answer("v1", "1")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["section"], "vocabulary");
+    assert!(res.body["data"]["is_correct"].is_null());
+    // 再取得しても制限時間は最初の取得から数える
+    let res = send(&app, Method::GET, &section_uri(0), None, Some(&token)).await;
+    assert_eq!(res.body["data"]["started_at"], section["started_at"]);
+    assert_eq!(res.body["data"]["answers"].as_array().unwrap().len(), 1);
+
+    let res = send(&app, Method::GET, &section_uri(1), None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["questions"][0]["id"], "g1");
+    let res = send(
+        &app,
+        Method::POST,
+        &answers_uri,
+        Some(answer("g1", "2")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(&app, Method::GET, &section_uri(2), None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    // 次の科目を始めたら前の科目には戻れない
+    let res = send(
+        &app,
+        Method::POST,
+        &answers_uri,
+        Some(answer("v1", "2")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = send(&app, Method::GET, &section_uri(0), None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    // 他人の模試は見えない
+    let exam_uri = format!("/api/exams/{}", exam_id);
+    let res = send(&app, Method::GET, &exam_uri, None, Some(&user_token("u2"))).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    let res = send(&app, Method::GET, &exam_uri, None, Some(&token)).await;
+    assert_eq!(res.body["data"]["answers"].as_array().unwrap().len(), 2);
+
+    let finish_uri = format!("/api/exams/{}/finish", exam_id);
+    let res = send(&app, Method::POST, &finish_uri, None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let result = &res.body["data"];
+    assert_eq!(result["sections"][0]["correct"], 1);
+    assert_eq!(result["sections"][1]["correct"], 0);
+    // 言語知識 1/2 → 30点。読解・聴解は出題なしで合否判定から除く
+    assert_eq!(result["divisions"][0]["scaled"], 30);
+    assert!(result["divisions"][1]["scaled"].is_null());
+    assert_eq!(result["max_score"], 60);
+    assert_eq!(result["pass_mark"], 32);
+    assert_eq!(result["passed"], false);
+
+    let res = send(&app, Method::POST, &finish_uri, None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    let res = send(
+        &app,
+        Method::POST,
+        &answers_uri,
+        Some(answer("g1", "1")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // 終了後は正解を含めて返す
+    let res = send(&app, Method::GET, &exam_uri, None, Some(&token)).await;
+    assert_eq!(
+        res.body["data"]["sections"][0]["questions"][0]["sub_questions"][0]["answer"],
+        "1"
+    );
+
+    let res = send(&app, Method::GET, "/api/users/me/exams", None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(res.body["data"][0]["result"]["total_score"], 30);
+
+    // 取得してから制限時間を過ぎた科目には回答できず、取得もできない
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/exams",
+        Some(json!({"level_id": 3})),
+        Some(&token),
+    )
+    .await;
+    let exam_id = res.body["data"]["id"].as_str().unwrap().to_string();
+    let section_uri = format!("/api/exams/{}/sections/0", exam_id);
+    let res = send(&app, Method::GET, &section_uri, None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    // 制限時間 (30分) と猶予が過ぎるまで待ったことにする
+    let mut session: ExamSession = db.read("exams", &exam_id).await.unwrap().unwrap();
+    let started_at = session.sections[0].started_at.unwrap();
+    session.sections[0].started_at = Some(started_at - 1800 - 60);
+    db.update("exams", &exam_id, session).await.unwrap();
+    let res = send(
+        &app,
+        Method::POST,
+        &format!("/api/exams/{}/answers", exam_id),
+        Some(answer("v1", "1")),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.body["code"], "validation_error");
+    let res = send(&app, Method::GET, &section_uri, None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_review_queue_follows_sm2_schedule() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    seed_question(&db, "q2").await;
+    let token = user_token("u1");
+    let answer = |question_id: &str, selected: &str| json!({"question_id": question_id, "sub_question_id": 1, "selected_answer": selected});
+
+    // 正解のみの問題にはカードを作らない
+    send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer("q2", "1")),
+        Some(&token),
+    )
+    .await;
+    assert!(
+        db.read::<ReviewCard>("review_cards", "u1_q2_1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer("q1", "2")),
+        Some(&token),
+    )
+    .await;
+    let card: ReviewCard = db.read("review_cards", "u1_q1_1").await.unwrap().unwrap();
+    assert_eq!(card.interval_days, 1);
+    assert_eq!(card.lapses, 1);
+
+    // 不正解直後は翌日出題なので、UTC の今日の復習には出ない
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/review",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 0);
+
+    let mut overdue = card.clone();
+    overdue.due_at -= 2 * 24 * 3600;
+    db.update("review_cards", &overdue.id, &overdue)
+        .await
+        .unwrap();
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/review",
+        None,
+        Some(&token),
+    )
+    .await;
+    let due = res.body["data"].as_array().unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0]["question_id"], "q1");
+    assert!(res.body["next_cursor"].is_null());
+
+    // 正解すると次回は1日後、続けて正解すると6日後
+    send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer("q1", "1")),
+        Some(&token),
+    )
+    .await;
+    send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer("q1", "1")),
+        Some(&token),
+    )
+    .await;
+    let card: ReviewCard = db.read("review_cards", "u1_q1_1").await.unwrap().unwrap();
+    assert_eq!(card.repetitions, 2);
+    assert_eq!(card.interval_days, 6);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/review",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 0);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/review?tz_offset=1000",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_adaptive_selection_uses_elo_ratings() {
+    let (app, db) = setup();
+    for i in 0..10 {
+        let id = format!("q{}", i);
+        seed_question(&db, &id).await;
+        let rating = ItemRating {
+            question_id: id.clone(),
+            sub_question_id: 1,
+            level_id: 3,
+            category_id: "8".to_string(),
+            rating: i * 100,
+            attempts: 30,
+        };
+        db.create("item_ratings", &ItemRating::doc_id(&id, 1), rating)
+            .await
+            .unwrap();
+    }
+    let ability = UserAbility {
+        user_id: "u1".to_string(),
+        level_id: 3,
+        category_id: "8".to_string(),
+        rating: 947,
+        attempts: 50,
+    };
+    db.create("user_abilities", "u1_3_8", ability)
+        .await
+        .unwrap();
+    let token = user_token("u1");
+
+    // 能力 947 → 正答率70%の難易度は 800。近い4問 (q6〜q9) から選ばれる
+    for _ in 0..5 {
+        let res = send(
+            &app,
+            Method::GET,
+            "/api/level/3/categories/8/questions?mode=adaptive&limit=2",
+            None,
+            Some(&token),
+        )
+        .await;
+        assert_eq!(res.status, StatusCode::OK);
+        let ids: Vec<&str> = res.body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|q| q["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(
+            ids.iter().all(|id| ["q6", "q7", "q8", "q9"].contains(id)),
+            "{:?}",
+            ids
+        );
+    }
+
+    // 未ログインは能力 0 → 目標難易度 -147 に近い q0〜q3
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/level/3/categories/8/questions?mode=adaptive&limit=2",
+        None,
+        None,
+    )
+    .await;
+    for q in res.body["data"].as_array().unwrap() {
+        assert!(["q0", "q1", "q2", "q3"].contains(&q["id"].as_str().unwrap()));
+    }
+
+    // 回答で能力と難易度が更新される (初回の小問は K=40、互角の期待値 0.5)
+    seed_question(&db, "fresh").await;
+    send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "fresh", "sub_question_id": 1, "selected_answer": "2"})),
+        Some(&user_token("u2")),
+    )
+    .await;
+    let item: ItemRating = db.read("item_ratings", "fresh_1").await.unwrap().unwrap();
+    assert_eq!((item.rating, item.attempts, item.level_id), (20, 1, 3));
+    let ability: UserAbility = db.read("user_abilities", "u2_3_8").await.unwrap().unwrap();
+    assert_eq!((ability.rating, ability.attempts), (-20, 1));
+}
+
+#[tokio::test]
+async fn test_admin_question_create_update_validation() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    let mut new_question = json!(sample_question(""));
+    new_question["sub_questions"][0]["select_answer"] = json!([
+        {"key": "1", "value": "ために"},
+        {"key": "2", "value": "ように"},
+        {"key": "3", "value": "ことに"},
+        {"key": "4", "value": "わけに"},
+    ]);
+    new_question.as_object_mut().unwrap().remove("id");
+
+    // Admin 以外は作成できない
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(new_question.clone()),
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(new_question.clone()),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let id = res.body["data"]["id"].as_str().unwrap().to_string();
+    assert!(!id.is_empty());
+    assert!(
+        db.read::<Question>("questions", &id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    // 品質監視と同じ規則で不正な小問を拒否する
+    let mut malformed = new_question.clone();
+    malformed["sub_questions"][0]["select_answer"][3]["value"] = json!(" ");
+    malformed["sub_questions"][0]["answer"] = json!("5");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(malformed),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let message = res.body["error"].as_str().unwrap();
+    assert!(message.contains("正解キー不在"), "{}", message);
+    assert!(message.contains("空選択肢1個"), "{}", message);
+
+    // 既存の小問と dedup キーが衝突する作成は拒否する
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(json!(sample_question("q2"))),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert!(res.body["error"].as_str().unwrap().contains("q1"));
+
+    // PUT: 選択肢の誤字だけを直して置き換える (自分自身とは衝突しない)
+    let mut fixed = sample_question("ignored");
+    fixed.sub_questions[0].select_answer[2].value = "けれど".to_string();
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/questions/q1",
+        Some(json!(fixed)),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["id"], "q1");
+    let saved: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(saved.sub_questions[0].select_answer[2].value, "けれど");
+    assert!(
+        db.read::<Question>("questions", "ignored")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // PATCH: 指定したフィールドだけを更新する
+    let res = send(
+        &app,
+        Method::PATCH,
+        "/api/admin/questions/q1",
+        Some(json!({"sentence": "正しいものを選びなさい。"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let saved: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(saved.sentence, "正しいものを選びなさい。");
+    assert_eq!(saved.sub_questions[0].select_answer[2].value, "けれど");
+
+    // 更新後の内容も検証する
+    let res = send(
+        &app,
+        Method::PATCH,
+        "/api/admin/questions/q1",
+        Some(json!({"sub_questions": [{"id": 1, "sentence": "文", "select_answer": [], "answer": "1"}]})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(res.body["error"].as_str().unwrap().contains("選択肢0個"));
+
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/questions/missing",
+        Some(json!(sample_question("missing"))),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_reindex_dedup_keys_for_legacy_questions() {
+    let (app, db) = setup();
+    let admin = admin_token();
+    // dedup_keys を持たない既存のドキュメント
+    db.create("questions", "q1", sample_question("q1"))
+        .await
+        .unwrap();
+    seed_question(&db, "q2").await;
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/reindex-dedup-keys?dry_run=true",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["changed"], 1);
+    assert_eq!(res.body["data"]["committed"], 0);
+    assert_eq!(res.body["data"]["question_ids"], json!(["q1"]));
+    let stored: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert!(stored.dedup_keys.is_empty());
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/reindex-dedup-keys",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["committed"], 1);
+    let stored: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(
+        stored.dedup_keys,
+        question_dedup_keys(&sample_question("q1"))
+    );
+    assert_eq!(
+        stored.sub_questions[0].explanation.as_deref(),
+        Some("理由を表す「ので」が入る。")
+    );
+
+    // 索引を付けた問題とキーで照合する
+    let mut duplicate = sample_question("q3");
+    duplicate.sub_questions[0].select_answer.reverse();
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(json!(duplicate)),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert!(
+        res.body["error"]
+            .as_str()
+            .unwrap()
+            .contains("既存の問題と重複しています: q1 (小問1)"),
+        "{}",
+        res.body
+    );
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/reindex-dedup-keys",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["changed"], 0);
+}
+
+#[tokio::test]
+async fn test_question_revisions_and_rollback() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    // 履歴の無い問題を初めて編集すると、編集前の内容が baseline として残る
+    let mut fixed = sample_question("q1");
+    fixed.sub_questions[0].select_answer[2].value = "けれど".to_string();
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/questions/q1?reason=typo",
+        Some(json!(fixed)),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q1/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let revisions = res.body["data"].as_array().unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0]["revision"], 2);
+    assert_eq!(revisions[0]["action"], "update");
+    assert_eq!(revisions[0]["editor"], "admin");
+    assert_eq!(revisions[0]["reason"], "typo");
+    assert_eq!(
+        revisions[0]["changes"],
+        json!([{
+            "path": "sub_questions[id=1].select_answer[key=3].value",
+            "before": "けど",
+            "after": "けれど",
+        }])
+    );
+    assert_eq!(revisions[1]["action"], "baseline");
+    assert_eq!(revisions[1]["editor"], "system");
+
+    // 版1に戻す。ページ末尾の版の差分も直前の版と比較する
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/q1/revisions/1/rollback",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["revision"], 3);
+    let saved: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(saved.sub_questions[0].select_answer[2].value, "けど");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q1/revisions?limit=1",
+        None,
+        Some(&admin),
+    )
+    .await;
+    let latest = &res.body["data"][0];
+    assert_eq!(latest["action"], "rollback");
+    assert_eq!(latest["reason"], "第1版に戻す");
+    assert_eq!(latest["changes"][0]["after"], "けど");
+    assert!(res.body["next_cursor"].is_string());
+
+    // 削除しても履歴は残り、削除前の版に戻せる
+    let res = send(
+        &app,
+        Method::DELETE,
+        "/api/admin/questions/q1",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/q1/revisions/2/rollback?reason=restore",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["revision"], 5);
+    let saved: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(saved.sub_questions[0].select_answer[2].value, "けれど");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/q1/revisions/9/rollback",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q1/revisions",
+        None,
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_bulk_import_reports_each_row() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    let mut fresh = sample_question("q2");
+    fresh.sub_questions[0].select_answer[0].value = "ために".to_string();
+    let mut copy = sample_question("q3");
+    copy.sub_questions[0].select_answer.reverse();
+    let mut malformed = sample_question("q4");
+    malformed.sub_questions[0].answer = "9".to_string();
+    let mut repeated = fresh.clone();
+    repeated.id = "q5".to_string();
+    let body = [
+        json!(fresh).to_string(),
+        json!(copy).to_string(),
+        json!(malformed).to_string(),
+        String::new(),
+        json!(repeated).to_string(),
+        "{broken".to_string(),
+    ]
+    .join("\n");
+
+    // dry_run では何も書き込まない
+    let res = send_text(
+        &app,
+        "/api/admin/questions/import?dry_run=true",
+        "application/x-ndjson",
+        &body,
+        &admin,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let data = &res.body["data"];
+    assert_eq!(
+        data["summary"],
+        json!({"total": 5, "accepted": 1, "duplicate": 2, "invalid": 2, "failed": 0})
+    );
+    let rows = data["rows"].as_array().unwrap();
+    assert_eq!(rows[0]["status"], "accepted");
+    assert_eq!(rows[1]["status"], "duplicate");
+    assert_eq!(rows[1]["duplicate_of"], "q1");
+    assert_eq!(rows[1]["sub_question_id"], 1);
+    assert_eq!(rows[2]["status"], "invalid");
+    assert!(rows[2]["reason"].as_str().unwrap().contains("正解キー不在"));
+    // 同じファイル内の先の行との重複
+    assert_eq!(rows[3]["line"], 5);
+    assert_eq!(rows[3]["duplicate_of"], "q2");
+    assert_eq!(rows[4]["line"], 6);
+    assert_eq!(rows[4]["status"], "invalid");
+    assert_eq!(data["committed"], 0);
+    assert!(
+        db.read::<Question>("questions", "q2")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let res = send_text(
+        &app,
+        "/api/admin/questions/import",
+        "application/x-ndjson",
+        &body,
+        &admin,
+    )
+    .await;
+    assert_eq!(res.body["data"]["committed"], 1);
+    let imported = db
+        .read::<Question>("questions", "q2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(imported.status, QuestionStatus::PendingReview);
+    // 以降の重複チェックでキーから引けるよう索引を付けて保存する
+    assert_eq!(imported.dedup_keys, question_dedup_keys(&imported));
+    assert!(!imported.dedup_keys.is_empty());
+
+    // 取り込んだ問題は作成の版として履歴に残る
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q2/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["action"], "create");
+
+    // CSV は同じ id の連続する行を1問にまとめる
+    let csv = "id,level_id,category_id,sentence,sub_question_id,sub_sentence,option_1,option_2,option_3,option_4,answer\n\
+        q6,4,8,問題,1,雨（　　）降る,が,を,に,で,1\n\
+        q6,4,8,問題,2,本（　　）読む,が,を,に,で,2\n\
+        q2,3,8,問題,1,文,あ,い,う,え,1\n";
+    let res = send_text(&app, "/api/admin/questions/import", "text/csv", csv, &admin).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let rows = res.body["data"]["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["status"], "accepted");
+    assert_eq!(rows[1]["line"], 4);
+    assert_eq!(rows[1]["status"], "duplicate");
+    assert_eq!(rows[1]["reason"], "問題IDが既に存在します");
+    let saved: Question = db.read("questions", "q6").await.unwrap().unwrap();
+    assert_eq!(saved.sub_questions.len(), 2);
+    assert_eq!(saved.level_name, "N4");
+
+    let res = send_text(
+        &app,
+        "/api/admin/questions/import",
+        "text/csv",
+        csv,
+        &user_token("u1"),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_bulk_import_accepts_id_after_rejected_row() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    // 不正な行の後に同じ ID で修正した行を置く
+    let mut malformed = sample_question("q2");
+    malformed.sub_questions[0].select_answer[0].value = "ために".to_string();
+    malformed.sub_questions[0].answer = "9".to_string();
+    let mut fixed = malformed.clone();
+    fixed.sub_questions[0].answer = "1".to_string();
+    // 既存の問題と重複した行の後も同じ
+    let mut copy = sample_question("q3");
+    copy.sub_questions[0].select_answer.reverse();
+    let mut rewritten = copy.clone();
+    rewritten.sub_questions[0].select_answer[0].value = "ように".to_string();
+    rewritten.sub_questions[0].select_answer[1].value = "ほど".to_string();
+    let body = [&malformed, &fixed, &copy, &rewritten, &fixed]
+        .map(|q| json!(q).to_string())
+        .join("\n");
+
+    let res = send_text(
+        &app,
+        "/api/admin/questions/import",
+        "application/x-ndjson",
+        &body,
+        &admin,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let rows = res.body["data"]["rows"].as_array().unwrap();
+    let statuses: Vec<&str> = rows.iter().map(|r| r["status"].as_str().unwrap()).collect();
+    assert_eq!(
+        statuses,
+        ["invalid", "accepted", "duplicate", "accepted", "duplicate"]
+    );
+    // 受理した行の ID は以降の行では使えない
+    assert_eq!(rows[4]["reason"], "問題IDが既に存在します");
+    assert_eq!(res.body["data"]["committed"], 2);
+    let saved: Question = db.read("questions", "q2").await.unwrap().unwrap();
+    assert_eq!(saved.sub_questions[0].answer, "1");
+}
+
+#[tokio::test]
+async fn test_export_formats_filter_by_level_and_category() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let mut other_level = sample_question("q2");
+    other_level.level_id = 4;
+    db.create("questions", "q2", other_level).await.unwrap();
+    let admin = admin_token();
+
+    let export = |format: &str| {
+        format!(
+            "/api/admin/questions/export?format={}&level_id=3&category_id=8",
+            format
+        )
+    };
+
+    let res = send(&app, Method::GET, &export("jsonl"), None, Some(&admin)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(
+        res.headers[header::CONTENT_DISPOSITION]
+            .to_str()
+            .unwrap()
+            .contains("questions.jsonl")
+    );
+    let text = String::from_utf8(res.raw).unwrap();
+    let lines: Vec<Question> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].id, "q1");
+    assert_eq!(lines[0].sub_questions[0].answer, "1");
+
+    let res = send(&app, Method::GET, &export("anki_csv"), None, Some(&admin)).await;
+    let text = String::from_utf8(res.raw).unwrap();
+    assert!(text.starts_with("#separator:Comma\n#html:true\n#tags column:3\n"));
+    assert!(text.contains("JLPT::N3"));
+    assert_eq!(text.lines().count(), 4);
+
+    let res = send(&app, Method::GET, &export("gift"), None, Some(&admin)).await;
+    let text = String::from_utf8(res.raw).unwrap();
+    assert!(text.contains("::q1_1::"));
+    assert!(text.contains("\t=ので\n"));
+
+    let res = send(&app, Method::GET, &export("qti"), None, Some(&admin)).await;
+    assert_eq!(res.headers[header::CONTENT_TYPE], "application/zip");
+    assert!(res.raw.starts_with(b"PK"));
+
+    let res = send(&app, Method::GET, &export("pdf"), None, Some(&admin)).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = send(
+        &app,
+        Method::GET,
+        &export("jsonl"),
+        None,
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_mistake_export_for_anki() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    seed_question(&db, "gone").await;
+    let token = user_token("u1");
+
+    // 同じ小問を2回間違えてもカードは1枚
+    answer_wrong(&app, "q1", &token).await;
+    answer_wrong(&app, "q1", &token).await;
+    answer_wrong(&app, "gone", &token).await;
+    db.delete("questions", "gone").await.unwrap();
+    answer_wrong(&app, "q1", &user_token("u2")).await;
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes/export?format=anki_csv",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let text = String::from_utf8(res.raw).unwrap();
+    let cards: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(cards.len(), 1);
+    assert!(cards[0].contains("正解: 1. ので<br>あなたの解答: 3. けど"));
+    assert!(cards[0].ends_with("JLPT::N3 文法"));
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes/export?format=apkg",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(
+        res.headers[header::CONTENT_DISPOSITION]
+            .to_str()
+            .unwrap()
+            .contains("mistakes.apkg")
+    );
+    assert!(res.raw.starts_with(b"PK"));
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/users/me/mistakes/export",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+/// Gemini の `generateContent` を真似るモックサーバーを起動し、`.../v1beta` の URL を返す
+async fn mock_gemini(output: Value) -> String {
+    mock_gemini_recording(output).await.0
+}
+
+/// [`mock_gemini`] と同じモックサーバーを起動し、受け取ったプロンプトの記録も返す。
+/// API キーは `x-goog-api-key` ヘッダでだけ受け付ける (クエリに載せたら拒否する)
+async fn mock_gemini_recording(output: Value) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+    use axum::{Json, http::Uri, response::IntoResponse, routing::post};
+
+    let text = output.to_string();
+    let prompts = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = prompts.clone();
+    let mock = Router::new().route(
+        "/v1beta/models/{*rest}",
+        post(
+            move |headers: HeaderMap, uri: Uri, Json(request): Json<Value>| async move {
+                if headers
+                    .get("x-goog-api-key")
+                    .is_none_or(|k| k != "test-key")
+                    || uri.query().is_some()
+                {
+                    return StatusCode::UNAUTHORIZED.into_response();
+                }
+                let prompt = request["contents"][0]["parts"][0]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                recorded.lock().unwrap().push(prompt);
+                Json(json!({
+                    "candidates": [{
+                        "content": {"role": "model", "parts": [{"text": text}]},
+                        "finishReason": "STOP",
+                        "index": 0,
+                    }]
+                }))
+                .into_response()
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock).await.unwrap() });
+    (format!("http://{}/v1beta", addr), prompts)
+}
+
+fn generated_question(options: [&str; 4], answer: &str) -> Value {
+    json!({
+        "sentence": "次の文の（　　）に入れるのに最もよいものを選びなさい。",
+        "sub_questions": [{
+            "id": 1,
+            "sentence": "駅に着いた（　　）、電車はもう出ていた。",
+            "select_answer": options
+                .iter()
+                .enumerate()
+                .map(|(i, v)| json!({"key": (i + 1).to_string(), "value": v}))
+                .collect::<Vec<_>>(),
+            "answer": answer,
+            "explanation": "逆接の「ときには」が入る。",
+        }],
+    })
+}
+
+#[tokio::test]
+async fn test_generation_job_fills_pending_queue() {
+    init_env();
+    let output = json!([
+        generated_question(["ときには", "ために", "ように", "ほど"], "1"),
+        // 既存の問題と同じ選択肢
+        generated_question(["ので", "のに", "けど", "から"], "1"),
+        // 正解の key が選択肢に無い
+        generated_question(["あ", "い", "う", "え"], "9"),
+        {"sub_questions": 1},
+    ]);
+    let base_url = mock_gemini(output).await;
+    let db = Arc::new(Database::memory());
+    let llm = GeminiClient::new("test-key", "gemini-test", Some(&base_url));
+    let app = build_app(
+        db.clone(),
+        AppConfig::new("http://localhost:3000").with_llm(Arc::new(llm)),
+    );
+    db.create(
+        "categories",
+        "c8",
+        CatValue {
+            level_id: 3,
+            id: 8,
+            name: "文法".to_string(),
+            reten: None,
+        },
+    )
+    .await
+    .unwrap();
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/generation-jobs",
+        Some(json!({"level_id": 3, "category_id": 99})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/generation-jobs",
+        Some(json!({"level_id": 3, "category_id": 8, "count": 4})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::ACCEPTED);
+    assert_eq!(res.body["data"]["status"], "running");
+    let job_uri = format!(
+        "/api/admin/generation-jobs/{}",
+        res.body["data"]["id"].as_str().unwrap()
+    );
+
+    let mut job = Value::Null;
+    for _ in 0..100 {
+        job = send(&app, Method::GET, &job_uri, None, Some(&admin))
+            .await
+            .body["data"]
+            .clone();
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "succeeded", "{}", job);
+    assert_eq!(job["model"], "gemini-test");
+    assert_eq!(job["pending_ids"].as_array().unwrap().len(), 1);
+    let rejected: Vec<u64> = job["rejected"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(rejected, vec![1, 2, 3]);
+    assert!(
+        job["rejected"][0]["reason"]
+            .as_str()
+            .unwrap()
+            .contains("q1")
+    );
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/review-queue?level_id=3",
+        None,
+        Some(&admin),
+    )
+    .await;
+    let pending = res.body["data"].as_array().unwrap();
+    assert_eq!(pending.len(), 1);
+    let pending_id = pending[0]["id"].as_str().unwrap().to_string();
+    assert_eq!(pending_id, job["pending_ids"][0]);
+    assert_eq!(pending[0]["status"], "pending_review");
+    assert_eq!(pending[0]["category_name"], "文法");
+    assert_eq!(pending[0]["generated_by"], "gemini-test");
+
+    // 承認するまで学習者には見えない
+    let question_uri = format!("/api/questions/{}", pending_id);
+    let res = send(&app, Method::GET, &question_uri, None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = send(
+        &app,
+        Method::POST,
+        &format!("/api/admin/questions/{}/approve", pending_id),
+        Some(json!({})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(&app, Method::GET, &question_uri, None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/review-queue",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 0);
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("/api/admin/questions/{}/revisions", pending_id),
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["action"], "update");
+    assert_eq!(res.body["data"][1]["action"], "create");
+    assert_eq!(res.body["data"][1]["editor"], "admin");
+}
+
+#[tokio::test]
+async fn test_generation_requires_llm() {
+    let (app, _db) = setup();
+    let admin = admin_token();
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/generation-jobs",
+        Some(json!({"level_id": 3, "category_id": 8})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/prompt-templates/default",
+        Some(json!({"body": "{level} の {category} を {count} 問"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/prompt-templates",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["templates"][0]["updated_by"], "admin");
+}
+
+#[tokio::test]
+async fn test_generation_examples_skip_unpublished() {
+    init_env();
+    let (base_url, prompts) = mock_gemini_recording(json!([generated_question(
+        ["ときには", "ために", "ように", "ほど"],
+        "1"
+    )]))
+    .await;
+    let db = Arc::new(Database::memory());
+    let llm = GeminiClient::new("test-key", "gemini-test", Some(&base_url));
+    let app = build_app(
+        db.clone(),
+        AppConfig::new("http://localhost:3000").with_llm(Arc::new(llm)),
+    );
+    db.create(
+        "categories",
+        "c8",
+        CatValue {
+            level_id: 3,
+            id: 8,
+            name: "文法".to_string(),
+            reten: None,
+        },
+    )
+    .await
+    .unwrap();
+    // ID 順で公開中の問題より前に、1回に読む件数を超える下書きを置く
+    for i in 0..25 {
+        let id = format!("d{:02}", i);
+        let mut question = sample_question(&id);
+        question.status = QuestionStatus::Draft;
+        question.sub_questions[0].sentence = Some(format!("下書き{}（　　）。", i));
+        db.create("questions", &id, question).await.unwrap();
+    }
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/generation-jobs",
+        Some(json!({"level_id": 3, "category_id": 8, "count": 1})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::ACCEPTED);
+    let job_uri = format!(
+        "/api/admin/generation-jobs/{}",
+        res.body["data"]["id"].as_str().unwrap()
+    );
+    let mut job = Value::Null;
+    for _ in 0..100 {
+        job = send(&app, Method::GET, &job_uri, None, Some(&admin))
+            .await
+            .body["data"]
+            .clone();
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "succeeded", "{}", job);
+
+    let prompts = prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains(r#""id":"q1""#), "{}", prompts[0]);
+    assert!(!prompts[0].contains("下書き"));
+}
+
+#[tokio::test]
+async fn test_draft_explanations_fills_missing_only() {
+    init_env();
+    let output = json!([{
+        "sub_question_id": 1,
+        "explanation": "上書きしてはいけない",
+        "option_explanations": {
+            "1": "理由を表す。",
+            "2": "逆接なので合わない。",
+            "9": "存在しない選択肢",
+        },
+    }, {
+        "sub_question_id": 5,
+        "explanation": "存在しない小問",
+    }]);
+    let base_url = mock_gemini(output).await;
+    let db = Arc::new(Database::memory());
+    let llm = GeminiClient::new("test-key", "gemini-test", Some(&base_url));
+    let app = build_app(
+        db.clone(),
+        AppConfig::new("http://localhost:3000").with_llm(Arc::new(llm)),
+    );
+    seed_question(&db, "q1").await;
+    let admin = admin_token();
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/missing/explanations/draft",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/q1/explanations/draft",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let data = &res.body["data"];
+    assert_eq!(data["model"], "gemini-test");
+    assert_eq!(data["drafted"], json!([1]));
+    let sub = &data["proposed"]["sub_questions"][0];
+    assert_eq!(sub["explanation"], "理由を表す「ので」が入る。");
+    assert_eq!(
+        sub["option_explanations"],
+        json!({"1": "理由を表す。", "2": "逆接なので合わない。"})
+    );
+    assert!(!data["changes"].as_array().unwrap().is_empty());
+
+    // 下書きは保存されない
+    let stored = db
+        .read::<Question>("questions", "q1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.sub_questions[0].option_explanations.is_empty());
+
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/questions/q1",
+        Some(data["proposed"].clone()),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "2"})),
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["is_correct"], false);
+    assert_eq!(
+        res.body["data"]["explanation"],
+        "理由を表す「ので」が入る。"
+    );
+    assert_eq!(
+        res.body["data"]["option_explanations"]["2"],
+        "逆接なので合わない。"
+    );
+
+    // LLM が設定されていなければ 400
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/q1/explanations/draft",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_create_question_status() {
+    let (app, _db) = setup();
+    let admin = admin_token();
+    let create = |id: &str, option: &str, status: Option<Value>| {
+        let mut body = json!(sample_question(id));
+        body["sub_questions"][0]["select_answer"][0]["value"] = json!(option);
+        match status {
+            Some(status) => body["status"] = status,
+            None => {
+                body.as_object_mut().unwrap().remove("status");
+            }
+        }
+        send(
+            &app,
+            Method::POST,
+            "/api/admin/questions",
+            Some(body),
+            Some(&admin),
+        )
+    };
+
+    // 省略時と null は下書き、指定した状態はそのまま使う
+    let res = create("q1", "ために", None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["status"], "draft");
+    let res = create("q2", "ように", Some(json!("published"))).await;
+    assert_eq!(res.body["data"]["status"], "published");
+    let res = create("q3", "ほど", Some(Value::Null)).await;
+    assert_eq!(res.body["data"]["status"], "draft");
+    let res = create("q4", "ばかり", Some(json!("pending_review"))).await;
+    assert_eq!(res.body["data"]["status"], "pending_review");
+
+    let res = create("q5", "だけ", Some(json!("retired"))).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = create("q6", "しか", Some(json!("archived"))).await;
+    assert!(res.status.is_client_error());
+}
+
+#[tokio::test]
+async fn test_question_lifecycle() {
+    let (app, db) = setup();
+    // status の無い既存の問題は公開済みとして扱う
+    seed_question(&db, "legacy").await;
+    let admin = admin_token();
+    let token = user_token("u1");
+    let list_uri = "/api/level/3/categories/8/questions";
+
+    let mut draft = json!(sample_question("q2"));
+    draft["status"] = json!("draft");
+    draft["sub_questions"][0]["select_answer"][0]["value"] = json!("ために");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(draft),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["status"], "draft");
+
+    let ids = |res: &TestResponse| -> Vec<String> {
+        res.body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|q| q["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let res = send(&app, Method::GET, list_uri, None, Some(&token)).await;
+    assert_eq!(ids(&res), vec!["legacy"]);
+    let res = send(&app, Method::GET, list_uri, None, Some(&admin)).await;
+    assert_eq!(ids(&res).len(), 2);
+
+    let action = |name: &str| format!("/api/admin/questions/q2/{}", name);
+    // 下書きはいきなり承認できない
+    let res = send(
+        &app,
+        Method::POST,
+        &action("approve"),
+        Some(json!({})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    let res = send(&app, Method::POST, &action("submit"), None, Some(&admin)).await;
+    assert_eq!(res.body["data"]["status"], "pending_review");
+    let res = send(
+        &app,
+        Method::POST,
+        &action("reject"),
+        Some(json!({})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = send(
+        &app,
+        Method::POST,
+        &action("reject"),
+        Some(json!({"comment": "解説が不足"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["status"], "draft");
+    assert_eq!(res.body["data"]["review"]["decision"], "rejected");
+    assert_eq!(res.body["data"]["review"]["comment"], "解説が不足");
+
+    // 内容の編集では状態は変わらない
+    let res = send(
+        &app,
+        Method::PATCH,
+        "/api/admin/questions/q2",
+        Some(json!({"status": "published", "sentence": "修正済み"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["status"], "draft");
+
+    send(&app, Method::POST, &action("submit"), None, Some(&admin)).await;
+    let res = send(
+        &app,
+        Method::POST,
+        &action("approve"),
+        Some(json!({"comment": "OK"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["status"], "published");
+    assert_eq!(res.body["data"]["review"]["reviewer"], "admin");
+    assert_eq!(res.body["data"]["review"]["decision"], "approved");
+    let res = send(&app, Method::GET, list_uri, None, Some(&token)).await;
+    assert_eq!(ids(&res).len(), 2);
+
+    let res = send(&app, Method::POST, &action("retire"), None, Some(&admin)).await;
+    assert_eq!(res.body["data"]["status"], "retired");
+    let res = send(&app, Method::GET, list_uri, None, Some(&token)).await;
+    assert_eq!(ids(&res), vec!["legacy"]);
+    let res = send(&app, Method::GET, "/api/questions/q2", None, Some(&token)).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q2/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["changes"][0]["path"], "status");
+    assert_eq!(res.body["data"][0]["changes"][0]["after"], "retired");
+}
+
+#[tokio::test]
+async fn test_hint_marks_answer_assisted() {
+    let (app, db) = setup();
+    let mut question = sample_question("q1");
+    question.sub_questions[0].hints = vec![Hint {
+        kind: HintKind::GrammarPoint,
+        text: "理由を表す接続助詞".to_string(),
+    }];
+    db.create("questions", "q1", question).await.unwrap();
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1/sub/1/hint",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1/sub/9/hint",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1/sub/1/hint",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["level"], 1);
+    assert_eq!(res.body["data"]["max_level"], 2);
+    assert_eq!(res.body["data"]["hints"][0]["kind"], "grammar_point");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1/sub/1/hint?level=5",
+        None,
+        Some(&token),
+    )
+    .await;
+    let hints = res.body["data"]["hints"].as_array().unwrap();
+    assert_eq!(hints.len(), 2);
+    assert_eq!(hints[1]["kind"], "eliminate");
+    assert_ne!(hints[1]["key"], "1");
+
+    let answer = json!({"question_id": "q1", "sub_question_id": 1, "selected_answer": "1"});
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer.clone()),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"]["is_correct"], true);
+    assert_eq!(res.body["data"]["assisted"], true);
+    // ヒントありの回答は Elo の推定に使わない
+    assert!(
+        db.read::<ItemRating>("item_ratings", &ItemRating::doc_id("q1", 1))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // 記録は1回の回答で消費する
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(answer),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"]["assisted"], false);
+
+    let res = send(&app, Method::GET, "/api/users/me/stats", None, Some(&token)).await;
+    let stats = &res.body["data"];
+    assert_eq!(stats["total_answers"], 2);
+    assert_eq!(stats["total_assisted"], 1);
+    assert_eq!(stats["levels"][0]["categories"][0]["assisted"], 1);
+
+    // 選択肢の除外は登録できない
+    let mut question = sample_question("q1");
+    question.sub_questions[0].hints = vec![Hint {
+        kind: HintKind::Eliminate,
+        text: "2 ではない".to_string(),
+    }];
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/questions/q1",
+        Some(json!(question)),
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+/// 管理者が作成した問題をレビューに回して承認する
+async fn publish(app: &Router, id: &str, admin: &str) {
+    let res = send(
+        app,
+        Method::POST,
+        &format!("/api/admin/questions/{}/submit", id),
+        None,
+        Some(admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let res = send(
+        app,
+        Method::POST,
+        &format!("/api/admin/questions/{}/approve", id),
+        Some(json!({})),
+        Some(admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["status"], "published", "{}", res.body);
+}
+
+#[tokio::test]
+async fn test_question_types_ordering_and_passage() {
+    let (app, _db) = setup();
+    let admin = admin_token();
+    let token = user_token("u1");
+    let options = |values: [&str; 4]| {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| json!({"key": (i + 1).to_string(), "value": v}))
+            .collect::<Vec<_>>()
+    };
+
+    let mut ordering = json!({
+        "level_id": 3,
+        "category_id": "9",
+        "category_name": "文の組み立て",
+        "sentence": "次の文の ★ に入る最もよいものを選びなさい。",
+        "prerequisites": null,
+        "question_type": "ordering",
+        "sub_questions": [{
+            "id": 1,
+            "sentence": "私は ＿＿ ＿＿ ★ ＿＿ 。",
+            "select_answer": options(["読んだ", "本を", "昨日", "ことがある"]),
+            "answer": "1",
+            "ordering": {"fragments": ["昨日", "本を", "読んだ", "ことがある"], "star_position": 2},
+        }],
+    });
+    // ★ の断片と正解が合わない
+    ordering["sub_questions"][0]["answer"] = json!("2");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(ordering.clone()),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    ordering["sub_questions"][0]["answer"] = json!("1");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(ordering),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    // 作成した問題は下書きになり、承認するまで学習者には見えない
+    assert_eq!(res.body["data"]["status"], "draft");
+    let id = res.body["data"]["id"].as_str().unwrap().to_string();
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("/api/questions/{}", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    publish(&app, &id, &admin).await;
+
+    // 学習者には ★ の位置だけを返し、断片の順は返さない
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("/api/questions/{}", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(res.body["data"]["question_type"], "ordering");
+    let sub = &res.body["data"]["sub_questions"][0];
+    assert_eq!(sub["star_position"], 2);
+    assert!(sub.get("ordering").is_none());
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": id, "sub_question_id": 1, "selected_order": ["3", "2", "1", "4"]})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["is_correct"], true);
+    assert_eq!(
+        res.body["data"]["correct_order"],
+        json!(["3", "2", "1", "4"])
+    );
+
+    // ★ は合っていても並び全体が違えば不正解
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": id, "sub_question_id": 1, "selected_order": ["2", "3", "1", "4"]})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.body["data"]["is_correct"], false);
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": id, "sub_question_id": 1, "selected_order": ["1", "1", "2", "3"]})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let mut passage = json!({
+        "level_id": 3,
+        "category_id": "12",
+        "category_name": "内容理解",
+        "sentence": "次の文章を読んで、質問に答えなさい。",
+        "prerequisites": null,
+        "question_type": "passage",
+        "sub_questions": [{
+            "id": 1,
+            "sentence": "筆者が一番言いたいことは何か。",
+            "select_answer": options(["早起き", "運動", "読書", "料理"]),
+            "answer": "3",
+        }],
+    });
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(passage.clone()),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    passage["passage"] = json!("毎朝、本を読む時間を作っている。");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(passage),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let id = res.body["data"]["id"].as_str().unwrap().to_string();
+    publish(&app, &id, &admin).await;
+    let res = send(
+        &app,
+        Method::GET,
+        &format!("/api/questions/{}", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"]["passage"],
+        "毎朝、本を読む時間を作っている。"
+    );
+}
+
+#[tokio::test]
+async fn test_rich_text_migration_and_rendering() {
+    let (app, db) = setup();
+    let admin = admin_token();
+
+    // 旧来の表記の空欄と漢字だけのルビ
+    let mut legacy = sample_question("q1");
+    legacy.sub_questions[0].sentence =
+        Some("雨が降った（）、試合《しあい》は中止になった。".to_string());
+    db.create("questions", "q1", legacy).await.unwrap();
+    seed_question(&db, "q2").await;
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/normalize-markup?dry_run=true",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["changed"], 1);
+    assert_eq!(res.body["data"]["committed"], 0);
+    assert_eq!(res.body["data"]["question_ids"], json!(["q1"]));
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/normalize-markup?reason=migrate",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["committed"], 1);
+    let stored = db
+        .read::<Question>("questions", "q1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        stored.sub_questions[0].sentence.as_deref(),
+        Some("雨が降った（　　）、｜試合《しあい》は中止になった。")
+    );
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q1/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"].as_array().unwrap().len(), 2);
+
+    // 2回目は変わる問題が無い
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/normalize-markup",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"]["changed"], 0);
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1?render=html",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"]["sub_questions"][0]["sentence"],
+        "雨が降った<span class=\"blank\">（　　）</span>、<ruby>試合<rt>しあい</rt></ruby>は中止になった。"
+    );
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/level/3/categories/8/questions?render=plain",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"][0]["sub_questions"][0]["sentence"],
+        "雨が降った（　　）、試合は中止になった。"
+    );
+
+    // 閉じていない下線は作成できない
+    let mut question = json!(sample_question("q3"));
+    question["sub_questions"][0]["sentence"] = json!("<u>雨が降った（　　）、試合は中止になった。");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(question),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(
+        res.body["error"]
+            .as_str()
+            .unwrap()
+            .contains("下線が閉じられていません")
+    );
+}
+
+#[tokio::test]
+async fn test_furigana_query() {
+    let (app, db) = setup();
+    let mut question = sample_question("q1");
+    question.level_id = 5;
+    question.level_name = "N5".to_string();
+    db.create("questions", "q1", question).await.unwrap();
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1?furigana=all",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"]["sub_questions"][0]["sentence"],
+        "｜雨《あめ》が｜降《ふ》った（　　）、｜試合《しあい》は｜中止《ちゅうし》になった。"
+    );
+
+    // N5 の問題では N5 の一覧に無い漢字 (降・試・合・止) を含む語だけ
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/level/5/categories/8/questions?furigana=above_level&render=html",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"][0]["sub_questions"][0]["sentence"],
+        "雨が<ruby>降<rt>ふ</rt></ruby>った<span class=\"blank\">（　　）</span>、<ruby>試合<rt>しあい</rt></ruby>は<ruby>中止<rt>ちゅうし</rt></ruby>になった。"
+    );
+    assert!(res.body["data"][0].get("furigana_unresolved").is_none());
+
+    // 読みの分からない語は付けずに残し、一覧で返す
+    let mut question = sample_question("q2");
+    question.sub_questions[0].sentence = Some("薔薇が（　　）。".to_string());
+    db.create("questions", "q2", question).await.unwrap();
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q2?furigana=all",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(
+        res.body["data"]["sub_questions"][0]["sentence"],
+        "薔薇が（　　）。"
+    );
+    assert_eq!(res.body["data"]["furigana_unresolved"], json!(["薔薇"]));
+
+    // 既定では付けない。Admin にも付けない
+    let res = send(&app, Method::GET, "/api/questions/q1", None, None).await;
+    assert_eq!(
+        res.body["data"]["sub_questions"][0]["sentence"],
+        "雨が降った（　　）、試合は中止になった。"
+    );
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/questions/q1?furigana=all",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(
+        res.body["data"]["sub_questions"][0]["sentence"],
+        "雨が降った（　　）、試合は中止になった。"
+    );
+}
+
+fn monitor_request(uri: &str) -> Request<Body> {
+    // 品質監視は Authorization ヘッダで認証する
+    Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("x-forwarded-for", "203.0.113.1")
+        .header(header::AUTHORIZATION, format!("Bearer {}", admin_token()))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_monitor_reports_level_violations() {
+    let (app, db) = setup();
+    let mut question = sample_question("q1");
+    question.level_id = 5;
+    question.level_name = "N5".to_string();
+    db.create("questions", "q1", question).await.unwrap();
+
+    let res =
+        into_test_response(&app, monitor_request("/api/admin/monitor-quality?level=n5")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["summary"]["warnings"], 1);
+    assert_eq!(
+        res.body["data"]["summary"]["findings_by_rule"],
+        json!({"level_vocabulary": 1})
+    );
+    assert_eq!(res.body["data"]["summary"]["deleted"], 0);
+    let details = &res.body["data"]["levels"][0]["warning_details"][0];
+    assert_eq!(details["question_id"], "q1");
+    let finding = &details["findings"][0];
+    assert_eq!(finding["rule"], "level_vocabulary");
+    assert_eq!(finding["severity"], "warn");
+    assert_eq!(
+        finding["detail"]["kanji"],
+        json!(["次", "文", "最", "選", "降", "試", "合", "止"])
+    );
+    assert_eq!(
+        finding["detail"]["words"],
+        json!(["文", "最も", "選ぶ", "試合", "中止"])
+    );
+}
+
+#[tokio::test]
+async fn test_quality_rule_config_quarantines_and_rejects() {
+    let (app, db) = setup();
+    let admin = admin_token();
+    let mut question = sample_question("q1");
+    question.level_id = 5;
+    question.level_name = "N5".to_string();
+    db.create("questions", "q1", question).await.unwrap();
+
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/quality-rules/n5-vocabulary",
+        Some(json!({"rule": "no_such_rule"})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/quality-rules/n5-vocabulary",
+        Some(json!({"rule": "option_count", "params": {"count": "4"}})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    // N5 ではレベル外の漢字・語を隔離の対象にする
+    let res = send(
+        &app,
+        Method::PUT,
+        "/api/admin/quality-rules/n5-vocabulary",
+        Some(json!({"rule": "level_vocabulary", "severity": "quarantine", "level_ids": [5]})),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["updated_by"], "admin");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/quality-rules",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let rules = res.body["data"]["rules"].as_array().unwrap();
+    assert!(
+        rules
+            .iter()
+            .any(|r| r["id"] == "duplicate_option" && r["default_severity"] == "quarantine")
+    );
+    assert_eq!(res.body["data"]["configs"][0]["id"], "n5-vocabulary");
+
+    // 隔離の対象はレベル5だけで、作成時にも拒否される
+    let mut new_question = json!(sample_question(""));
+    new_question["level_id"] = json!(5);
+    new_question["sub_questions"][0]["select_answer"][3]["value"] = json!("ため");
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(new_question.clone()),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(
+        res.body["error"]
+            .as_str()
+            .unwrap()
+            .contains("レベル外の漢字"),
+        "{}",
+        res.body
+    );
+
+    let res = into_test_response(
+        &app,
+        monitor_request("/api/admin/monitor-quality?level=n5&execute=true"),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let summary = &res.body["data"]["summary"];
+    assert_eq!(summary["quarantine"], 1);
+    assert_eq!(summary["quarantined"], 1);
+    assert_eq!(summary["deleted"], 0);
+
+    let stored: Question = db.read("questions", "q1").await.unwrap().unwrap();
+    assert_eq!(stored.status, QuestionStatus::PendingReview);
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q1/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["editor"], "quality-monitor");
+    assert_eq!(
+        res.body["data"][0]["reason"],
+        "品質監視で隔離: level_vocabulary"
+    );
+
+    // 設定を消すと既定 (警告のみ) に戻る
+    let res = send(
+        &app,
+        Method::DELETE,
+        "/api/admin/quality-rules/n5-vocabulary",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(
+        &app,
+        Method::DELETE,
+        "/api/admin/quality-rules/n5-vocabulary",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions",
+        Some(new_question),
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
+
+#[tokio::test]
+async fn test_monitor_delete_records_revision_and_cascades() {
+    let (app, db) = setup();
+    let admin = admin_token();
+    let token = user_token("u1");
+    // 同じ小問を持つ問題は後の方を削除の対象にする
+    seed_question(&db, "q1").await;
+    seed_question(&db, "q2").await;
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": "q2", "sub_question_id": 1, "selected_answer": "2"})),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(
+        db.read::<ReviewCard>("review_cards", "u1_q2_1")
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    let res = into_test_response(
+        &app,
+        monitor_request("/api/admin/monitor-quality?level=n3&execute=true"),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["summary"]["deleted"], 1, "{}", res.body);
+    assert!(
+        db.read::<Question>("questions", "q2")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // 削除直前の内容が品質監視の版として残り、学習記録は連鎖削除される
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/questions/q2/revisions",
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    let revisions = res.body["data"].as_array().unwrap();
+    assert_eq!(revisions[0]["action"], "delete");
+    assert_eq!(revisions[0]["editor"], "quality-monitor");
+    assert_eq!(revisions[0]["reason"], "品質監視で削除");
+    let answers: Vec<Value> = db.read_all("user_answers").await.unwrap();
+    assert!(answers.iter().all(|a| a["question_id"] != "q2"));
+    assert!(
+        db.read::<ReviewCard>("review_cards", "u1_q2_1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // 削除した版から復元できる
+    let revision = revisions[0]["revision"].as_u64().unwrap();
+    let res = send(
+        &app,
+        Method::POST,
+        &format!("/api/admin/questions/q2/revisions/{}/rollback", revision),
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    db.delete("questions", "q1").await.unwrap();
+    let res = send(
+        &app,
+        Method::POST,
+        &format!("/api/admin/questions/q2/revisions/{}/rollback", revision),
+        None,
+        Some(&admin),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert!(
+        db.read::<Question>("questions", "q2")
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_monitor_reports_similar_across_categories_and_levels() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    let mut other_category = sample_question("q2");
+    other_category.category_id = Some("4".to_string());
+    other_category.sub_questions[0].sentence =
+        Some("雪が降った（　　）、試合は中止になった。".to_string());
+    other_category.sub_questions[0].answer = "2".to_string();
+    db.create("questions", "q2", other_category).await.unwrap();
+    let mut other_level = sample_question("q3");
+    other_level.level_id = 4;
+    other_level.level_name = "N4".to_string();
+    other_level.sub_questions[0].sentence =
+        Some("雨が降った（　　）、試合は延期になった。".to_string());
+    db.create("questions", "q3", other_level).await.unwrap();
+
+    let res = into_test_response(&app, monitor_request("/api/admin/monitor-quality")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let data = &res.body["data"];
+    assert_eq!(data["summary"]["duplicates_similar"], 2);
+    assert_eq!(data["summary"]["duplicates_similar_cross_level"], 1);
+    assert_eq!(data["summary"]["deleted"], 0);
+
+    let n3 = data["levels"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|lv| lv["level"] == "N3")
+        .unwrap();
+    assert_eq!(n3["duplicates_similar"], 1);
+    let similar = &n3["similar_details"][0];
+    assert_eq!(similar["scope"], "cross_category");
+    assert_eq!(similar["question_id_a"], "q1");
+    assert_eq!(similar["question_id_b"], "q2");
+    assert_eq!(similar["similarity"], "95%");
+
+    let cross = &data["cross_level_similar_details"][0];
+    assert_eq!(cross["scope"], "cross_level");
+    assert_eq!(cross["question_id_a"], "q1");
+    assert_eq!(cross["level_b"], "N4");
+    assert_eq!(cross["question_id_b"], "q3");
+}
+
+#[tokio::test]
+async fn test_report_duplicate_is_conflict() {
+    let (app, _db) = setup();
+    let token = user_token("u1");
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/questions/q1/report",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/questions/q1/report",
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.body["code"], "conflict");
+    assert_eq!(res.body["error"], "既に報告済みです");
+
+    let res = send(
+        &app,
+        Method::GET,
+        "/api/admin/reports",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.body["data"][0]["question_id"], "q1");
+    assert_eq!(res.body["data"][0]["report_count"], 1);
+}
+
+/// 連鎖削除はバックグラウンドで走るため、完了するまで待つ
+/// 問題に紐づく user_answers と review_cards の連鎖削除を待つ
+async fn wait_for_no_user_answers(db: &Database, question_id: &str) -> bool {
+    for _ in 0..50 {
+        let query = StoreQuery::new().eq("question_id", question_id);
+        let answers = db
+            .query::<UserAnswer>("user_answers", query.clone())
+            .await
+            .unwrap();
+        let cards = db.query::<ReviewCard>("review_cards", query).await.unwrap();
+        if answers.is_empty() && cards.is_empty() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
+async fn answer_wrong(app: &Router, question_id: &str, token: &str) {
+    let res = send(
+        app,
+        Method::POST,
+        "/api/answers",
+        Some(json!({"question_id": question_id, "sub_question_id": 1, "selected_answer": "3"})),
+        Some(token),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_delete_cascades_user_answers() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    seed_question(&db, "q2").await;
+    let token = user_token("u1");
+    answer_wrong(&app, "q1", &token).await;
+    answer_wrong(&app, "q2", &token).await;
+
+    let res = send(
+        &app,
+        Method::DELETE,
+        "/api/admin/questions/q1",
+        None,
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["deleted"], "q1");
+
+    let res = send(&app, Method::GET, "/api/questions/q1", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert!(wait_for_no_user_answers(&db, "q1").await);
+
+    // 削除していない問題の回答は残る
+    let remaining = db
+        .query::<UserAnswer>("user_answers", StoreQuery::new().eq("question_id", "q2"))
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    let cards = db
+        .query::<ReviewCard>("review_cards", StoreQuery::new().eq("question_id", "q2"))
+        .await
+        .unwrap();
+    assert_eq!(cards.len(), 1);
+}
+
+#[tokio::test]
+async fn test_admin_bulk_delete_cascades_user_answers() {
+    let (app, db) = setup();
+    seed_question(&db, "q1").await;
+    seed_question(&db, "q2").await;
+    let token = user_token("u1");
+    answer_wrong(&app, "q1", &token).await;
+    answer_wrong(&app, "q2", &token).await;
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/bulk-delete",
+        Some(json!({"ids": ["q1", "q2"]})),
+        Some(&admin_token()),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["data"]["deleted"], 2);
+
+    assert!(wait_for_no_user_answers(&db, "q1").await);
+    assert!(wait_for_no_user_answers(&db, "q2").await);
+
+    let res = send(
+        &app,
+        Method::POST,
+        "/api/admin/questions/bulk-delete",
+        Some(json!({"ids": ["q1"]})),
+        Some(&user_token("u1")),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}